@@ -0,0 +1,136 @@
+//! Comparison of the abi of two contracts, used to check that an upgrade is safe
+
+use std::collections::BTreeSet;
+
+use crate::utils::ast::Contract;
+
+/// The kind of abi item that changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AbiItemKind {
+    /// An abi function
+    Function,
+    /// An abi event
+    Event,
+    /// An abi error
+    Error,
+}
+
+/// A change to the abi between two versions of a contract
+///
+/// Items are identified by their canonical signature, e.g. `transfer(address,uint256)`. An item
+/// that was renamed is reported as removed and added.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AbiChange {
+    /// The item only exists in the new contract
+    Added {
+        /// The kind of item
+        kind: AbiItemKind,
+        /// The item's canonical signature
+        signature: String,
+    },
+    /// The item only exists in the old contract
+    Removed {
+        /// The kind of item
+        kind: AbiItemKind,
+        /// The item's canonical signature
+        signature: String,
+    },
+    /// An item with the same name exists in both contracts, but its signature changed, and with
+    /// it the selector or event topic
+    SignatureChanged {
+        /// The kind of item
+        kind: AbiItemKind,
+        /// The item's name
+        name: String,
+        /// The signature in the old contract
+        old: String,
+        /// The signature in the new contract
+        new: String,
+    },
+}
+
+/// Abi Diff
+///
+/// Reports every abi breaking change from `old` to `new`: functions, events and errors that were
+/// added, removed, or had their signature changed.
+pub fn abi_diff(old: &Contract, new: &Contract) -> Vec<AbiChange> {
+    let mut changes = Vec::new();
+    for kind in [
+        AbiItemKind::Function,
+        AbiItemKind::Event,
+        AbiItemKind::Error,
+    ] {
+        changes.extend(diff_signatures(
+            kind,
+            signatures(old, kind),
+            signatures(new, kind),
+        ));
+    }
+    changes
+}
+
+fn signatures(contract: &Contract, kind: AbiItemKind) -> BTreeSet<String> {
+    match kind {
+        AbiItemKind::Function => contract
+            .functions
+            .iter()
+            .map(|f| f.canonical_signature())
+            .collect(),
+        AbiItemKind::Event => contract
+            .events
+            .iter()
+            .map(|e| e.canonical_signature())
+            .collect(),
+        AbiItemKind::Error => contract
+            .errors
+            .iter()
+            .map(|e| e.canonical_signature())
+            .collect(),
+    }
+}
+
+fn diff_signatures(
+    kind: AbiItemKind,
+    old: BTreeSet<String>,
+    new: BTreeSet<String>,
+) -> Vec<AbiChange> {
+    let mut removed: Vec<String> = old.difference(&new).cloned().collect();
+    let mut added: Vec<String> = new.difference(&old).cloned().collect();
+    let mut changes = Vec::new();
+
+    // Pair up removed and added signatures that share a name
+    removed.retain(|old_sig| {
+        let name = signature_name(old_sig);
+        match added
+            .iter()
+            .position(|new_sig| signature_name(new_sig) == name)
+        {
+            Some(index) => {
+                changes.push(AbiChange::SignatureChanged {
+                    kind,
+                    name: name.to_string(),
+                    old: old_sig.clone(),
+                    new: added.remove(index),
+                });
+                false
+            }
+            None => true,
+        }
+    });
+
+    changes.extend(
+        removed
+            .into_iter()
+            .map(|signature| AbiChange::Removed { kind, signature }),
+    );
+    changes.extend(
+        added
+            .into_iter()
+            .map(|signature| AbiChange::Added { kind, signature }),
+    );
+    changes
+}
+
+fn signature_name(signature: &str) -> &str {
+    signature.split('(').next().unwrap_or(signature)
+}
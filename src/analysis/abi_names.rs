@@ -0,0 +1,45 @@
+//! Detection of abi declarations that share a name
+
+use std::collections::HashMap;
+
+use crate::{
+    error::Diagnostic,
+    parser::Ast,
+    span::{Span, Spanned},
+};
+
+/// Check Abi Names
+///
+/// Builtins such as `__FUNC_SIG`, `__EVENT_HASH` and `__ERROR` look declarations up by name, so
+/// reports functions, events and errors that reuse the name of an earlier abi declaration.
+/// Functions may share a name with each other, as overloads are distinguished by their
+/// parameters.
+pub fn check_abi_names(ast: &[Spanned<Ast>]) -> Vec<Diagnostic> {
+    let mut seen: HashMap<&String, (&'static str, &Span)> = HashMap::new();
+
+    let mut diagnostics = Vec::new();
+    for (item, span) in ast {
+        let (kind, name) = match item {
+            Ast::AbiFunction(function) => ("function", &function.name),
+            Ast::AbiEvent(event) => ("event", &event.name),
+            Ast::AbiError(error) => ("error", &error.name),
+            _ => continue,
+        };
+
+        match seen.get(name) {
+            Some(("function", _)) if kind == "function" => {}
+            Some((first_kind, first)) => diagnostics.push(
+                Diagnostic::warning(
+                    format!("{kind} `{name}` has the same name as {first_kind} `{name}`"),
+                    span.clone(),
+                )
+                .with_related((*first).clone()),
+            ),
+            None => {
+                seen.insert(name, (kind, span));
+            }
+        }
+    }
+
+    diagnostics
+}
@@ -0,0 +1,63 @@
+//! Validation of builtin function arguments
+
+use crate::{
+    error::Diagnostic,
+    parser::{Arg, Ast, MacroBody},
+    span::{Span, Spanned},
+};
+
+use super::symbols::SymbolTable;
+
+/// Check Builtins
+///
+/// Validates the arguments of builtin invocations inside of macro bodies against the symbol table.
+/// Only the names are checked, numeric resolution is left to codegen.
+pub fn check_builtins(ast: &[Spanned<Ast>], symbols: &SymbolTable) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for (item, _) in ast {
+        let Ast::MacroDefinition { statements, .. } = item else {
+            continue;
+        };
+
+        for (statement, span) in statements {
+            if let MacroBody::BuiltinInvocation { name, args } = statement {
+                if name == "codesize" {
+                    diagnostics.extend(check_codesize(args, span, symbols));
+                }
+            }
+        }
+    }
+
+    diagnostics
+}
+
+/// `__codesize(MACRO)` must be passed exactly one defined macro
+fn check_codesize(args: &[Spanned<Arg>], span: &Span, symbols: &SymbolTable) -> Vec<Diagnostic> {
+    let [(arg, arg_span)] = args else {
+        return vec![Diagnostic::error(
+            format!("__codesize expects 1 argument, found {}", args.len()),
+            span.clone(),
+        )];
+    };
+
+    let Arg::Valid(name) = arg else {
+        return vec![Diagnostic::error(
+            "__codesize expects a macro name",
+            arg_span.clone(),
+        )];
+    };
+
+    if symbols.find_macro(name).is_some() {
+        return vec![];
+    }
+
+    let message = match symbols.lookup(name).first() {
+        Some(symbol) => format!(
+            "__codesize expects a macro, but `{name}` is a {}",
+            symbol.kind.describe()
+        ),
+        None => format!("__codesize references undefined macro `{name}`"),
+    };
+    vec![Diagnostic::error(message, arg_span.clone())]
+}
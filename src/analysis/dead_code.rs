@@ -0,0 +1,63 @@
+//! Detection of unreachable statements in macro bodies
+
+use crate::{
+    error::Diagnostic,
+    parser::{Ast, MacroBody},
+    span::{Span, Spanned},
+    utils::opcodes::Opcode,
+};
+
+/// Check Dead Code
+///
+/// Execution never falls through an unconditional terminator (`stop`, `return`, `revert`,
+/// `invalid`, `selfdestruct` or `jump`), so the statements following one can only be reached by
+/// jumping to a label between them. Reports each run of statements after a terminator that is not
+/// preceded by a jump destination.
+pub fn check_dead_code(ast: &[Spanned<Ast>]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for (item, _) in ast {
+        let Ast::MacroDefinition { statements, .. } = item else {
+            continue;
+        };
+
+        // The terminator that made the following statements unreachable, and their span so far
+        let mut dead: Option<(Opcode, Option<Span>)> = None;
+        for (statement, span) in statements {
+            match statement {
+                MacroBody::JumpLabelDest(_) => {
+                    diagnostics.extend(report(dead.take()));
+                    continue;
+                }
+                // Already reported by the parser
+                MacroBody::UnexpectedToken(_) => continue,
+                _ => {}
+            }
+
+            match &mut dead {
+                Some((_, run)) => {
+                    let start = run.as_ref().map_or(span.start, |run| run.start);
+                    *run = Some(start..span.end);
+                }
+                None => {
+                    if let MacroBody::Opcode(opcode) = statement {
+                        if opcode.is_halting() || *opcode == Opcode::Jump {
+                            dead = Some((*opcode, None));
+                        }
+                    }
+                }
+            }
+        }
+        diagnostics.extend(report(dead));
+    }
+
+    diagnostics
+}
+
+fn report(dead: Option<(Opcode, Option<Span>)>) -> Option<Diagnostic> {
+    let (terminator, run) = dead?;
+    Some(Diagnostic::warning(
+        format!("unreachable code after `{}`", terminator.mnemonic()),
+        run?,
+    ))
+}
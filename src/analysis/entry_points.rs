@@ -0,0 +1,56 @@
+//! Checks that a deployable contract defines its entry point macros
+
+use crate::{
+    error::{Diagnostic, Severity},
+    parser::Ast,
+    span::Spanned,
+};
+
+use super::symbols::SymbolTable;
+
+/// The macro that holds the runtime code of a contract
+pub const MAIN: &str = "MAIN";
+
+/// The macro that holds the deployment code of a contract
+pub const CONSTRUCTOR: &str = "CONSTRUCTOR";
+
+/// Check Entry Points
+///
+/// Reports a contract without a `MAIN` macro, an abi constructor without a `CONSTRUCTOR` macro, and
+/// a `CONSTRUCTOR` macro without an abi constructor. A missing `MAIN` has no definition to point
+/// at, so it points at the start of the file.
+pub fn check_entry_points(
+    ast: &[Spanned<Ast>],
+    symbols: &SymbolTable,
+    severity: Severity,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    if symbols.find_macro(MAIN).is_none() {
+        diagnostics.push(Diagnostic::new(
+            severity,
+            format!("contract has no `{MAIN}` macro"),
+            0..0,
+        ));
+    }
+
+    let abi_constructor = ast.iter().find_map(|(item, span)| match item {
+        Ast::AbiConstructor(_) => Some(span),
+        _ => None,
+    });
+    match (abi_constructor, symbols.find_macro(CONSTRUCTOR)) {
+        (Some(span), None) => diagnostics.push(Diagnostic::new(
+            severity,
+            format!("a constructor is declared, but there is no `{CONSTRUCTOR}` macro"),
+            span.clone(),
+        )),
+        (None, Some(symbol)) => diagnostics.push(Diagnostic::new(
+            severity,
+            format!("`{CONSTRUCTOR}` macro is defined, but no constructor is declared"),
+            symbol.span.clone(),
+        )),
+        _ => {}
+    }
+
+    diagnostics
+}
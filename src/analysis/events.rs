@@ -0,0 +1,38 @@
+//! Validation of abi event definitions
+
+use crate::{error::Diagnostic, parser::Ast, span::Spanned};
+
+/// The maximum number of indexed parameters of a non-anonymous event, the first of the four log
+/// topics is reserved for the event hash
+pub const MAX_INDEXED_PARAMS: usize = 3;
+
+/// Check Events
+///
+/// Reports non-anonymous events that declare more indexed parameters than there are topics
+/// available. The diagnostic points at the first parameter over the limit.
+pub fn check_events(ast: &[Spanned<Ast>]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for (item, _) in ast {
+        let Ast::AbiEvent(event) = item else {
+            continue;
+        };
+        if event.anonymous {
+            continue;
+        }
+
+        let indexed: Vec<_> = event.inputs.iter().filter(|(p, _)| p.indexed).collect();
+        if let Some((_, span)) = indexed.get(MAX_INDEXED_PARAMS) {
+            diagnostics.push(Diagnostic::error(
+                format!(
+                    "event `{}` has {} indexed parameters, but at most {MAX_INDEXED_PARAMS} are allowed",
+                    event.name,
+                    indexed.len()
+                ),
+                span.clone(),
+            ));
+        }
+    }
+
+    diagnostics
+}
@@ -0,0 +1,50 @@
+//! Checks that push immediates fit the push they follow
+
+use crate::{
+    error::Diagnostic,
+    parser::{Ast, MacroBody},
+    span::Spanned,
+    utils::bytes_util::{literal_bit_length, literal_to_hex},
+};
+
+/// Check Push Immediates
+///
+/// Reports a literal, written in hex or decimal, that follows a `pushN` but needs more than `N`
+/// bytes, such as `push1 256`. A literal that follows anything else is pushed on its own and
+/// sized to fit, so it is not checked.
+pub fn check_push_immediates(ast: &[Spanned<Ast>]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for (item, _) in ast {
+        let Ast::MacroDefinition { statements, .. } = item else {
+            continue;
+        };
+
+        for pair in statements.windows(2) {
+            let [(MacroBody::Opcode(opcode), push_span), (MacroBody::HexLiteral(lit), span)] = pair
+            else {
+                continue;
+            };
+            let Some(size) = opcode.push_size() else {
+                continue;
+            };
+
+            let needed = literal_bit_length(lit).div_ceil(8);
+            if needed > size {
+                diagnostics.push(
+                    Diagnostic::error(
+                        format!(
+                            "`{}` needs {needed} bytes, which does not fit in `{}`",
+                            literal_to_hex(lit, true, true),
+                            opcode.mnemonic()
+                        ),
+                        span.clone(),
+                    )
+                    .with_related(push_span.clone()),
+                );
+            }
+        }
+    }
+
+    diagnostics
+}
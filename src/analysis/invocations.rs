@@ -0,0 +1,161 @@
+//! Validation of macro invocations
+
+use std::collections::HashMap;
+
+use crate::{
+    error::Diagnostic,
+    parser::{Arg, Args, Ast, MacroBody},
+    span::{Span, Spanned},
+    utils::interner::Symbol,
+};
+
+use super::{
+    defined_labels,
+    symbols::{SymbolKind, SymbolTable},
+};
+
+/// Check Invocations
+///
+/// Validates every macro invocation inside of macro bodies. The invoked macro must exist and be
+/// passed as many arguments as it declares, constant references, both in bodies and as
+/// arguments, must name a defined constant and argument references must name a parameter of the
+/// enclosing macro.
+pub fn check_invocations(ast: &[Spanned<Ast>], symbols: &SymbolTable) -> Vec<Diagnostic> {
+    // The invocation graph is keyed by the symbols of the macro names
+    let arities: HashMap<Symbol, usize> = ast
+        .iter()
+        .filter_map(|(item, _)| match item {
+            Ast::MacroDefinition { name, args, .. } => Some((symbols.symbol(name)?, args.len())),
+            _ => None,
+        })
+        .collect();
+
+    let mut diagnostics = Vec::new();
+
+    for (item, _) in ast {
+        let Ast::MacroDefinition {
+            statements,
+            args: params,
+            ..
+        } = item
+        else {
+            continue;
+        };
+
+        for (statement, span) in statements {
+            let (name, args) = match statement {
+                MacroBody::MacroInvocation { name, args } => (name, args),
+                MacroBody::ConstantRef((name, _)) => {
+                    diagnostics.extend(check_constant_ref(name, span, symbols));
+                    continue;
+                }
+                _ => continue,
+            };
+
+            match symbols.symbol(name).and_then(|symbol| arities.get(&symbol)) {
+                Some(&expected) if expected != args.len() => {
+                    diagnostics.push(Diagnostic::error(
+                        format!(
+                            "macro `{name}` expects {expected} argument(s), found {}",
+                            args.len()
+                        ),
+                        span.clone(),
+                    ));
+                }
+                Some(_) => {}
+                None => diagnostics.push(Diagnostic::error(
+                    format!("invocation of undefined macro `{name}`"),
+                    span.clone(),
+                )),
+            }
+
+            diagnostics.extend(check_args(args, params, symbols));
+        }
+    }
+
+    diagnostics
+}
+
+/// Check Bare Macro References
+///
+/// Reports jump label references that name a macro rather than a label, such as `FOO` where
+/// `FOO(...)` was meant. Without parentheses the name is parsed as a label, so the macro is never
+/// invoked. Names that are also defined as a label are left alone.
+pub fn check_bare_macro_references(ast: &[Spanned<Ast>], symbols: &SymbolTable) -> Vec<Diagnostic> {
+    let labels = defined_labels(ast);
+    let params: HashMap<Symbol, &Args> = ast
+        .iter()
+        .filter_map(|(item, _)| match item {
+            Ast::MacroDefinition { name, args, .. } => Some((symbols.symbol(name)?, args)),
+            _ => None,
+        })
+        .collect();
+
+    let mut diagnostics = Vec::new();
+    for (item, _) in ast {
+        let Ast::MacroDefinition { statements, .. } = item else {
+            continue;
+        };
+
+        for (statement, span) in statements {
+            let MacroBody::JumpLabel(name) = statement else {
+                continue;
+            };
+            if labels.contains(name) || symbols.find_macro(name).is_none() {
+                continue;
+            }
+
+            let args = symbols
+                .symbol(name)
+                .and_then(|symbol| params.get(&symbol))
+                .map(|args| {
+                    args.iter()
+                        .map(|(arg, _)| match arg {
+                            Arg::Valid(param) => param.as_str(),
+                            _ => "..",
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                })
+                .unwrap_or_default();
+            diagnostics.push(Diagnostic::warning(
+                format!(
+                    "`{name}` is a macro but is used as a jump label, did you mean `{name}({args})`?"
+                ),
+                span.clone(),
+            ));
+        }
+    }
+
+    diagnostics
+}
+
+/// Resolves the constant and argument references passed to an invocation
+fn check_args(args: &Args, params: &Args, symbols: &SymbolTable) -> Vec<Diagnostic> {
+    args.iter()
+        .filter_map(|(arg, span)| match arg {
+            Arg::ConstantRef((name, _)) => check_constant_ref(name, span, symbols),
+            Arg::ArgRef((name, _))
+                if !params
+                    .iter()
+                    .any(|(param, _)| matches!(param, Arg::Valid(p) if p == name)) =>
+            {
+                Some(Diagnostic::error(
+                    format!("`<{name}>` is not an argument of the enclosing macro"),
+                    span.clone(),
+                ))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Reports a constant reference that does not name a defined constant
+fn check_constant_ref(name: &str, span: &Span, symbols: &SymbolTable) -> Option<Diagnostic> {
+    symbols.find(name, SymbolKind::Constant).is_none().then(|| {
+        Diagnostic::error(
+            format!("reference to undefined constant `[{name}]`"),
+            span.clone(),
+        )
+    })
+}
@@ -0,0 +1,30 @@
+//! Detection of raw `jumpdest` opcodes
+
+use crate::{
+    error::Diagnostic,
+    parser::{Ast, MacroBody},
+    span::Spanned,
+    utils::opcodes::Opcode,
+};
+
+/// Check Raw Jumpdests
+///
+/// Reports `jumpdest` written as an opcode in a macro body. A label such as `done:` is lowered to
+/// a `jumpdest` whose address is tracked so that `done` can be pushed and jumped to, whereas the
+/// address of a raw `jumpdest` is not known to the compiler.
+pub fn check_raw_jumpdests(ast: &[Spanned<Ast>]) -> Vec<Diagnostic> {
+    ast.iter()
+        .filter_map(|(item, _)| match item {
+            Ast::MacroDefinition { statements, .. } => Some(statements),
+            _ => None,
+        })
+        .flatten()
+        .filter(|(statement, _)| *statement == MacroBody::Opcode(Opcode::Jumpdest))
+        .map(|(_, span)| {
+            Diagnostic::warning(
+                "`jumpdest` is not tracked as a jump destination, use a label such as `name:` instead",
+                span.clone(),
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,74 @@
+//! Validation of data location keywords on abi parameters
+
+use crate::{
+    error::Diagnostic,
+    parser::Ast,
+    span::Spanned,
+    utils::{abi::FunctionParam, ast::ArgumentLocation},
+};
+
+/// Where a parameter was declared
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParamContext {
+    FunctionInput,
+    FunctionOutput,
+    Error,
+}
+
+/// Check Locations
+///
+/// Reports data location keywords that are not valid for the parameter they are attached to.
+/// - `storage` is never valid in an abi
+/// - `calldata` is not valid on a return value
+/// - error parameters take no location
+/// - a location is only valid on a dynamic type, such as `bytes`, `string`, arrays or tuples
+///
+/// Event parameters cannot be declared with a location, so they are not checked.
+pub fn check_locations(ast: &[Spanned<Ast>]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for (item, _) in ast {
+        match item {
+            Ast::AbiFunction(function) => {
+                for param in &function.inputs {
+                    diagnostics.extend(check_param(param, ParamContext::FunctionInput));
+                }
+                for param in &function.outputs {
+                    diagnostics.extend(check_param(param, ParamContext::FunctionOutput));
+                }
+            }
+            Ast::AbiError(error) => {
+                for param in &error.inputs {
+                    diagnostics.extend(check_param(param, ParamContext::Error));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    diagnostics
+}
+
+fn check_param(
+    (param, span): &Spanned<FunctionParam>,
+    context: ParamContext,
+) -> Option<Diagnostic> {
+    let location = param.location.as_ref()?;
+
+    let message = match (location, context) {
+        (_, ParamContext::Error) => "error parameters cannot have a data location".to_string(),
+        (ArgumentLocation::Storage, _) => {
+            "`storage` is not a valid location for an abi parameter".to_string()
+        }
+        (ArgumentLocation::Calldata, ParamContext::FunctionOutput) => {
+            "`calldata` is not a valid location for a return value".to_string()
+        }
+        _ if !param.kind.is_memory_type() => format!(
+            "`{location}` is only valid on dynamic types, not `{}`",
+            param.kind
+        ),
+        _ => return None,
+    };
+
+    Some(Diagnostic::error(message, span.clone()))
+}
@@ -0,0 +1,112 @@
+//! Analysis
+//!
+//! Semantic checks that run over the parsed ast. Each pass lives in its own module and reports
+//! its findings as [`Diagnostic`]s rather than failing, so that as much feedback as possible can
+//! be surfaced at once.
+
+pub mod abi_diff;
+pub mod abi_names;
+pub mod builtins;
+pub mod dead_code;
+pub mod entry_points;
+pub mod events;
+pub mod immediates;
+pub mod invocations;
+pub mod jumpdests;
+pub mod locations;
+pub mod outlined;
+pub mod params;
+pub mod peephole;
+pub mod reachability;
+pub mod selectors;
+pub mod shadowing;
+pub mod stack;
+pub mod storage;
+pub mod symbols;
+pub mod tables;
+pub mod test_cases;
+pub mod typos;
+
+use std::collections::HashSet;
+
+use crate::{
+    error::{Diagnostic, Severity},
+    parser::{Ast, MacroBody},
+    span::Spanned,
+};
+use peephole::RedundantPattern;
+use symbols::SymbolTable;
+
+/// Configuration of the optional analysis passes
+#[derive(Debug, Clone)]
+pub struct AnalysisOptions {
+    /// Opcode sequences reported by the peephole lint
+    pub redundant_patterns: Vec<RedundantPattern>,
+    /// Severity of identifiers that look like misspelt opcodes, `None` disables the check
+    pub opcode_typos: Option<Severity>,
+    /// Severity of missing `MAIN` and `CONSTRUCTOR` macros, `None` disables the check. Disabled by
+    /// default, as files that are only included by others have no entry points.
+    pub entry_points: Option<Severity>,
+}
+
+impl Default for AnalysisOptions {
+    fn default() -> Self {
+        Self {
+            redundant_patterns: peephole::default_patterns(),
+            opcode_typos: Some(Severity::Warning),
+            entry_points: None,
+        }
+    }
+}
+
+/// Run every analysis pass over the ast with the default options
+pub fn analyze(ast: &[Spanned<Ast>]) -> Vec<Diagnostic> {
+    analyze_with_options(ast, &AnalysisOptions::default())
+}
+
+/// Run every analysis pass over the ast
+pub fn analyze_with_options(ast: &[Spanned<Ast>], options: &AnalysisOptions) -> Vec<Diagnostic> {
+    let symbols = SymbolTable::from_ast(ast);
+
+    let mut diagnostics = Vec::new();
+    diagnostics.extend(builtins::check_builtins(ast, &symbols));
+    diagnostics.extend(events::check_events(ast));
+    diagnostics.extend(abi_names::check_abi_names(ast));
+    diagnostics.extend(params::check_duplicate_params(ast));
+    diagnostics.extend(selectors::check_selectors(ast));
+    diagnostics.extend(locations::check_locations(ast));
+    diagnostics.extend(invocations::check_invocations(ast, &symbols));
+    diagnostics.extend(invocations::check_bare_macro_references(ast, &symbols));
+    diagnostics.extend(outlined::check_fn_arg_invocations(ast));
+    diagnostics.extend(immediates::check_push_immediates(ast));
+    diagnostics.extend(stack::check_fn_stack(ast));
+    diagnostics.extend(stack::check_stack_limit(ast));
+    diagnostics.extend(dead_code::check_dead_code(ast));
+    diagnostics.extend(shadowing::check_opcode_shadowing(ast));
+    diagnostics.extend(jumpdests::check_raw_jumpdests(ast));
+    diagnostics.extend(tables::check_jump_tables(ast));
+    diagnostics.extend(storage::check_storage_slots(ast));
+    diagnostics.extend(peephole::check_peephole(ast, &options.redundant_patterns));
+    if let Some(severity) = options.opcode_typos {
+        diagnostics.extend(typos::check_opcode_typos(ast, &symbols, severity));
+    }
+    if let Some(severity) = options.entry_points {
+        diagnostics.extend(entry_points::check_entry_points(ast, &symbols, severity));
+    }
+    diagnostics
+}
+
+/// The names of every jump destination defined in any macro
+pub(crate) fn defined_labels(ast: &[Spanned<Ast>]) -> HashSet<&String> {
+    ast.iter()
+        .filter_map(|(item, _)| match item {
+            Ast::MacroDefinition { statements, .. } => Some(statements),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|(statement, _)| match statement {
+            MacroBody::JumpLabelDest(label) => Some(label),
+            _ => None,
+        })
+        .collect()
+}
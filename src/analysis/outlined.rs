@@ -0,0 +1,50 @@
+//! Detection of argument invocations that an outlined macro cannot resolve
+
+use crate::{
+    error::Diagnostic,
+    parser::{Arg, Ast, MacroBody, MacroType},
+    span::Spanned,
+};
+
+/// Check Fn Arg Invocations
+///
+/// Reports `<arg>` statements in the body of a `fn` that do not name one of its parameters. An
+/// outlined macro is compiled once and jumped to, so unlike an inlined macro it cannot pick up the
+/// arguments of whichever macro invoked it. Arguments passed on to an invocation are checked by
+/// [`check_invocations`](super::invocations::check_invocations).
+pub fn check_fn_arg_invocations(ast: &[Spanned<Ast>]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for (item, _) in ast {
+        let Ast::MacroDefinition {
+            name,
+            macro_type: (MacroType::Fn, _),
+            statements,
+            args,
+            ..
+        } = item
+        else {
+            continue;
+        };
+
+        for (statement, span) in statements {
+            let MacroBody::ArgsInvocation((arg, _)) = statement else {
+                continue;
+            };
+            if !args
+                .iter()
+                .any(|(param, _)| matches!(param, Arg::Valid(p) if p == arg))
+            {
+                diagnostics.push(Diagnostic::error(
+                    format!(
+                        "`<{arg}>` is not an argument of fn `{name}`, outlined macros cannot use \
+                         the arguments of their caller"
+                    ),
+                    span.clone(),
+                ));
+            }
+        }
+    }
+
+    diagnostics
+}
@@ -0,0 +1,65 @@
+//! Detection of duplicate parameter names in abi definitions
+
+use std::collections::HashSet;
+
+use crate::{
+    error::Diagnostic,
+    parser::Ast,
+    span::{Span, Spanned},
+};
+
+/// Check Duplicate Params
+///
+/// Reports parameter names that are repeated within a single function, event or error. This is
+/// valid abi, but is almost certainly a mistake. The inputs and outputs of a function share a
+/// namespace, and unnamed parameters are never reported. The diagnostic points at the repeated
+/// occurrence.
+pub fn check_duplicate_params(ast: &[Spanned<Ast>]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for (item, _) in ast {
+        let (kind, name, params): (_, _, Vec<(&str, &Span)>) = match item {
+            Ast::AbiFunction(function) => (
+                "function",
+                &function.name,
+                function
+                    .inputs
+                    .iter()
+                    .chain(&function.outputs)
+                    .map(|(param, span)| (param.name.as_str(), span))
+                    .collect(),
+            ),
+            Ast::AbiEvent(event) => (
+                "event",
+                &event.name,
+                event
+                    .inputs
+                    .iter()
+                    .map(|(param, span)| (param.name.as_str(), span))
+                    .collect(),
+            ),
+            Ast::AbiError(error) => (
+                "error",
+                &error.name,
+                error
+                    .inputs
+                    .iter()
+                    .map(|(param, span)| (param.name.as_str(), span))
+                    .collect(),
+            ),
+            _ => continue,
+        };
+
+        let mut seen = HashSet::new();
+        for (param, span) in params {
+            if !param.is_empty() && !seen.insert(param) {
+                diagnostics.push(Diagnostic::warning(
+                    format!("{kind} `{name}` has more than one parameter named `{param}`"),
+                    span.clone(),
+                ));
+            }
+        }
+    }
+
+    diagnostics
+}
@@ -0,0 +1,119 @@
+//! Peephole lint for opcode sequences that have no effect
+
+use crate::{
+    error::Diagnostic,
+    parser::{Ast, MacroBody},
+    span::Spanned,
+    utils::opcodes::{Opcode, OPCODES_MAP},
+};
+
+/// A single element of a [`RedundantPattern`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PatternItem {
+    /// A specific opcode
+    Opcode(Opcode),
+    /// Any push opcode
+    AnyPush,
+    /// A hex literal, which is pushed to the stack
+    Literal,
+}
+
+impl PatternItem {
+    fn matches(&self, statement: &MacroBody) -> bool {
+        match (self, statement) {
+            (PatternItem::Opcode(expected), MacroBody::Opcode(opcode)) => expected == opcode,
+            (PatternItem::AnyPush, MacroBody::Opcode(opcode)) => opcode.is_push(),
+            (PatternItem::Literal, MacroBody::HexLiteral(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+/// A sequence of consecutive statements that can be removed without changing behaviour
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RedundantPattern {
+    /// The statements to match
+    pub items: Vec<PatternItem>,
+    /// Explanation reported when the pattern matches
+    pub message: String,
+}
+
+impl RedundantPattern {
+    /// Create a new pattern
+    pub fn new(items: Vec<PatternItem>, message: impl Into<String>) -> Self {
+        Self {
+            items,
+            message: message.into(),
+        }
+    }
+}
+
+/// The patterns checked by [`crate::analysis::analyze`]
+///
+/// Longer patterns come first so that `push1 0x00 pop` is reported as a whole rather than from the
+/// literal onwards.
+pub fn default_patterns() -> Vec<RedundantPattern> {
+    let pop = PatternItem::Opcode(Opcode::Pop);
+    let opcode = |mnemonic: String| PatternItem::Opcode(OPCODES_MAP[mnemonic.as_str()]);
+
+    let mut patterns = vec![
+        RedundantPattern::new(
+            vec![PatternItem::AnyPush, PatternItem::Literal, pop],
+            "pushing a value and immediately popping it has no effect",
+        ),
+        RedundantPattern::new(
+            vec![PatternItem::Literal, pop],
+            "pushing a value and immediately popping it has no effect",
+        ),
+    ];
+    for n in 1..=16 {
+        patterns.push(RedundantPattern::new(
+            vec![opcode(format!("dup{n}")), pop],
+            format!("`dup{n} pop` has no effect"),
+        ));
+        patterns.push(RedundantPattern::new(
+            vec![opcode(format!("swap{n}")), opcode(format!("swap{n}"))],
+            format!("`swap{n} swap{n}` has no effect"),
+        ));
+    }
+    patterns
+}
+
+/// Check Peephole
+///
+/// Scans each macro body for the given patterns, reporting an info diagnostic spanning every
+/// matched sequence. Matches do not overlap, the first pattern to match at a position wins.
+pub fn check_peephole(ast: &[Spanned<Ast>], patterns: &[RedundantPattern]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for (item, _) in ast {
+        let Ast::MacroDefinition { statements, .. } = item else {
+            continue;
+        };
+
+        let mut index = 0;
+        while index < statements.len() {
+            let window = &statements[index..];
+            let matched = patterns.iter().find(|pattern| {
+                pattern.items.len() <= window.len()
+                    && pattern
+                        .items
+                        .iter()
+                        .zip(window)
+                        .all(|(item, (statement, _))| item.matches(statement))
+            });
+
+            match matched {
+                Some(pattern) => {
+                    let start = window[0].1.start;
+                    let end = window[pattern.items.len() - 1].1.end;
+                    diagnostics.push(Diagnostic::info(pattern.message.clone(), start..end));
+                    index += pattern.items.len();
+                }
+                None => index += 1,
+            }
+        }
+    }
+
+    diagnostics
+}
@@ -0,0 +1,82 @@
+//! Reachability of definitions from an entry macro, used to find unused definitions and to split
+//! the deployment and runtime bytecode
+
+use std::collections::HashSet;
+
+use crate::utils::ast::{Contract, MacroArg, Statement, StatementType};
+
+/// Reachable From
+///
+/// Returns the names of every macro, constant and table transitively referenced from the entry
+/// macro, including the entry itself. Invocations, their arguments, constant pushes and builtin
+/// arguments are followed. An entry that does not exist reaches nothing.
+pub fn reachable_from(contract: &Contract, entry: &str) -> HashSet<String> {
+    let constants: HashSet<String> = contract
+        .constants
+        .lock()
+        .map(|constants| constants.iter().map(|c| c.name.clone()).collect())
+        .unwrap_or_default();
+
+    // The traversal borrows names from the contract, and only the result is allocated
+    let mut reachable: HashSet<&str> = HashSet::new();
+    let mut pending = vec![entry];
+
+    while let Some(name) = pending.pop() {
+        if reachable.contains(name) {
+            continue;
+        }
+
+        // Looked up in place, as the `find_*_by_name` helpers clone the definition
+        if let Some(definition) = contract.macros.iter().find(|m| m.name == name) {
+            collect_references(&definition.statements, &mut pending);
+        } else if !constants.contains(name) && !contract.tables.iter().any(|t| t.name == name) {
+            // Labels and undefined names are not definitions
+            continue;
+        }
+        reachable.insert(name);
+    }
+
+    reachable.into_iter().map(str::to_string).collect()
+}
+
+/// Segment Reachability
+///
+/// Returns the names of the macros reachable from `CONSTRUCTOR` and from `MAIN`, which make up
+/// the deployment and runtime bytecode respectively. A macro used by both entry points is in both
+/// sets, and a missing entry point reaches nothing.
+pub fn segment_reachability(contract: &Contract) -> (HashSet<String>, HashSet<String>) {
+    let macros = |entry| {
+        reachable_from(contract, entry)
+            .into_iter()
+            .filter(|name| contract.find_macro_by_name(name).is_some())
+            .collect()
+    };
+
+    (macros("CONSTRUCTOR"), macros("MAIN"))
+}
+
+/// Collects every name a list of statements may refer to
+fn collect_references<'a>(statements: &'a [Statement], references: &mut Vec<&'a str>) {
+    for statement in statements {
+        match &statement.ty {
+            StatementType::MacroInvocation(invocation) => {
+                references.push(&invocation.macro_name);
+                references.extend(invocation.args.iter().filter_map(arg_reference));
+            }
+            StatementType::Constant(name) => references.push(name),
+            StatementType::BuiltinFunctionCall(call) => {
+                references.extend(call.args.iter().filter_map(arg_reference));
+            }
+            StatementType::Label(label) => collect_references(&label.inner, references),
+            _ => {}
+        }
+    }
+}
+
+/// The definition an invocation argument may refer to
+fn arg_reference(arg: &MacroArg) -> Option<&str> {
+    match arg {
+        MacroArg::Ident(name) | MacroArg::ConstantRef(name) => Some(name),
+        MacroArg::Literal(_) | MacroArg::ArgCall(_) => None,
+    }
+}
@@ -0,0 +1,57 @@
+//! Detection of abi functions that share a selector
+
+use std::collections::HashMap;
+
+use crate::{
+    error::Diagnostic,
+    lowering::selector,
+    parser::Ast,
+    span::{Span, Spanned},
+};
+
+/// Check Selectors
+///
+/// Reports functions whose selector, the first four bytes of the hash of their canonical
+/// signature, matches that of an earlier function. This includes overloads that differ only by
+/// their return types, as well as genuine hash collisions between different signatures. Each
+/// diagnostic points at the later function and relates the earlier one.
+pub fn check_selectors(ast: &[Spanned<Ast>]) -> Vec<Diagnostic> {
+    let mut seen: HashMap<[u8; 4], (&String, &Span)> = HashMap::new();
+
+    let mut diagnostics = Vec::new();
+    for (item, span) in ast {
+        let Ast::AbiFunction(function) = item else {
+            continue;
+        };
+
+        let types = function
+            .inputs
+            .iter()
+            .map(|(param, _)| param.kind.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        let selector = selector(&format!("{}({types})", function.name));
+
+        match seen.get(&selector) {
+            Some((name, first)) => diagnostics.push(
+                Diagnostic::error(
+                    format!(
+                        "function `{}` has the same selector, 0x{}, as function `{name}`",
+                        function.name,
+                        selector
+                            .iter()
+                            .map(|byte| format!("{byte:02x}"))
+                            .collect::<String>()
+                    ),
+                    span.clone(),
+                )
+                .with_related((*first).clone()),
+            ),
+            None => {
+                seen.insert(selector, (&function.name, span));
+            }
+        }
+    }
+
+    diagnostics
+}
@@ -0,0 +1,40 @@
+//! Detection of definitions named after opcodes
+
+use crate::{
+    error::Diagnostic,
+    parser::{Ast, MacroType},
+    span::Spanned,
+    utils::opcodes::opcode_from_str_ci,
+};
+
+/// Check Opcode Shadowing
+///
+/// Reports macros, constants and tables whose name matches an opcode mnemonic in any case, such
+/// as a macro named `ADD`. These are legal, but are easily confused with the opcode when reading a
+/// macro body.
+pub fn check_opcode_shadowing(ast: &[Spanned<Ast>]) -> Vec<Diagnostic> {
+    ast.iter()
+        .filter_map(|(item, span)| {
+            let (kind, name) = match item {
+                Ast::MacroDefinition {
+                    name, macro_type, ..
+                } => match macro_type.0 {
+                    MacroType::Macro => ("macro", name),
+                    MacroType::Fn => ("fn", name),
+                    MacroType::Test => ("test", name),
+                },
+                Ast::ConstantDefinition { name, .. } => ("constant", name),
+                Ast::TableDefinition { name, .. } => ("table", name),
+                _ => return None,
+            };
+            let opcode = opcode_from_str_ci(name)?;
+            Some(Diagnostic::warning(
+                format!(
+                    "{kind} `{name}` has the same name as the opcode `{}`",
+                    opcode.mnemonic()
+                ),
+                span.clone(),
+            ))
+        })
+        .collect()
+}
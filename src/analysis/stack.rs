@@ -0,0 +1,249 @@
+//! Stack checks of outlined (fn) macros
+
+use std::collections::HashMap;
+
+use crate::{
+    error::Diagnostic,
+    parser::{Ast, MacroBody, MacroType},
+    span::{Span, Spanned},
+    utils::{
+        ast::{MacroDefinition, StatementType},
+        opcodes::Opcode,
+    },
+};
+
+/// The maximum number of items the EVM stack can hold
+pub const STACK_LIMIT: usize = 1024;
+
+/// The declared stack signature of a macro
+#[derive(Debug, Clone, Copy)]
+struct Signature {
+    takes: usize,
+    returns: usize,
+    is_fn: bool,
+}
+
+/// Check Fn Stack
+///
+/// `fn` macros are outlined and jumped to, so their `takes` and `returns` must describe the stack
+/// exactly. The straight line prefix of every macro body, up to the first jump, jump destination
+/// or halting opcode, is walked to report
+/// - `fn` macros whose body consumes more items than they declare to take
+/// - `fn` macros without control flow whose body leaves a different number of items than they
+///   declare to return
+/// - invocations of `fn` macros with fewer items on the stack than the callee takes
+pub fn check_fn_stack(ast: &[Spanned<Ast>]) -> Vec<Diagnostic> {
+    let signatures: HashMap<&str, Signature> = ast
+        .iter()
+        .filter_map(|(item, _)| match item {
+            Ast::MacroDefinition {
+                name,
+                macro_type,
+                takes,
+                returns,
+                ..
+            } => Some((
+                name.as_str(),
+                Signature {
+                    takes: takes.0,
+                    returns: returns.0,
+                    is_fn: macro_type.0 == MacroType::Fn,
+                },
+            )),
+            _ => None,
+        })
+        .collect();
+
+    let mut diagnostics = Vec::new();
+
+    for (item, span) in ast {
+        let Ast::MacroDefinition {
+            name,
+            macro_type,
+            takes,
+            returns,
+            statements,
+            ..
+        } = item
+        else {
+            continue;
+        };
+        let is_fn = macro_type.0 == MacroType::Fn;
+
+        // The height of the stack relative to the items the macro takes, and the lowest it reaches
+        let mut height = 0isize;
+        let mut lowest = 0isize;
+        let mut complete = true;
+        let mut after_push = false;
+
+        for (statement, statement_span) in statements {
+            // The literal following a sized push is its immediate, which the push already counted
+            let immediate = after_push && matches!(statement, MacroBody::HexLiteral(_));
+            after_push = matches!(statement, MacroBody::Opcode(opcode) if opcode.is_push());
+            if immediate {
+                continue;
+            }
+
+            let Some((inputs, outputs)) = stack_effect(statement, &signatures) else {
+                complete = false;
+                break;
+            };
+
+            if let MacroBody::MacroInvocation { name: callee, .. } = statement {
+                let available = takes.0 as isize + height;
+                if signatures[callee.as_str()].is_fn && available < inputs as isize {
+                    diagnostics.push(Diagnostic::warning(
+                        format!(
+                            "fn `{callee}` takes {inputs} stack item(s), but only {} are available",
+                            available.max(0)
+                        ),
+                        statement_span.clone(),
+                    ));
+                }
+            }
+
+            height -= inputs as isize;
+            lowest = lowest.min(height);
+            height += outputs as isize;
+
+            if ends_block(statement) {
+                complete = false;
+                break;
+            }
+        }
+
+        if !is_fn {
+            continue;
+        }
+
+        let consumed = lowest.unsigned_abs();
+        if consumed > takes.0 {
+            diagnostics.push(Diagnostic::warning(
+                format!(
+                    "fn `{name}` declares takes ({}) but its body consumes {consumed} stack item(s)",
+                    takes.0
+                ),
+                definition_span(span, &takes.1),
+            ));
+        } else if complete && takes.0 as isize + height != returns.0 as isize {
+            diagnostics.push(Diagnostic::warning(
+                format!(
+                    "fn `{name}` declares returns ({}) but its body leaves {} stack item(s)",
+                    returns.0,
+                    (takes.0 as isize + height).max(0)
+                ),
+                definition_span(span, &returns.1),
+            ));
+        }
+    }
+
+    diagnostics
+}
+
+/// Check Stack Limit
+///
+/// Reports `takes` and `returns` annotations on any macro that are larger than the EVM stack can
+/// hold, which are almost certainly typos.
+pub fn check_stack_limit(ast: &[Spanned<Ast>]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for (item, span) in ast {
+        let Ast::MacroDefinition {
+            name,
+            takes,
+            returns,
+            ..
+        } = item
+        else {
+            continue;
+        };
+
+        for (keyword, (size, annotation)) in [("takes", takes), ("returns", returns)] {
+            if *size > STACK_LIMIT {
+                diagnostics.push(Diagnostic::warning(
+                    format!(
+                        "`{name}` declares {keyword} ({size}), but the stack holds at most {STACK_LIMIT} items"
+                    ),
+                    definition_span(span, annotation),
+                ));
+            }
+        }
+    }
+
+    diagnostics
+}
+
+/// Max Stack Depth
+///
+/// Simulates a lowered macro body, starting from the items it takes, and returns the deepest the
+/// stack gets. The stack effect of an invoked macro is not known from its definition alone, so
+/// the simulation stops at the first invocation and the peak reached before it is returned.
+/// Labels are assumed to be reached by falling through. Returns `None` if the body consumes more
+/// items than are on the stack.
+pub fn max_stack_depth(macro_def: &MacroDefinition) -> Option<usize> {
+    let mut depth = macro_def.takes;
+    let mut peak = depth;
+    let mut after_push = false;
+
+    for statement in &macro_def.statements {
+        // The literal following a sized push is its immediate, which the push already counted
+        let immediate = after_push && matches!(statement.ty, StatementType::Literal(_));
+        after_push = matches!(&statement.ty, StatementType::Opcode(opcode) if opcode.is_push());
+        if immediate {
+            continue;
+        }
+
+        let (inputs, outputs) = match &statement.ty {
+            StatementType::Opcode(opcode) => opcode.stack_io(),
+            StatementType::Literal(_)
+            | StatementType::Constant(_)
+            | StatementType::ArgCall(_)
+            | StatementType::LabelCall(_)
+            | StatementType::BuiltinFunctionCall(_) => (0, 1),
+            StatementType::Label(_) | StatementType::Code(_) => (0, 0),
+            StatementType::MacroInvocation(_) => break,
+        };
+        depth = depth.checked_sub(inputs)? + outputs;
+        peak = peak.max(depth);
+    }
+
+    Some(peak)
+}
+
+/// The number of stack items a statement consumes and produces, `None` if it is not known
+fn stack_effect(
+    statement: &MacroBody,
+    signatures: &HashMap<&str, Signature>,
+) -> Option<(usize, usize)> {
+    match statement {
+        MacroBody::Opcode(opcode) => Some(opcode.stack_io()),
+        // Bare literals, label references, arguments, constants and builtins each push one word
+        MacroBody::HexLiteral(_)
+        | MacroBody::AutoPush(_)
+        | MacroBody::JumpLabel(_)
+        | MacroBody::ArgsInvocation(_)
+        | MacroBody::ConstantRef(_)
+        | MacroBody::BuiltinInvocation { .. } => Some((0, 1)),
+        MacroBody::MacroInvocation { name, .. } => signatures
+            .get(name.as_str())
+            .map(|signature| (signature.takes, signature.returns)),
+        MacroBody::JumpLabelDest(_) | MacroBody::UnexpectedToken(_) => None,
+    }
+}
+
+/// Returns if execution does not continue to the next statement
+fn ends_block(statement: &MacroBody) -> bool {
+    matches!(
+        statement,
+        MacroBody::Opcode(opcode) if opcode.is_halting() || *opcode == Opcode::Jump || *opcode == Opcode::Jumpi
+    )
+}
+
+/// Prefer pointing at the offending declaration, falling back to the whole definition
+fn definition_span(definition: &Span, declaration: &Span) -> Span {
+    if declaration.is_empty() {
+        definition.clone()
+    } else {
+        declaration.clone()
+    }
+}
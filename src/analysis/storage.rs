@@ -0,0 +1,136 @@
+//! Free storage pointer resolution and storage slot collisions
+
+use std::collections::HashMap;
+
+use crate::{
+    error::Diagnostic,
+    parser::{Ast, ConstantValue},
+    span::{Span, Spanned},
+    utils::{ast::Literal, bytes_util::literal_to_hex},
+};
+
+/// A constant with its value resolved
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedConstant {
+    /// The name of the constant
+    pub name: String,
+    /// The literal value, or the slot assigned to a free storage pointer
+    pub value: Literal,
+    /// If the value was assigned to a free storage pointer
+    pub free_storage_pointer: bool,
+    /// The constant an alias such as `[OTHER]` resolves through, `None` if not an alias
+    pub alias_of: Option<String>,
+    /// The span of the constant definition
+    pub span: Span,
+}
+
+/// Resolve Constants
+///
+/// Assigns storage slots to `FREE_STORAGE_POINTER()` constants in definition order, starting at
+/// slot zero. Literal constants keep their value, and aliases take the value of the constant they
+/// refer to. Aliases of undefined constants and alias cycles are left out.
+pub fn resolve_constants(ast: &[Spanned<Ast>]) -> Vec<ResolvedConstant> {
+    let mut next_slot = 0u64;
+    let mut values: HashMap<&str, (Literal, bool)> = HashMap::new();
+    let mut aliases: HashMap<&str, &str> = HashMap::new();
+
+    let definitions: Vec<_> = ast
+        .iter()
+        .filter_map(|(item, span)| match item {
+            Ast::ConstantDefinition { name, value, .. } => Some((name, value, span)),
+            _ => None,
+        })
+        .collect();
+
+    for (name, value, _) in &definitions {
+        let resolved = match value {
+            ConstantValue::Literal(_) | ConstantValue::Expression(..) => {
+                value.as_u256_bytes().map(|value| (value, false))
+            }
+            ConstantValue::FreeStoragePointer => {
+                let mut slot = [0u8; 32];
+                slot[24..].copy_from_slice(&next_slot.to_be_bytes());
+                next_slot += 1;
+                Some((slot, true))
+            }
+            ConstantValue::ConstantRef((target, _)) => {
+                aliases.insert(name, target);
+                None
+            }
+        };
+        if let Some(resolved) = resolved {
+            values.entry(name).or_insert(resolved);
+        }
+    }
+
+    definitions
+        .into_iter()
+        .filter_map(|(name, value, span)| {
+            let (alias_of, (value, free_storage_pointer)) = match value {
+                ConstantValue::ConstantRef(_) => {
+                    let target = resolve_alias(name, &aliases)?;
+                    (Some(target.to_string()), *values.get(target)?)
+                }
+                _ => (None, *values.get(name.as_str())?),
+            };
+            Some(ResolvedConstant {
+                name: name.clone(),
+                value,
+                free_storage_pointer,
+                alias_of,
+                span: span.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Follows a chain of aliases to the constant it ends at, `None` if the chain is a cycle
+fn resolve_alias<'a>(name: &'a str, aliases: &HashMap<&'a str, &'a str>) -> Option<&'a str> {
+    let mut current = name;
+    for _ in 0..=aliases.len() {
+        match aliases.get(current) {
+            Some(target) => current = target,
+            None => return Some(current),
+        }
+    }
+    None
+}
+
+/// Check Storage Slots
+///
+/// Warns when a free storage pointer is assigned a slot that another constant also resolves to.
+/// Two literal constants sharing a value are not reported, as literals are not necessarily slots,
+/// and neither is an alias sharing the slot of the constant it refers to. The diagnostic points at
+/// the later definition, with the earlier one attached as related.
+pub fn check_storage_slots(ast: &[Spanned<Ast>]) -> Vec<Diagnostic> {
+    let constants = resolve_constants(ast);
+    let mut diagnostics = Vec::new();
+
+    // Aliases are compared by the constant they resolve through
+    let root =
+        |constant: &ResolvedConstant| constant.alias_of.clone().unwrap_or(constant.name.clone());
+
+    for (i, constant) in constants.iter().enumerate() {
+        let collision = constants[..i].iter().find(|earlier| {
+            earlier.value == constant.value
+                && (earlier.free_storage_pointer || constant.free_storage_pointer)
+                && root(earlier) != root(constant)
+        });
+        if let Some(earlier) = collision {
+            diagnostics.push(
+                Diagnostic::warning(
+                    format!(
+                        "constants `{}` and `{}` both resolve to storage slot {}",
+                        earlier.name,
+                        constant.name,
+                        literal_to_hex(&constant.value, true, true)
+                    ),
+                    constant.span.clone(),
+                )
+                .with_related(earlier.span.clone()),
+            );
+        }
+    }
+
+    diagnostics
+}
@@ -0,0 +1,138 @@
+//! Symbol table of every named top level definition
+
+use std::collections::HashMap;
+
+use crate::{
+    parser::{Ast, MacroType},
+    span::{Span, Spanned},
+    utils::interner::{Interner, Symbol},
+};
+
+/// The kind of definition a symbol refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SymbolKind {
+    /// An inlined macro
+    Macro,
+    /// An outlined (fn) macro
+    Fn,
+    /// A constant
+    Constant,
+    /// A jump or code table
+    Table,
+    /// An abi function
+    Function,
+    /// An abi event
+    Event,
+    /// An abi error
+    Error,
+}
+
+impl SymbolKind {
+    /// Returns if the symbol is a macro of either type
+    pub fn is_macro(&self) -> bool {
+        matches!(self, SymbolKind::Macro | SymbolKind::Fn)
+    }
+
+    /// Human readable name of the kind, used in diagnostics
+    pub fn describe(&self) -> &'static str {
+        match self {
+            SymbolKind::Macro => "macro",
+            SymbolKind::Fn => "fn",
+            SymbolKind::Constant => "constant",
+            SymbolKind::Table => "table",
+            SymbolKind::Function => "function",
+            SymbolKind::Event => "event",
+            SymbolKind::Error => "error",
+        }
+    }
+}
+
+/// A named definition
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Definition {
+    /// The interned name of the definition, resolved with [`SymbolTable::name`]
+    pub name: Symbol,
+    /// What was defined
+    pub kind: SymbolKind,
+    /// The span of the whole definition
+    pub span: Span,
+}
+
+/// Symbol Table
+///
+/// Maps names to every definition sharing that name. Definitions of different kinds may share a
+/// name, so lookups return all of them in source order. Names are interned, so each distinct name
+/// is only stored once.
+#[derive(Debug, Default, Clone)]
+pub struct SymbolTable {
+    names: Interner,
+    symbols: HashMap<Symbol, Vec<Definition>>,
+}
+
+impl SymbolTable {
+    /// Collect the symbols of all top level definitions
+    pub fn from_ast(ast: &[Spanned<Ast>]) -> Self {
+        let mut table = Self::default();
+
+        for (item, span) in ast {
+            let symbol = match item {
+                Ast::MacroDefinition {
+                    name, macro_type, ..
+                } => {
+                    let kind = match macro_type.0 {
+                        MacroType::Macro | MacroType::Test => SymbolKind::Macro,
+                        MacroType::Fn => SymbolKind::Fn,
+                    };
+                    (name, kind)
+                }
+                Ast::ConstantDefinition { name, .. } => (name, SymbolKind::Constant),
+                Ast::TableDefinition { name, .. } => (name, SymbolKind::Table),
+                Ast::AbiFunction(function) => (&function.name, SymbolKind::Function),
+                Ast::AbiEvent(event) => (&event.name, SymbolKind::Event),
+                Ast::AbiError(error) => (&error.name, SymbolKind::Error),
+                _ => continue,
+            };
+            table.insert(symbol.0, symbol.1, span.clone());
+        }
+
+        table
+    }
+
+    /// Add a symbol to the table
+    pub fn insert(&mut self, name: &str, kind: SymbolKind, span: Span) {
+        let name = self.names.intern(name);
+        self.symbols
+            .entry(name)
+            .or_default()
+            .push(Definition { name, kind, span });
+    }
+
+    /// Returns the interned name of a definition in this table, `None` if nothing has that name
+    pub fn symbol(&self, name: &str) -> Option<Symbol> {
+        self.names.get(name)
+    }
+
+    /// Returns the name of a definition from this table
+    pub fn name(&self, definition: &Definition) -> &str {
+        self.names.resolve(definition.name)
+    }
+
+    /// Returns all definitions with the given name
+    pub fn lookup(&self, name: &str) -> &[Definition] {
+        self.names
+            .get(name)
+            .and_then(|name| self.symbols.get(&name))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Returns the first macro (of either type) with the given name
+    pub fn find_macro(&self, name: &str) -> Option<&Definition> {
+        self.lookup(name).iter().find(|s| s.kind.is_macro())
+    }
+
+    /// Returns the first definition of the given kind with the given name
+    pub fn find(&self, name: &str, kind: SymbolKind) -> Option<&Definition> {
+        self.lookup(name).iter().find(|s| s.kind == kind)
+    }
+}
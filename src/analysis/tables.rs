@@ -0,0 +1,41 @@
+//! Validation of jump table entries
+
+use crate::{
+    error::Diagnostic,
+    parser::{Ast, TableStatements},
+    span::Spanned,
+};
+
+use super::defined_labels;
+
+/// Check Jump Tables
+///
+/// Jump table entries are jump destinations, so each must be defined as a label in some macro.
+/// Reports every entry that does not name a label defined anywhere in the contract.
+pub fn check_jump_tables(ast: &[Spanned<Ast>]) -> Vec<Diagnostic> {
+    let labels = defined_labels(ast);
+
+    let mut diagnostics = Vec::new();
+
+    for (item, _) in ast {
+        let Ast::TableDefinition {
+            name, statements, ..
+        } = item
+        else {
+            continue;
+        };
+
+        for (statement, span) in statements {
+            if let TableStatements::JumpLabel(label) = statement {
+                if !labels.contains(label) {
+                    diagnostics.push(Diagnostic::error(
+                        format!("table `{name}` references undefined label `{label}`"),
+                        span.clone(),
+                    ));
+                }
+            }
+        }
+    }
+
+    diagnostics
+}
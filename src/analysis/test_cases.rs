@@ -0,0 +1,45 @@
+//! Test macros and the environment their decorators set up, used to drive a test runner
+
+use crate::utils::ast::{Contract, DecoratorFlag, Literal};
+
+/// A test macro and the transaction it is called with
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct TestCase {
+    /// The name of the test macro
+    pub name: String,
+    /// The calldata set by a `calldata` flag, as written in the decorator
+    pub calldata: Option<String>,
+    /// The value set by a `value` flag
+    pub value: Option<Literal>,
+}
+
+/// Test Cases
+///
+/// Returns every test macro of the contract in definition order, along with the calldata and
+/// value set by its decorator. A flag given more than once takes its first value, and a test
+/// without a decorator is called with neither.
+pub fn test_cases(contract: &Contract) -> Vec<TestCase> {
+    contract
+        .macros
+        .iter()
+        .filter(|definition| definition.test)
+        .map(|definition| {
+            let mut case = TestCase {
+                name: definition.name.clone(),
+                ..Default::default()
+            };
+            let flags = definition.decorator.iter().flat_map(|d| &d.flags);
+            for flag in flags {
+                match flag {
+                    DecoratorFlag::Calldata(calldata) => {
+                        case.calldata.get_or_insert_with(|| calldata.clone());
+                    }
+                    DecoratorFlag::Value(value) => {
+                        case.value.get_or_insert(*value);
+                    }
+                }
+            }
+            case
+        })
+        .collect()
+}
@@ -0,0 +1,79 @@
+//! Detection of misspelt opcodes
+//!
+//! The lexer turns any identifier that is not an opcode into a [`Token::Ident`], which in a macro
+//! body becomes a jump label reference. A misspelt opcode therefore silently becomes a reference
+//! to a label that does not exist.
+//!
+//! [`Token::Ident`]: crate::lexer::token::Token::Ident
+
+use crate::{
+    error::{Diagnostic, Severity},
+    parser::{Ast, MacroBody},
+    span::Spanned,
+    utils::opcodes::OPCODES,
+};
+
+use super::{defined_labels, symbols::SymbolTable};
+
+/// Check Opcode Typos
+///
+/// Reports jump label references that do not name a label or any other definition, but are a
+/// single edit away from a known opcode.
+pub fn check_opcode_typos(
+    ast: &[Spanned<Ast>],
+    symbols: &SymbolTable,
+    severity: Severity,
+) -> Vec<Diagnostic> {
+    let labels = defined_labels(ast);
+
+    let mut diagnostics = Vec::new();
+    for (item, _) in ast {
+        let Ast::MacroDefinition { statements, .. } = item else {
+            continue;
+        };
+
+        for (statement, span) in statements {
+            let MacroBody::JumpLabel(name) = statement else {
+                continue;
+            };
+            if labels.contains(name) || !symbols.lookup(name).is_empty() {
+                continue;
+            }
+
+            let suggestions: Vec<String> = OPCODES
+                .iter()
+                .filter(|opcode| edit_distance(name, opcode) == 1)
+                .map(|opcode| format!("`{opcode}`"))
+                .collect();
+            if !suggestions.is_empty() {
+                diagnostics.push(Diagnostic::new(
+                    severity,
+                    format!(
+                        "unknown opcode `{name}`, did you mean {}?",
+                        suggestions.join(" or ")
+                    ),
+                    span.clone(),
+                ));
+            }
+        }
+    }
+
+    diagnostics
+}
+
+/// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
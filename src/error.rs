@@ -0,0 +1,281 @@
+//! Errors and diagnostics reported by each stage of the pipeline.
+
+use std::{fmt::Display, hash::Hash, path::PathBuf};
+
+use chumsky::{error::SimpleReason, prelude::Simple};
+
+use crate::{lexer::token::Token, span::Span};
+
+/// Chuff Error
+///
+/// Returned by the high level entry points when a stage could not complete without errors.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChuffError {
+    /// The source could not be lexed
+    Lex(Vec<Simple<char>>),
+    /// The token stream could not be parsed
+    Parse(Vec<Simple<Token>>),
+}
+
+/// How serious a [`Diagnostic`] is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// The source is invalid
+    Error,
+    /// The source is valid, but is likely to be a mistake
+    Warning,
+    /// A suggestion that does not affect validity
+    Info,
+}
+
+/// A Diagnostic
+///
+/// A message tied to a span of source code, emitted by the analysis passes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Diagnostic {
+    /// The severity of the diagnostic
+    pub severity: Severity,
+    /// A human readable message
+    pub message: String,
+    /// The span of source the diagnostic points at
+    pub span: Span,
+    /// Other spans involved in the diagnostic, such as a previous definition
+    pub related: Vec<Span>,
+}
+
+impl Diagnostic {
+    /// Create a diagnostic with the given severity
+    pub fn new(severity: Severity, message: impl Into<String>, span: Span) -> Self {
+        Self {
+            severity,
+            message: message.into(),
+            span,
+            related: Vec::new(),
+        }
+    }
+
+    /// Create an error level diagnostic
+    pub fn error(message: impl Into<String>, span: Span) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: message.into(),
+            span,
+            related: Vec::new(),
+        }
+    }
+
+    /// Create a warning level diagnostic
+    pub fn warning(message: impl Into<String>, span: Span) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message: message.into(),
+            span,
+            related: Vec::new(),
+        }
+    }
+
+    /// Create an info level diagnostic
+    pub fn info(message: impl Into<String>, span: Span) -> Self {
+        Self {
+            severity: Severity::Info,
+            message: message.into(),
+            span,
+            related: Vec::new(),
+        }
+    }
+
+    /// Attach another span involved in the diagnostic
+    pub fn with_related(mut self, span: Span) -> Self {
+        self.related.push(span);
+        self
+    }
+
+    /// Returns if the diagnostic is an error
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// Create an error level diagnostic from a lexer or parser error
+    pub fn from_simple<T: Display + Hash + Eq>(error: &Simple<T>) -> Self {
+        let diagnostic = Self::error(simple_message(error), error.span());
+        match error.reason() {
+            // Point at the delimiter that was left open as well as where it should have closed
+            SimpleReason::Unclosed { span, .. } => diagnostic.with_related(span.clone()),
+            _ => diagnostic,
+        }
+    }
+}
+
+/// The message of a lexer or parser error. Custom messages are not included by the `Display`
+/// implementation of [`Simple`], so they are taken from the reason instead.
+fn simple_message<T: Display + Hash + Eq>(error: &Simple<T>) -> String {
+    match error.reason() {
+        SimpleReason::Custom(message) => message.clone(),
+        SimpleReason::Unclosed { span, delimiter } => format!(
+            "Unclosed delimiter `{delimiter}` opened at {}..{}, {}",
+            span.start,
+            span.end,
+            error
+                .found()
+                .map_or("found end of input".to_string(), |found| format!(
+                    "found `{found}`"
+                ))
+        ),
+        _ => error.to_string(),
+    }
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Info => write!(f, "info"),
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} ({}..{})",
+            self.severity, self.message, self.span.start, self.span.end
+        )
+    }
+}
+
+impl Display for ChuffError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (stage, messages) = match self {
+            ChuffError::Lex(errors) => ("lexing", errors.iter().map(simple_message).collect()),
+            ChuffError::Parse(errors) => (
+                "parsing",
+                errors.iter().map(simple_message).collect::<Vec<_>>(),
+            ),
+        };
+        write!(
+            f,
+            "{} error(s) while {stage}: {}",
+            messages.len(),
+            messages.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for ChuffError {}
+
+/// Include Error
+///
+/// Returned by [`resolve_includes`](crate::includes::resolve_includes) when an included file could
+/// not be loaded.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IncludeError {
+    /// A file could not be read
+    Io {
+        /// The file that could not be read
+        path: PathBuf,
+        /// The reason reported by the operating system
+        message: String,
+    },
+    /// A file could not be lexed or parsed
+    Parse {
+        /// The file containing the error
+        path: PathBuf,
+        /// The errors reported while parsing
+        error: ChuffError,
+    },
+    /// A file includes itself, directly or through other files. The chain starts and ends with the
+    /// same file.
+    Cycle(Vec<PathBuf>),
+    /// Includes are nested more deeply than allowed
+    TooDeep {
+        /// The chain of includes from the root file to the file that exceeded the limit
+        chain: Vec<PathBuf>,
+        /// The maximum depth that was exceeded
+        max_depth: usize,
+    },
+}
+
+/// Formats an include chain as `a.huff -> b.huff -> c.huff`
+fn format_chain(chain: &[PathBuf]) -> String {
+    chain
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
+
+impl Display for IncludeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IncludeError::Io { path, message } => {
+                write!(f, "could not read {}: {message}", path.display())
+            }
+            IncludeError::Parse { path, error } => write!(f, "in {}: {error}", path.display()),
+            IncludeError::Cycle(chain) => write!(f, "include cycle: {}", format_chain(chain)),
+            IncludeError::TooDeep { chain, max_depth } => write!(
+                f,
+                "includes are nested more than {max_depth} deep: {}",
+                format_chain(chain)
+            ),
+        }
+    }
+}
+
+impl std::error::Error for IncludeError {}
+
+/// Merge Error
+///
+/// Returned by [`Contract::merge`](crate::utils::ast::Contract::merge) when both contracts define
+/// something with the same name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeError {
+    /// The kind and name of each conflicting definition
+    pub collisions: Vec<(&'static str, String)>,
+}
+
+impl Display for MergeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let collisions = self
+            .collisions
+            .iter()
+            .map(|(kind, name)| format!("{kind} `{name}`"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        write!(f, "both contracts define {collisions}")
+    }
+}
+
+impl std::error::Error for MergeError {}
+
+/// Build Error
+///
+/// Returned by [`ContractBuilder::build`](crate::utils::ast::ContractBuilder::build) when a
+/// definition's name is unusable.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct BuildError {
+    /// The kind and name of each definition whose name is not an identifier
+    pub invalid_names: Vec<(&'static str, String)>,
+    /// The kind and name of each definition that repeats the name of an earlier one
+    pub duplicates: Vec<(&'static str, String)>,
+}
+
+impl Display for BuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let problems = self
+            .invalid_names
+            .iter()
+            .map(|(kind, name)| format!("invalid {kind} name `{name}`"))
+            .chain(
+                self.duplicates
+                    .iter()
+                    .map(|(kind, name)| format!("duplicate {kind} `{name}`")),
+            )
+            .collect::<Vec<_>>()
+            .join(", ");
+        write!(f, "{problems}")
+    }
+}
+
+impl std::error::Error for BuildError {}
@@ -0,0 +1,317 @@
+//! Formatter
+//!
+//! Renders a parsed ast back into huff source code in a canonical layout.
+
+use crate::{
+    error::ChuffError,
+    parse_str,
+    parser::{Arg, Args, ArithOp, Ast, ConstantValue, MacroBody, MacroType, TableStatements},
+    span::Spanned,
+    utils::{
+        abi::{FunctionParam, FunctionType},
+        ast::TableKind,
+        bytes_util::literal_to_hex,
+    },
+};
+
+/// Indentation used for nested statements
+const INDENT: &str = "    ";
+
+/// The ast of a source before and after formatting
+pub type AstPair = (Vec<Spanned<Ast>>, Vec<Spanned<Ast>>);
+
+/// Format
+///
+/// Renders every top level definition, separated by a blank line. Nodes that were produced by
+/// error recovery do not retain their source, so they are rendered as comments.
+pub fn format(ast: &[Spanned<Ast>]) -> String {
+    ast.iter()
+        .map(|(item, _)| format_item(item))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+        + "\n"
+}
+
+/// Reparse
+///
+/// Parses the source, formats it, then parses the formatted output. Both asts are returned with
+/// their spans zeroed so that they can be compared for equality, which asserts that formatting
+/// did not change the meaning of the source.
+pub fn reparse(src: &str) -> Result<AstPair, ChuffError> {
+    let original = parse_str(src)?;
+    let reparsed = parse_str(&format(&original))?;
+
+    Ok((strip_spans(original), strip_spans(reparsed)))
+}
+
+/// Strip Spans
+///
+/// Replaces every span in the ast, including nested ones, with `0..0`.
+pub fn strip_spans(ast: Vec<Spanned<Ast>>) -> Vec<Spanned<Ast>> {
+    ast.into_iter()
+        .map(|(mut item, _)| {
+            match &mut item {
+                Ast::ConstantDefinition {
+                    name_span, value, ..
+                } => {
+                    *name_span = 0..0;
+                    if let ConstantValue::ConstantRef((_, span)) = value {
+                        *span = 0..0;
+                    }
+                }
+                Ast::MacroDefinition {
+                    name_span,
+                    macro_type,
+                    takes,
+                    returns,
+                    statements,
+                    args,
+                    ..
+                } => {
+                    *name_span = 0..0;
+                    macro_type.1 = 0..0;
+                    takes.1 = 0..0;
+                    returns.1 = 0..0;
+                    strip_arg_spans(args);
+                    for (statement, span) in statements.iter_mut() {
+                        *span = 0..0;
+                        match statement {
+                            MacroBody::MacroInvocation { args, .. }
+                            | MacroBody::BuiltinInvocation { args, .. } => strip_arg_spans(args),
+                            MacroBody::ArgsInvocation((_, span))
+                            | MacroBody::ConstantRef((_, span)) => *span = 0..0,
+                            _ => {}
+                        }
+                    }
+                }
+                Ast::TableDefinition {
+                    name_span,
+                    statements,
+                    ..
+                } => {
+                    *name_span = 0..0;
+                    statements.iter_mut().for_each(|(_, span)| *span = 0..0);
+                }
+                Ast::AbiFunction(function) => {
+                    function.state_mutability.1 = 0..0;
+                    function
+                        .inputs
+                        .iter_mut()
+                        .for_each(|(_, span)| *span = 0..0);
+                    function
+                        .outputs
+                        .iter_mut()
+                        .for_each(|(_, span)| *span = 0..0);
+                }
+                Ast::AbiEvent(event) => {
+                    event.inputs.iter_mut().for_each(|(_, span)| *span = 0..0);
+                }
+                Ast::AbiError(error) => {
+                    error.inputs.iter_mut().for_each(|(_, span)| *span = 0..0);
+                }
+                _ => {}
+            }
+            (item, 0..0)
+        })
+        .collect()
+}
+
+fn strip_arg_spans(args: &mut Args) {
+    for (arg, span) in args.iter_mut() {
+        *span = 0..0;
+        if let Arg::ConstantRef((_, span)) | Arg::ArgRef((_, span)) = arg {
+            *span = 0..0;
+        }
+    }
+}
+
+fn format_item(item: &Ast) -> String {
+    match item {
+        Ast::ParsingError { message, .. } => format!("// {message}"),
+        Ast::FileInclude { path, only } => match only {
+            Some(only) => format!("#include \"{path}\" {{ {} }}", only.join(", ")),
+            None => format!("#include \"{path}\""),
+        },
+        Ast::ConstantDefinition { name, value, .. } => {
+            let value = match value {
+                ConstantValue::Literal(lit) => literal_to_hex(lit, true, true),
+                ConstantValue::FreeStoragePointer => "FREE_STORAGE_POINTER()".to_string(),
+                ConstantValue::ConstantRef((name, _)) => format!("[{name}]"),
+                ConstantValue::Expression(first, rest) => {
+                    rest.iter()
+                        .fold(literal_to_hex(first, true, true), |out, (op, lit)| {
+                            let op = match op {
+                                ArithOp::Add => "+",
+                                ArithOp::Sub => "-",
+                                ArithOp::Mul => "*",
+                                ArithOp::Div => "/",
+                            };
+                            format!("{out} {op} {}", literal_to_hex(lit, true, true))
+                        })
+                }
+            };
+            format!("#define constant {name} = {value}")
+        }
+        Ast::MacroDefinition {
+            name,
+            macro_type,
+            takes,
+            returns,
+            statements,
+            args,
+            decorator,
+            ..
+        } => {
+            let keyword = match macro_type.0 {
+                MacroType::Macro => "macro",
+                MacroType::Fn => "fn",
+                MacroType::Test => "test",
+            };
+            let mut out = decorator
+                .as_ref()
+                .map(|decorator| decorator.to_huff_source() + "\n")
+                .unwrap_or_default();
+            out += &format!(
+                "#define {keyword} {name}({}) = takes ({}) returns ({}) {{\n",
+                format_args(args),
+                takes.0,
+                returns.0
+            );
+
+            // Statements following a jump destination are indented beneath it
+            let mut depth = 1;
+            for (statement, _) in statements {
+                if let MacroBody::JumpLabelDest(_) = statement {
+                    depth = 1;
+                }
+                out.push_str(&INDENT.repeat(depth));
+                out.push_str(&format_statement(statement));
+                out.push('\n');
+                if let MacroBody::JumpLabelDest(_) = statement {
+                    depth = 2;
+                }
+            }
+
+            out + "}"
+        }
+        Ast::TableDefinition {
+            name,
+            kind,
+            statements,
+            ..
+        } => {
+            let keyword = match kind {
+                TableKind::JumpTable => "jumptable",
+                TableKind::JumpTablePacked => "jumptablepacked",
+                TableKind::CodeTable => "codetable",
+            };
+            let mut out = format!("#define {keyword} {name}() = {{\n");
+            for (statement, _) in statements {
+                let statement = match statement {
+                    TableStatements::JumpLabel(label) => label.clone(),
+                    TableStatements::Code(code) => format!("0x{code}"),
+                    TableStatements::Error(message) => format!("/* {message} */"),
+                };
+                out.push_str(INDENT);
+                out.push_str(&statement);
+                out.push('\n');
+            }
+            out + "}"
+        }
+        Ast::AbiFunction(function) => {
+            let mut out = format!(
+                "#define function {}({}) {}",
+                function.name,
+                format_params(function.inputs.iter().map(|(p, _)| p)),
+                format_function_type(&function.state_mutability.0)
+            );
+            if !function.outputs.is_empty() {
+                out.push_str(&format!(
+                    " returns ({})",
+                    format_params(function.outputs.iter().map(|(p, _)| p))
+                ));
+            }
+            out
+        }
+        Ast::AbiEvent(event) => {
+            let inputs = event
+                .inputs
+                .iter()
+                .map(|(param, _)| {
+                    let mut parts = vec![param.kind.to_string()];
+                    if param.indexed {
+                        parts.push("indexed".to_string());
+                    }
+                    if !param.name.is_empty() {
+                        parts.push(param.name.clone());
+                    }
+                    parts.join(" ")
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            let anonymous = if event.anonymous { " anonymous" } else { "" };
+            format!("#define event {}({inputs}){anonymous}", event.name)
+        }
+        Ast::AbiError(error) => format!(
+            "#define error {}({})",
+            error.name,
+            format_params(error.inputs.iter().map(|(p, _)| p))
+        ),
+        Ast::AbiConstructor(constructor) => format!(
+            "#define constructor({})",
+            format_params(constructor.inputs.iter())
+        ),
+    }
+}
+
+fn format_statement(statement: &MacroBody) -> String {
+    match statement {
+        MacroBody::Opcode(opcode) => opcode.mnemonic().to_string(),
+        MacroBody::MacroInvocation { name, args } => format!("{name}({})", format_args(args)),
+        MacroBody::ArgsInvocation((arg, _)) => format!("<{arg}>"),
+        MacroBody::ConstantRef((name, _)) => format!("[{name}]"),
+        MacroBody::BuiltinInvocation { name, args } => format!("__{name}({})", format_args(args)),
+        MacroBody::JumpLabel(label) => label.clone(),
+        MacroBody::JumpLabelDest(label) => format!("{label}:"),
+        MacroBody::HexLiteral(lit) => literal_to_hex(lit, true, true),
+        MacroBody::AutoPush(lit) => format!("push {}", literal_to_hex(lit, true, true)),
+        MacroBody::UnexpectedToken(token) => token.clone(),
+    }
+}
+
+fn format_args(args: &Args) -> String {
+    args.iter()
+        .map(|(arg, _)| match arg {
+            Arg::Valid(name) => name.clone(),
+            Arg::ConstantRef((name, _)) => format!("[{name}]"),
+            Arg::ArgRef((name, _)) => format!("<{name}>"),
+            Arg::Number(num) => num.to_string(),
+            Arg::Literal(lit) => literal_to_hex(lit, true, true),
+            Arg::Invalid => "/* invalid */".to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn format_params<'a>(params: impl Iterator<Item = &'a FunctionParam>) -> String {
+    params
+        .map(|param| {
+            let mut parts = vec![param.kind.to_string()];
+            parts.extend(param.location.as_ref().map(ToString::to_string));
+            if !param.name.is_empty() {
+                parts.push(param.name.clone());
+            }
+            parts.join(" ")
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn format_function_type(function_type: &FunctionType) -> &'static str {
+    match function_type {
+        FunctionType::View => "view",
+        FunctionType::Payable => "payable",
+        FunctionType::NonPayable => "nonpayable",
+        FunctionType::Pure => "pure",
+    }
+}
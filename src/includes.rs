@@ -0,0 +1,172 @@
+//! Include Resolution
+//!
+//! Loads the files named by `#include` directives and flattens them into a single ast.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    error::IncludeError,
+    parse_str,
+    parser::{Arg, Ast, MacroBody},
+    span::Spanned,
+};
+
+/// The include depth used by tooling when no other limit is configured
+pub const DEFAULT_MAX_INCLUDE_DEPTH: usize = 64;
+
+/// Resolve Includes
+///
+/// Parses the file at `path` and replaces each `#include` with the definitions of the included
+/// file, recursively. Include paths are relative to the file that includes them. A file that
+/// includes itself is reported as a cycle.
+///
+/// An include that lists macros, `#include "lib.huff" { A, B }`, only takes those macros and the
+/// macros they use from the included file. Every other kind of definition is still taken, as the
+/// listed macros may depend on it. When a file is included more than once the selections are
+/// merged, so a plain include takes every macro, and each definition is kept once, where it was
+/// first included.
+///
+/// The root file is at depth zero, and an error naming the chain of includes is returned when a
+/// file would be included deeper than `max_depth`.
+///
+/// Spans are left relative to the file each definition was parsed from.
+pub fn resolve_includes(path: &Path, max_depth: usize) -> Result<Vec<Spanned<Ast>>, IncludeError> {
+    let mut resolver = Resolver {
+        max_depth,
+        chain: Vec::new(),
+        expanded: HashMap::new(),
+        files: 0,
+    };
+    let mut kept = HashSet::new();
+    Ok(resolver
+        .resolve(path.to_path_buf())?
+        .into_iter()
+        .filter(|(origin, _)| kept.insert(*origin))
+        .map(|(_, definition)| definition)
+        .collect())
+}
+
+/// A definition tagged with the file it was parsed from and its position in that file, so that
+/// a definition reached through several includes can be kept once
+type Tagged = ((usize, usize), Spanned<Ast>);
+
+struct Resolver {
+    max_depth: usize,
+    /// The files currently being resolved, from the root file down
+    chain: Vec<PathBuf>,
+    /// The flattened definitions of every file that has been expanded, by canonical path, before
+    /// any of its macros are selected
+    expanded: HashMap<PathBuf, Vec<Tagged>>,
+    /// The number of files that have been read, used to tag their definitions
+    files: usize,
+}
+
+impl Resolver {
+    fn resolve(&mut self, path: PathBuf) -> Result<Vec<Tagged>, IncludeError> {
+        let io_error = |path: &Path, error: std::io::Error| IncludeError::Io {
+            path: path.to_path_buf(),
+            message: error.to_string(),
+        };
+        let canonical = path.canonicalize().map_err(|e| io_error(&path, e))?;
+
+        if self.chain.iter().any(|included| {
+            included
+                .canonicalize()
+                .is_ok_and(|included| included == canonical)
+        }) {
+            let mut chain = self.chain.clone();
+            chain.push(path);
+            return Err(IncludeError::Cycle(chain));
+        }
+        if self.chain.len() > self.max_depth {
+            let mut chain = self.chain.clone();
+            chain.push(path);
+            return Err(IncludeError::TooDeep {
+                chain,
+                max_depth: self.max_depth,
+            });
+        }
+        if let Some(expanded) = self.expanded.get(&canonical) {
+            return Ok(expanded.clone());
+        }
+
+        let src = std::fs::read_to_string(&path).map_err(|e| io_error(&path, e))?;
+        let ast = parse_str(&src).map_err(|error| IncludeError::Parse {
+            path: path.clone(),
+            error,
+        })?;
+
+        let file = self.files;
+        self.files += 1;
+
+        let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        self.chain.push(path);
+        let mut flattened = Vec::new();
+        for (index, (item, span)) in ast.into_iter().enumerate() {
+            match item {
+                Ast::FileInclude { path, only } => {
+                    let included = self.resolve(dir.join(path))?;
+                    match only {
+                        Some(only) => flattened.extend(select_macros(included, &only)),
+                        None => flattened.extend(included),
+                    }
+                }
+                item => flattened.push(((file, index), (item, span))),
+            }
+        }
+        self.chain.pop();
+
+        self.expanded.insert(canonical, flattened.clone());
+        Ok(flattened)
+    }
+}
+
+/// Keeps the listed macros and every macro they invoke or pass as an argument, transitively.
+/// Definitions other than macros are always kept.
+fn select_macros(ast: Vec<Tagged>, only: &[String]) -> Vec<Tagged> {
+    let mut keep = HashSet::new();
+    let mut pending = only.to_vec();
+
+    while let Some(name) = pending.pop() {
+        if !keep.insert(name.clone()) {
+            continue;
+        }
+        for (_, (item, _)) in &ast {
+            let Ast::MacroDefinition {
+                name: definition,
+                statements,
+                ..
+            } = item
+            else {
+                continue;
+            };
+            if *definition != name {
+                continue;
+            }
+            for (statement, _) in statements {
+                let args = match statement {
+                    MacroBody::MacroInvocation { name, args } => {
+                        pending.push(name.clone());
+                        args
+                    }
+                    MacroBody::BuiltinInvocation { args, .. } => args,
+                    _ => continue,
+                };
+                pending.extend(args.iter().filter_map(|(arg, _)| match arg {
+                    Arg::Valid(name) => Some(name.clone()),
+                    _ => None,
+                }));
+            }
+        }
+    }
+
+    ast.into_iter()
+        .filter(|(_, (item, _))| match item {
+            Ast::MacroDefinition { name, .. } => keep.contains(name),
+            _ => true,
+        })
+        .collect()
+}
@@ -2,11 +2,19 @@ pub mod token;
 pub mod utils;
 
 use chumsky::prelude::*;
-use {token::Token, utils::key};
+use {
+    token::{CommentKind, Token, KEYWORDS_MAP},
+    utils::key,
+};
 
 use crate::{
     span::Spanned,
-    utils::{bytes_util::str_to_bytes32, opcodes::OPCODES_MAP, types::PrimitiveEVMType},
+    utils::{
+        builtins::BUILTINS_MAP,
+        bytes_util::{str_to_bytes32, str_to_bytes32_radix},
+        opcodes::{Opcode, OPCODES_MAP},
+        types::PrimitiveEVMType,
+    },
 };
 
 /// Chuff Lexer
@@ -16,6 +24,104 @@ pub fn lexer() -> impl Parser<char, Vec<Spanned<Token>>, Error = Simple<char>> {
     let other_whitespace = lex_non_newline_whitespace();
     let newline = lex_newline_and_comments();
 
+    // Single token can be the below
+    let token = lex_token()
+        .or(newline.clone())
+        // Skip invalid characters
+        .recover_with(skip_then_retry_until([]));
+
+    // Attach spans to all of the resolved tokens
+    let tokens = token
+        .map_with_span(|tok, span| (tok, span))
+        .padded_by(other_whitespace.repeated())
+        .repeated() // make sure there's a newline at the end of input
+        .chain(
+            newline
+                .clone()
+                // if there isn't a newline at the end of input, just insert a fake newline token
+                .or(end().rewind().to(Token::Newline))
+                // the fake newline is given an empty span at the end of input
+                .map_with_span(|tok, span| (tok, span)),
+        )
+        .then_ignore(end());
+
+    // Leading blank lines produce no tokens
+    lex_byte_order_mark()
+        .ignore_then(
+            lex_non_newline_whitespace()
+                .or(newline.ignored())
+                .repeated(),
+        )
+        .ignore_then(tokens)
+        .map(apply_definition_context)
+}
+
+/// Trivia Lexer
+///
+/// Lexes the same tokens as [`lexer`], but keeps each comment as a [`Token::Comment`] and each
+/// line ending as its own [`Token::Newline`] rather than collapsing them, for tooling such as the
+/// formatter that needs to know where comments were written.
+pub fn trivia_lexer() -> impl Parser<char, Vec<Spanned<Token>>, Error = Simple<char>> {
+    let other_whitespace = lex_non_newline_whitespace();
+
+    let token = lex_token()
+        .or(lex_comment())
+        .or(text::newline().to(Token::Newline))
+        .recover_with(skip_then_retry_until([]));
+
+    lex_byte_order_mark()
+        .ignore_then(
+            token
+                .map_with_span(|tok, span| (tok, span))
+                .padded_by(other_whitespace.repeated())
+                .repeated(),
+        )
+        .then_ignore(end())
+        .map(tag_trailing_comments)
+        .map(apply_definition_context)
+}
+
+/// Lex Lossless
+///
+/// Lexes the same tokens as [`trivia_lexer`], and also emits a [`Token::Whitespace`] for each run
+/// of spaces and tabs, so that the source can be rebuilt exactly by concatenating the text covered
+/// by each token's span. Characters that could not be lexed, including a byte order mark, are
+/// emitted as [`Token::Unknown`] rather than skipped.
+pub fn lex_lossless(src: &str) -> Vec<Spanned<Token>> {
+    // Reports an error so that a token that was lexed with an error is still preferred
+    let unknown = any()
+        .validate(|c: char, span, emit| {
+            emit(Simple::custom(span, format!("Unexpected character `{c}`")));
+            Token::Unknown(c.to_string())
+        })
+        .labelled("unknown");
+
+    let token = lex_token()
+        .or(lex_comment())
+        .or(text::newline().to(Token::Newline))
+        .or(one_of(" \t").repeated().at_least(1).to(Token::Whitespace))
+        .or(unknown);
+
+    let (tokens, _) = token
+        .map_with_span(|tok, span| (tok, span))
+        .repeated()
+        .then_ignore(end())
+        .map(tag_trailing_comments)
+        .map(apply_definition_context)
+        .parse_recovery(src);
+    tokens.unwrap_or_default()
+}
+
+/// Skips a UTF-8 byte order mark at the start of a file. It is skipped rather than removed from
+/// the source, so spans still count it as a character.
+fn lex_byte_order_mark() -> impl Parser<char, (), Error = Simple<char>> + Clone {
+    just('\u{FEFF}').or_not().ignored()
+}
+
+/// Lex Token
+///
+/// Lexes any single token other than newlines and comments.
+fn lex_token() -> impl Parser<char, Token, Error = Simple<char>> {
     let define = lex_define();
     let include = lex_include();
     let hex_literals = lex_literals();
@@ -27,8 +133,7 @@ pub fn lexer() -> impl Parser<char, Vec<Spanned<Token>>, Error = Simple<char>> {
     let opcode_or_ident = lex_opcode_or_ident();
     let number = lex_number();
 
-    // Single token can be the below
-    let token = define
+    define
         .or(evm_type)
         .or(free_storage_pointer)
         .or(include)
@@ -38,26 +143,137 @@ pub fn lexer() -> impl Parser<char, Vec<Spanned<Token>>, Error = Simple<char>> {
         .or(operators)
         .or(opcode_or_ident)
         .or(number)
-        .or(newline.clone())
-        // Skip invalid characters
-        .recover_with(skip_then_retry_until([]));
+}
 
-    // Attach spans to all of the resolved tokens
-    let tokens = token
-        .map_with_span(|tok, span| (tok, span))
-        .padded_by(other_whitespace.repeated())
-        .repeated() // make sure there's a newline at the end of input
-        .chain(
-            newline
-                .clone()
-                // if there isn't a newline at the end of input, just insert a fake newline token
-                .or(end().rewind().to(Token::Newline))
-                // make sure to attach a span! this might be incorrect for the fake newlines
-                .map_with_span(|tok, span| (tok, span)),
-        )
-        .then_ignore(end());
+/// Lex Comment
+///
+/// Lexes a single line or multiline comment, including its delimiters. A line comment does not
+/// consume the line ending that terminates it.
+fn lex_comment() -> impl Parser<char, Token, Error = Simple<char>> {
+    let line_comment = just("//")
+        .ignore_then(filter(|c| *c != '\n' && *c != '\r').repeated())
+        .collect::<String>()
+        .map(|body| Token::Comment(CommentKind::Line, format!("//{body}")));
+
+    let block_comment = just("/*")
+        .ignore_then(take_until(just("*/")))
+        .map(|(body, _)| {
+            let body: String = body.into_iter().collect();
+            Token::Comment(CommentKind::Block, format!("/*{body}*/"))
+        });
+
+    line_comment.or(block_comment).labelled("comment")
+}
+
+/// Marks comments that follow another token on the same line as trailing comments
+fn tag_trailing_comments(tokens: Vec<Spanned<Token>>) -> Vec<Spanned<Token>> {
+    let mut line_has_token = false;
+
+    tokens
+        .into_iter()
+        .map(|(token, span)| {
+            let token = match token {
+                Token::Newline => {
+                    line_has_token = false;
+                    Token::Newline
+                }
+                Token::Comment(kind, text) => {
+                    let kind = match line_has_token {
+                        true => CommentKind::Trailing,
+                        false => kind,
+                    };
+                    // A block comment spanning several lines ends on a line of its own
+                    line_has_token = !text.contains(['\n', '\r']);
+                    Token::Comment(kind, text)
+                }
+                Token::Whitespace => Token::Whitespace,
+                token => {
+                    line_has_token = true;
+                    token
+                }
+            };
+            (token, span)
+        })
+        .collect()
+}
 
-    newline.clone().or_not().ignore_then(tokens)
+/// The kind of definition a token belongs to, as tracked by [`apply_definition_context`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DefinitionContext {
+    /// Following `#define`, before the kind of definition is known
+    Define,
+    /// A function, event, error or constructor declaration
+    Abi,
+    /// The signature of a macro, before its `=`
+    MacroSignature,
+    /// The body of a macro
+    MacroBody,
+    /// Anything else
+    Other,
+}
+
+/// Apply Definition Context
+///
+/// Tokens are lexed without knowing where they are, so a word that is both an opcode and a type
+/// or name takes a single meaning everywhere. This second pass reinterprets them by the kind of
+/// definition they appear in: inside an ABI declaration opcodes are names, such as the function in
+/// `#define function balance(address owner)`, and inside a macro body `address` is the opcode
+/// rather than the type. Data locations such as `memory` are only keywords within ABI
+/// declarations, elsewhere they are identifiers that can name labels, macros and constants. `test`
+/// is only a keyword directly after `#define`. A definition lasts until the next `#define`,
+/// `#include` or decorator. Comments, whitespace and line endings are passed through untouched.
+fn apply_definition_context(tokens: Vec<Spanned<Token>>) -> Vec<Spanned<Token>> {
+    let mut context = DefinitionContext::Other;
+
+    tokens
+        .into_iter()
+        .map(|(token, span)| {
+            if matches!(
+                token,
+                Token::Comment(..) | Token::Whitespace | Token::Newline
+            ) {
+                return (token, span);
+            }
+            let token = match (token, context) {
+                (Token::Ident(ident), DefinitionContext::Define) if ident == "test" => Token::Test,
+                (token, _) => token,
+            };
+            context = match (&token, context) {
+                (Token::Define, _) => DefinitionContext::Define,
+                (Token::Include | Token::Pound, _) => DefinitionContext::Other,
+                (
+                    Token::Function | Token::Event | Token::Error | Token::Constructor,
+                    DefinitionContext::Define,
+                ) => DefinitionContext::Abi,
+                (Token::Macro | Token::Fn | Token::Test, DefinitionContext::Define) => {
+                    DefinitionContext::MacroSignature
+                }
+                (_, DefinitionContext::Define) => DefinitionContext::Other,
+                (Token::Assign, DefinitionContext::MacroSignature) => DefinitionContext::MacroBody,
+                (_, context) => context,
+            };
+
+            let token = match (token, context) {
+                (Token::Opcode(opcode), DefinitionContext::Abi) => {
+                    Token::Ident(opcode.mnemonic().to_string())
+                }
+                (Token::PrimitiveType(PrimitiveEVMType::Address), DefinitionContext::MacroBody) => {
+                    Token::Opcode(Opcode::Address)
+                }
+                (Token::Calldata, context) if context != DefinitionContext::Abi => {
+                    Token::Ident("calldata".to_string())
+                }
+                (Token::Memory, context) if context != DefinitionContext::Abi => {
+                    Token::Ident("memory".to_string())
+                }
+                (Token::Storage, context) if context != DefinitionContext::Abi => {
+                    Token::Ident("storage".to_string())
+                }
+                (token, _) => token,
+            };
+            (token, span)
+        })
+        .collect()
 }
 
 /// Lex Operators
@@ -76,6 +292,19 @@ pub fn lex_operators() -> impl Parser<char, Token, Error = Simple<char>> {
         .or(just('>').to(Token::RightAngle))
         .or(just(',').to(Token::Comma))
         .or(just(":").to(Token::Colon))
+        // `#define` and `#include` are lexed first, any other `#` opens a decorator
+        .or(just('#').to(Token::Pound))
+        // Arithmetic used in constant expressions. Numbers are never signed, so `-` is always an
+        // operator. A `/` that starts a comment is left to the comment lexers.
+        .or(just('+').to(Token::Add))
+        .or(just('-').to(Token::Sub))
+        .or(just('*').to(Token::Mul))
+        .or(just('/')
+            .ignore_then(one_of("/*").rewind().or_not())
+            .try_map(|next, span| match next {
+                Some(_) => Err(Simple::custom(span, "Expected division")),
+                None => Ok(Token::Div),
+            }))
 }
 
 /// Lex Literals
@@ -89,8 +318,10 @@ pub fn lex_literals() -> impl Parser<char, Token, Error = Simple<char>> {
         .ignore_then(text::digits(16))
         .map(|num: String| {
             // work out when to return it as an identifier
-            if num.len() < 64 {
-                Token::Literal(str_to_bytes32(&num))
+            if num.len() <= 64 {
+                Token::Literal(
+                    str_to_bytes32(&num).expect("at most 64 hex digits always fit in 32 bytes"),
+                )
             } else {
                 Token::Code(num.clone())
             }
@@ -125,13 +356,22 @@ pub fn lex_evm_type() -> impl Parser<char, Token, Error = Simple<char>> {
 /// Lex Builtin Function
 ///
 /// Builtin functions are interpreted as identifiers that are proceeded by two underscores `__`.
-///
-/// TODO: Currently any identifier is valid and discrimination is done at the parsing state, should it be moved forward?
+/// Names that are not in the [`BUILTINS_MAP`] are reported and lexed as [`Token::Unknown`].
 pub fn lex_builtin_function() -> impl Parser<char, Token, Error = Simple<char>> {
     just('_')
         .ignore_then(just('_'))
         .ignore_then(text::ident())
-        .map(Token::BuiltinFunction)
+        .validate(|name: String, span, emit| {
+            if BUILTINS_MAP.contains_key(format!("__{name}").as_str()) {
+                Token::BuiltinFunction(name)
+            } else {
+                emit(Simple::custom(
+                    span,
+                    format!("Unknown builtin function `__{name}`"),
+                ));
+                Token::Unknown(format!("__{name}"))
+            }
+        })
 }
 
 /// Lex ABI Type
@@ -147,14 +387,18 @@ pub fn lex_abi_type() -> impl Parser<char, PrimitiveEVMType, Error = Simple<char
 
 /// Lex Array
 ///
-/// Used to determine if an abi type is an array
+/// Used to determine if an abi type is an array. Dimensions are given in source order, with `0`
+/// for a dynamic dimension, so `uint256[][2]` lexes as `[0, 2]`. This is the order
+/// [`FunctionParamType::Array`](crate::utils::abi::FunctionParamType::Array) stores and displays.
 pub fn lex_array() -> impl Parser<char, Vec<usize>, Error = Simple<char>> {
     just('[')
         .ignore_then(text::digits(10).or_not())
         .then_ignore(just(']'))
-        .map(|num: Option<String>| match num {
-            Some(x) => x.parse().unwrap(),
-            None => 0,
+        .try_map(|num: Option<String>, span| match num {
+            Some(x) => x
+                .parse()
+                .map_err(|_| Simple::custom(span, format!("Array size `{x}` is too large"))),
+            None => Ok(0),
         })
         .repeated()
 }
@@ -238,58 +482,82 @@ pub fn lex_int() -> impl Parser<char, PrimitiveEVMType, Error = Simple<char>> {
 
 /// Lex Number
 ///
-/// Lex number has lower precedence than lex literal, as it is used to parse them
-/// validly, but show a warning to the user
+/// Lexes a decimal number. Numbers that fit in a `usize`, such as stack annotations, are a
+/// [`Token::Num`] and larger ones, such as the immediate of `push32`, are a [`Token::Literal`].
+/// Letters glued to the digits, as in `1f`, and numbers that do not fit in 32 bytes are reported
+/// and lexed as a [`Token::Unknown`].
 pub fn lex_number() -> impl Parser<char, Token, Error = Simple<char>> {
-    text::digits(16).map(|n: String| Token::Num(n.parse().unwrap_or(0)))
+    text::digits(10)
+        .then(filter(char::is_ascii_alphanumeric).repeated())
+        .map(|(digits, rest): (String, Vec<char>)| digits + &rest.into_iter().collect::<String>())
+        .validate(|n, span, emit| match str_to_bytes32_radix(&n, 10) {
+            Ok(literal) => n.parse().map_or(Token::Literal(literal), Token::Num),
+            Err(err) => {
+                emit(Simple::custom(
+                    span,
+                    format!("Invalid decimal number `{n}`, {err}"),
+                ));
+                Token::Unknown(n)
+            }
+        })
 }
 
 /// Lex Opcode or identifier
 ///
 /// Steps:
 ///     1. Attempt to parse all identifiers as opcodes.
-///     2. If not an opcode, attempt to parse it as a keyword.
+///     2. If not an opcode, look it up in the [`KEYWORDS_MAP`].
 ///     3. If not a keyword, mark as an arbitrary identifier
+///
+/// The whole identifier is looked up, digits included, so `push32`, `dup16`, `swap16` and `log4`
+/// are single opcodes rather than a mnemonic followed by a number. A numbered mnemonic outside of
+/// the opcode's range, such as `log5` or `push33`, is an identifier.
 pub fn lex_opcode_or_ident() -> impl Parser<char, Token, Error = Simple<char>> {
     text::ident()
-        .map(|ident: String| {
-            let is_opcode = OPCODES_MAP.get(&ident);
-
-            match is_opcode {
-                Some(opcode) => Token::Opcode(*opcode),
-                None => match ident.as_str() {
-                    "macro" => Token::Macro,
-                    "calldata" => Token::Calldata,
-                    "memory" => Token::Memory,
-                    "storage" => Token::Storage,
-                    "constant" => Token::Constant,
-                    "fn" => Token::Fn,
-                    "function" => Token::Function,
-                    "event" => Token::Event,
-                    "error" => Token::Error,
-                    "takes" => Token::Takes,
-                    "returns" => Token::Returns,
-                    "codetable" => Token::CodeTable,
-                    "jumptable" => Token::JumpTable,
-                    "jumptablepacked" => Token::JumpTablePacked,
-
-                    // TODO: do we only want these to lex as these tokens in a specific context?
-                    "pure" => Token::Pure,
-                    "payable" => Token::Payable,
-                    "nonpayable" => Token::NonPayable,
-                    "view" => Token::View,
-                    "indexed" => Token::Indexed,
-
-                    _ => Token::Ident(ident),
-                },
+        // The `__` prefix is reserved for builtins, which are validated by `lex_builtin_function`
+        .try_map(|ident: String, span| match ident.starts_with("__") {
+            true => Err(Simple::custom(span, "Identifiers may not start with `__`")),
+            false => Ok(ident),
+        })
+        // An identifier directly followed by a colon is a jump destination, even when it is spelt
+        // like an opcode or keyword such as `return:`
+        .then(just(':').rewind().or_not())
+        .map(|(ident, colon)| match (colon, OPCODES_MAP.get(&ident)) {
+            (Some(_), _) => Token::Ident(ident),
+            (None, Some(opcode)) => Token::Opcode(*opcode),
+            (None, None) => KEYWORDS_MAP
+                .get(&ident)
+                .cloned()
+                .unwrap_or(Token::Ident(ident)),
+        })
+        // Without whitespace an opcode and the literal after it, such as `push10x20`, lex as a
+        // single identifier that would otherwise be silently treated as a jump label
+        .validate(|token, span, emit| {
+            if let Token::Ident(ident) = &token {
+                if let Some((opcode, literal)) = split_glued_literal(ident) {
+                    emit(Simple::custom(
+                        span,
+                        format!("Expected whitespace between `{opcode}` and `{literal}`"),
+                    ));
+                    return Token::Unknown(ident.clone());
+                }
             }
+            token
         })
         // TODO: this line came from copilot im not to confident in it
         // .unwrap_or_else(|| Token::Unknown(ident))
-        .padded()
+        // Not padded, padding would consume a leading newline and steal the token that follows it
         .labelled("opcode")
 }
 
+/// Splits an identifier made of an opcode directly followed by a hex literal into the two
+fn split_glued_literal(ident: &str) -> Option<(&str, &str)> {
+    let start = ident.find("0x")?;
+    let (opcode, literal) = ident.split_at(start);
+    let is_hex = literal.len() > 2 && literal[2..].chars().all(|c| c.is_ascii_hexdigit());
+    (OPCODES_MAP.contains_key(opcode) && is_hex).then_some((opcode, literal))
+}
+
 /// Lex Define
 ///
 /// Separately lex the define keyword due to the leading '#'
@@ -322,6 +590,13 @@ pub fn lex_free_storage_pointer() -> impl Parser<char, Token, Error = Simple<cha
 /// This function also handles tossing out comments. Since comments can only occur
 /// at either the end of a line or completely on their own line, they should be
 /// collapsed down into newline tokens.
+///
+/// A line comment ends at the line ending (`\n`, `\r\n` or a lone `\r`) or at the end of input,
+/// whichever comes first.
+///
+/// Line endings inside of a multiline comment, including the carriage return of a CRLF, are
+/// consumed as part of the comment body. The comment and the line endings around it therefore
+/// collapse into a single newline token spanning all of them.
 fn lex_newline_and_comments() -> impl Parser<char, Token, Error = Simple<char>> + Clone {
     let other_whitespace = lex_non_newline_whitespace();
 
@@ -331,8 +606,10 @@ fn lex_newline_and_comments() -> impl Parser<char, Token, Error = Simple<char>>
         .to(())
         .labelled("multiline_comment");
 
+    // The line ending is left to be lexed as a newline, so a comment on the last line of a file
+    // without a trailing newline is still terminated
     let comment = just("//")
-        .then(take_until(just('\n')))
+        .then(filter(|c| *c != '\n' && *c != '\r').repeated())
         .padded_by(other_whitespace.repeated())
         .to(())
         .labelled("comment");
@@ -1,20 +1,83 @@
 use std::fmt::Display;
 
-use crate::utils::{opcodes::Opcode, types::PrimitiveEVMType};
+use phf::phf_map;
+
+use crate::utils::{bytes_util::literal_to_hex, opcodes::Opcode, types::PrimitiveEVMType};
 
 pub type Literal = [u8; 32];
 
+/// Identifiers that lex as keywords rather than as [`Token::Ident`]
+///
+/// Opcodes take precedence, so a keyword that shares its name with an opcode never lexes as the
+/// keyword.
+pub static KEYWORDS_MAP: phf::Map<&'static str, Token> = phf_map! {
+    "macro" => Token::Macro,
+    "calldata" => Token::Calldata,
+    "memory" => Token::Memory,
+    "storage" => Token::Storage,
+    "constant" => Token::Constant,
+    "fn" => Token::Fn,
+    "function" => Token::Function,
+    "event" => Token::Event,
+    "error" => Token::Error,
+    "constructor" => Token::Constructor,
+    "takes" => Token::Takes,
+    "returns" => Token::Returns,
+    "codetable" => Token::CodeTable,
+    "jumptable" => Token::JumpTable,
+    "jumptablepacked" => Token::JumpTablePacked,
+    // TODO: do we only want these to lex as these tokens in a specific context?
+    "pure" => Token::Pure,
+    "payable" => Token::Payable,
+    "nonpayable" => Token::NonPayable,
+    "view" => Token::View,
+    "indexed" => Token::Indexed,
+};
+
+/// Where a comment was written
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CommentKind {
+    /// A `//` comment on its own line
+    Line,
+    /// A `/* */` comment that starts its own line
+    Block,
+    /// A comment of either style that follows another token on the same line
+    Trailing,
+}
+
+/// A broad grouping of tokens, for syntax highlighting
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TokenCategory {
+    /// Keywords, data locations and builtin functions
+    Keyword,
+    /// An opcode
+    Opcode,
+    /// Hex literals, numbers, strings and code
+    Literal,
+    /// Identifiers and labels
+    Ident,
+    /// Arithmetic operators, `=` and angle brackets
+    Operator,
+    /// Delimiters, commas, colons and pounds
+    Punctuation,
+    /// A comment
+    Comment,
+    /// An abi type
+    Type,
+    /// Newlines, whitespace and the end of input, which are not highlighted
+    Whitespace,
+}
+
 /// The kind of token
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Token {
     /// EOF Token
     Eof,
-    /// A Comment
-    Comment(String),
+    /// A Comment, only produced by the trivia lexer
+    Comment(CommentKind, String),
     /// A newline
     Newline,
-    /// Division
-    /// Lexing done at the comment level due to clash
+    /// Division, a `/` that does not start a comment
     Div,
     /// "#define" keyword
     Define,
@@ -34,6 +97,8 @@ pub enum Token {
     Constant,
     /// "error" keyword
     Error,
+    /// "constructor" keyword
+    Constructor,
     /// "takes" keyword
     Takes,
     /// "returns" keyword
@@ -121,13 +186,78 @@ pub enum Token {
     Unknown(String),
 }
 
+impl Token {
+    /// The category of the token, for syntax highlighting
+    pub fn category(&self) -> TokenCategory {
+        match self {
+            Token::Define
+            | Token::Include
+            | Token::Macro
+            | Token::Fn
+            | Token::Test
+            | Token::Function
+            | Token::Event
+            | Token::Constant
+            | Token::Error
+            | Token::Constructor
+            | Token::Takes
+            | Token::Returns
+            | Token::View
+            | Token::Pure
+            | Token::Payable
+            | Token::NonPayable
+            | Token::Indexed
+            | Token::FreeStoragePointer
+            | Token::JumpTable
+            | Token::JumpTablePacked
+            | Token::CodeTable
+            | Token::BuiltinFunction(_)
+            | Token::Calldata
+            | Token::Memory
+            | Token::Storage => TokenCategory::Keyword,
+            Token::Opcode(_) => TokenCategory::Opcode,
+            Token::Num(_) | Token::Str(_) | Token::Literal(_) | Token::Code(_) | Token::Path(_) => {
+                TokenCategory::Literal
+            }
+            Token::Ident(_) | Token::Label(_) | Token::Unknown(_) => TokenCategory::Ident,
+            Token::Div
+            | Token::Assign
+            | Token::LeftAngle
+            | Token::RightAngle
+            | Token::Add
+            | Token::Sub
+            | Token::Mul => TokenCategory::Operator,
+            Token::OpenParen
+            | Token::CloseParen
+            | Token::OpenBracket
+            | Token::CloseBracket
+            | Token::OpenBrace
+            | Token::CloseBrace
+            | Token::Comma
+            | Token::Colon
+            | Token::Pound => TokenCategory::Punctuation,
+            Token::Comment(..) => TokenCategory::Comment,
+            Token::PrimitiveType(_) | Token::ArrayType(..) => TokenCategory::Type,
+            Token::Eof | Token::Newline | Token::Whitespace => TokenCategory::Whitespace,
+        }
+    }
+
+    /// Returns the source spelling of the token if it is a keyword in the [`KEYWORDS_MAP`]
+    pub fn keyword(&self) -> Option<&'static str> {
+        KEYWORDS_MAP
+            .entries()
+            .find(|(_, token)| *token == self)
+            .map(|(keyword, _)| *keyword)
+    }
+}
+
 impl Display for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Token::Eof => write!(f, "EOF"),
-            Token::Comment(c) => write!(f, "Comment({})", c),
+            Token::Comment(_, c) => write!(f, "Comment({})", c),
             Token::Newline => write!(f, "Newline"),
-            Token::Div => write!(f, "Div"),
+            Token::Div => write!(f, "/"),
             Token::Define => write!(f, "Define"),
             Token::Include => write!(f, "Include"),
             Token::Macro => write!(f, "Macro"),
@@ -137,6 +267,7 @@ impl Display for Token {
             Token::Event => write!(f, "Event"),
             Token::Constant => write!(f, "Constant"),
             Token::Error => write!(f, "Error"),
+            Token::Constructor => write!(f, "Constructor"),
             Token::Takes => write!(f, "Takes"),
             Token::Returns => write!(f, "Returns"),
             Token::View => write!(f, "View"),
@@ -155,21 +286,16 @@ impl Display for Token {
             Token::CloseBrace => write!(f, "}}"),
             Token::LeftAngle => write!(f, "<"),
             Token::RightAngle => write!(f, ">"),
-            Token::Add => write!(f, "Add"),
-            Token::Sub => write!(f, "Sub"),
-            Token::Mul => write!(f, "Mul"),
+            Token::Add => write!(f, "+"),
+            Token::Sub => write!(f, "-"),
+            Token::Mul => write!(f, "*"),
             Token::Comma => write!(f, ","),
-            Token::Colon => write!(f, ";"),
+            Token::Colon => write!(f, ":"),
             Token::Pound => write!(f, "#"),
             Token::Num(n) => write!(f, "Num({})", n),
             Token::Whitespace => write!(f, "Whitespace"),
             Token::Str(s) => write!(f, "Str({})", s),
-            Token::Literal(l) => write!(
-                f,
-                "Literal({})",
-                l.iter()
-                    .fold(String::new(), |acc, x| acc + &format!("{:02x}", x))
-            ),
+            Token::Literal(l) => write!(f, "Literal({})", literal_to_hex(l, false, false)),
             Token::Code(c) => write!(f, "Code({})", c),
             Token::Opcode(o) => write!(f, "Opcode({})", o),
             Token::Label(l) => write!(f, "Label({})", l),
@@ -1,6 +1,276 @@
-// pub mod analysis;
-// pub mod error;
+// Every parser error is a `Simple<Token>`, which is larger than clippy would like
+#![allow(clippy::result_large_err)]
+
+pub mod analysis;
+pub mod error;
+#[cfg(feature = "fmt")]
+pub mod formatter;
+pub mod includes;
 pub mod lexer;
+pub mod lowering;
 pub mod parser;
 pub mod span;
+pub mod transform;
 pub mod utils;
+
+use std::collections::BTreeSet;
+
+use chumsky::{prelude::*, Stream};
+
+use crate::{
+    error::{ChuffError, Diagnostic},
+    lexer::{lexer, token::Token},
+    parser::{
+        parser_with_options, Arg, Ast, ConstantValue, MacroBody, ParserOptions, TableStatements,
+    },
+    span::{offset_to_line_col, LineCol, Span, Spanned},
+};
+
+/// Parse Str
+///
+/// Lexes and parses a huff source string, failing if either stage reports an error.
+pub fn parse_str(src: &str) -> Result<Vec<Spanned<Ast>>, ChuffError> {
+    parse_str_with_options(src, ParserOptions::default())
+}
+
+/// Parse Str With Options
+///
+/// [`parse_str`] with non default parser options.
+pub fn parse_str_with_options(
+    src: &str,
+    options: ParserOptions,
+) -> Result<Vec<Spanned<Ast>>, ChuffError> {
+    let src_len = src.chars().count();
+
+    let tokens = lexer().parse(src).map_err(ChuffError::Lex)?;
+    let (ast, errors) = parse_tokens(tokens, src_len, options);
+    match ast {
+        Some(ast) if errors.is_empty() => Ok(ast),
+        _ => Err(ChuffError::Parse(errors)),
+    }
+}
+
+/// Parses lexed tokens with recovery. Newlines are only meaningful to the lexer, so they are
+/// removed before the tokens reach the parser.
+fn parse_tokens(
+    tokens: Vec<Spanned<Token>>,
+    src_len: usize,
+    options: ParserOptions,
+) -> (Option<Vec<Spanned<Ast>>>, Vec<Simple<Token>>) {
+    let clean_tokens = tokens
+        .into_iter()
+        .filter(|(token, _)| *token != Token::Newline);
+    parser_with_options(options)
+        .parse_recovery(Stream::from_iter(src_len..src_len + 1, clean_tokens))
+}
+
+/// Diagnostics
+///
+/// Runs the whole pipeline over a huff source string, recovering from errors where possible, and
+/// collects the lexer and parser errors along with the lowering and analysis diagnostics.
+pub fn diagnostics(src: &str) -> Vec<Diagnostic> {
+    let src_len = src.chars().count();
+
+    let (tokens, lex_errors) = lexer().parse_recovery(src);
+    let mut diagnostics: Vec<_> = lex_errors.iter().map(Diagnostic::from_simple).collect();
+    let Some(tokens) = tokens else {
+        return diagnostics;
+    };
+
+    let (ast, parse_errors) = parse_tokens(tokens, src_len, ParserOptions::default());
+    diagnostics.extend(parse_errors.iter().map(Diagnostic::from_simple));
+    if let Some(ast) = ast {
+        diagnostics.extend(lowering::lower(&ast).1);
+        diagnostics.extend(analysis::analyze(&ast));
+    }
+
+    diagnostics
+}
+
+/// Diagnostics With Positions
+///
+/// Collects the [`diagnostics`] of a source string, with each span converted into the line and
+/// column of its start and end.
+pub fn diagnostics_with_positions(src: &str) -> Vec<(LineCol, LineCol, String)> {
+    diagnostics(src)
+        .into_iter()
+        .map(|diagnostic| {
+            (
+                offset_to_line_col(src, diagnostic.span.start),
+                offset_to_line_col(src, diagnostic.span.end),
+                diagnostic.message,
+            )
+        })
+        .collect()
+}
+
+/// Expected At
+///
+/// Returns the tokens the parser would accept at a character offset, for editor autocomplete.
+/// The expected sets of every parser error whose span covers the offset are combined. Keywords
+/// and opcodes are given as they are written in source.
+pub fn expected_at(src: &str, offset: usize) -> Vec<String> {
+    let src_len = src.chars().count();
+
+    let (tokens, _) = lexer().parse_recovery(src);
+    let Some(tokens) = tokens else {
+        return Vec::new();
+    };
+
+    let (_, errors) = parse_tokens(tokens, src_len, ParserOptions::default());
+    let expected: BTreeSet<String> = errors
+        .iter()
+        .filter(|error| error.span().start <= offset && offset <= error.span().end)
+        .flat_map(|error| error.expected())
+        .map(|token| match token {
+            Some(Token::Opcode(opcode)) => opcode.mnemonic().to_string(),
+            Some(token) => token
+                .keyword()
+                .map(str::to_string)
+                .unwrap_or_else(|| token.to_string()),
+            None => "end of input".to_string(),
+        })
+        .collect();
+
+    expected.into_iter().collect()
+}
+
+/// Definition At
+///
+/// Returns the top level definition whose span contains a character offset, for editor features
+/// such as hover and go to definition.
+pub fn definition_at(ast: &[Spanned<Ast>], offset: usize) -> Option<&Ast> {
+    ast.iter()
+        .find(|(_, span)| span.contains(&offset))
+        .map(|(item, _)| item)
+}
+
+/// Definitions Overlapping
+///
+/// Returns the indices of the top level definitions whose spans intersect an edited range, so
+/// that an editor can reparse only their source. A definition that ends where the edit starts,
+/// or starts where it ends, is included, as an insertion there may extend it.
+pub fn definitions_overlapping(ast: &[Spanned<Ast>], edit: Span) -> Vec<usize> {
+    ast.iter()
+        .enumerate()
+        .filter(|(_, (_, span))| span.start <= edit.end && edit.start <= span.end)
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// Statement At
+///
+/// Returns the macro body statement whose span contains a character offset, along with its span.
+pub fn statement_at(ast: &[Spanned<Ast>], offset: usize) -> Option<&Spanned<MacroBody>> {
+    match definition_at(ast, offset)? {
+        Ast::MacroDefinition { statements, .. } => {
+            statements.iter().find(|(_, span)| span.contains(&offset))
+        }
+        _ => None,
+    }
+}
+
+/// References
+///
+/// Returns the spans of every place a name is used, for editor features such as rename. Each span
+/// covers only the name, without the brackets of `[CONST]` or `<arg>`. Definitions, constant
+/// aliases, macro bodies, invocation arguments, macro parameters and jump tables are searched.
+/// Spans are in source order.
+pub fn references(ast: &[Spanned<Ast>], name: &str) -> Vec<Span> {
+    // The name is the first token of these spans, so its extent is known
+    let name_at = |span: &Span| span.start..span.start + name.chars().count();
+    let mut spans = Vec::new();
+
+    for (item, _) in ast {
+        match item {
+            Ast::MacroDefinition {
+                name: definition,
+                name_span,
+                statements,
+                args,
+                ..
+            } => {
+                if definition == name {
+                    spans.push(name_span.clone());
+                }
+                spans.extend(arg_references(args, name));
+                for (statement, span) in statements {
+                    match statement {
+                        MacroBody::MacroInvocation {
+                            name: invoked,
+                            args,
+                        } => {
+                            if invoked == name {
+                                spans.push(name_at(span));
+                            }
+                            spans.extend(arg_references(args, name));
+                        }
+                        MacroBody::BuiltinInvocation { args, .. } => {
+                            spans.extend(arg_references(args, name));
+                        }
+                        MacroBody::ConstantRef((used, span))
+                        | MacroBody::ArgsInvocation((used, span))
+                            if used == name =>
+                        {
+                            spans.push(span.clone());
+                        }
+                        MacroBody::JumpLabel(label) | MacroBody::JumpLabelDest(label)
+                            if label == name =>
+                        {
+                            spans.push(name_at(span));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Ast::ConstantDefinition {
+                name: definition,
+                name_span,
+                value,
+            } => {
+                if definition == name {
+                    spans.push(name_span.clone());
+                }
+                if let ConstantValue::ConstantRef((alias, span)) = value {
+                    if alias == name {
+                        spans.push(span.clone());
+                    }
+                }
+            }
+            Ast::TableDefinition {
+                name: definition,
+                name_span,
+                statements,
+                ..
+            } => {
+                if definition == name {
+                    spans.push(name_span.clone());
+                }
+                spans.extend(
+                    statements
+                        .iter()
+                        .filter(|(statement, _)| {
+                            matches!(statement, TableStatements::JumpLabel(label) if label == name)
+                        })
+                        .map(|(_, span)| span.clone()),
+                );
+            }
+            _ => {}
+        }
+    }
+
+    spans.sort_by_key(|span| span.start);
+    spans
+}
+
+fn arg_references(args: &[Spanned<Arg>], name: &str) -> Vec<Span> {
+    args.iter()
+        .filter_map(|(arg, span)| match arg {
+            Arg::Valid(used) if used == name => Some(span.clone()),
+            Arg::ConstantRef((used, span)) | Arg::ArgRef((used, span)) if used == name => {
+                Some(span.clone())
+            }
+            _ => None,
+        })
+        .collect()
+}
@@ -0,0 +1,454 @@
+//! Lowering
+//!
+//! Converts the spanned parser ast into the huff-rs style [`Contract`] representation consumed by
+//! later stages. Anything that cannot be represented is dropped and reported as a [`Diagnostic`].
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    error::Diagnostic,
+    parser::{Arg, Args, Ast, ConstantValue, MacroBody, MacroType, TableStatements},
+    span::{Span, Spanned},
+    utils::{
+        abi::{self, FunctionParam},
+        ast::{
+            Argument, BuiltinFunctionCall, BuiltinFunctionKind, ConstVal, ConstantDefinition,
+            Contract, ErrorDefinition, Event, FreeStoragePointer, Function, FunctionType, Label,
+            MacroArg, MacroDefinition, MacroInvocation, Statement, StatementType, TableDefinition,
+            TableKind,
+        },
+        builtins::{self, BUILTINS_MAP},
+        bytes_util::{keccak256, minimal_push, number_to_literal, str_to_bytes32},
+    },
+};
+
+/// Which definition is kept when a macro or constant is defined more than once
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DuplicateStrategy {
+    /// Keep the first definition, matching the lookups on [`Contract`]
+    #[default]
+    KeepFirst,
+    /// Keep the last definition, so later definitions override earlier ones
+    KeepLast,
+    /// Keep the first definition and report every later one as an error
+    Error,
+}
+
+/// Configuration of the lowering step
+#[derive(Debug, Default, Clone)]
+pub struct LowerOptions {
+    /// How duplicate macros and constants are resolved
+    pub on_duplicate: DuplicateStrategy,
+}
+
+/// Lower
+///
+/// Lowers every top level definition into a [`Contract`] with the default options. Function
+/// selectors, event hashes and table sizes are computed here.
+pub fn lower(ast: &[Spanned<Ast>]) -> (Contract, Vec<Diagnostic>) {
+    lower_with_options(ast, &LowerOptions::default())
+}
+
+/// Lower every top level definition into a [`Contract`]
+pub fn lower_with_options(
+    ast: &[Spanned<Ast>],
+    options: &LowerOptions,
+) -> (Contract, Vec<Diagnostic>) {
+    let mut contract = Contract::default();
+    let mut constants = Vec::new();
+    let mut macros = Vec::new();
+    let mut diagnostics = Vec::new();
+
+    for (item, span) in ast {
+        match item {
+            Ast::FileInclude { path, .. } => contract.imports.push(PathBuf::from(path)),
+            // Constants may alias each other, so they are resolved once all have been seen
+            Ast::ConstantDefinition { name, value, .. } => constants.push((name, value, span)),
+            Ast::MacroDefinition {
+                name,
+                macro_type,
+                takes,
+                returns,
+                statements,
+                args,
+                decorator,
+                ..
+            } => {
+                let statements = lower_statements(statements);
+                let definition = MacroDefinition::new(
+                    name.clone(),
+                    decorator.clone(),
+                    lower_macro_args(args),
+                    statements,
+                    takes.0,
+                    returns.0,
+                    macro_type.0 == MacroType::Fn,
+                    macro_type.0 == MacroType::Test,
+                );
+                macros.push((definition, span.clone()));
+            }
+            Ast::TableDefinition {
+                name,
+                kind,
+                statements,
+                ..
+            } => contract.tables.push(lower_table(name, kind, statements)),
+            Ast::AbiFunction(function) => {
+                let mut lowered = Function {
+                    name: function.name.clone(),
+                    signature: [0; 4],
+                    inputs: lower_params(&function.inputs),
+                    fn_type: match function.state_mutability.0 {
+                        abi::FunctionType::View => FunctionType::View,
+                        abi::FunctionType::Payable => FunctionType::Payable,
+                        abi::FunctionType::NonPayable => FunctionType::NonPayable,
+                        abi::FunctionType::Pure => FunctionType::Pure,
+                    },
+                    outputs: lower_params(&function.outputs),
+                };
+                lowered.signature = selector(&lowered.canonical_signature());
+                contract.functions.push(lowered);
+            }
+            Ast::AbiEvent(event) => {
+                let mut lowered = Event {
+                    name: event.name.clone(),
+                    parameters: event
+                        .inputs
+                        .iter()
+                        .map(|(param, _)| Argument {
+                            arg_type: Some(param.kind.to_string()),
+                            arg_location: None,
+                            name: (!param.name.is_empty()).then(|| param.name.clone()),
+                            indexed: param.indexed,
+                        })
+                        .collect(),
+                    hash: [0; 32],
+                };
+                lowered.hash = hash_signature(&lowered.canonical_signature());
+                contract.events.push(lowered);
+            }
+            Ast::AbiError(error) => {
+                let mut lowered = ErrorDefinition {
+                    name: error.name.clone(),
+                    selector: [0; 4],
+                    parameters: lower_params(&error.inputs),
+                };
+                lowered.selector = selector(&lowered.canonical_signature());
+                contract.errors.push(lowered);
+            }
+            // Only the first constructor is kept
+            Ast::AbiConstructor(constructor) => {
+                if contract.constructor.is_none() {
+                    contract.constructor =
+                        Some(constructor.inputs.iter().map(lower_param).collect());
+                }
+            }
+            // Parsing errors have already been reported by the parser
+            Ast::ParsingError { .. } => {}
+        }
+    }
+
+    let strategy = options.on_duplicate;
+    contract.macros = deduplicate(
+        macros,
+        |(definition, _)| &definition.name,
+        |(_, span)| span,
+        "macro",
+        strategy,
+        &mut diagnostics,
+    )
+    .into_iter()
+    .map(|(definition, _)| definition)
+    .collect();
+    contract.update_invocations();
+
+    let constants = deduplicate(
+        constants,
+        |(name, _, _)| name,
+        |(_, _, span)| span,
+        "constant",
+        strategy,
+        &mut diagnostics,
+    );
+    contract.constants = Arc::new(Mutex::new(lower_constants(&constants, &mut diagnostics)));
+    (contract, diagnostics)
+}
+
+/// Removes all but one definition of each name, as chosen by the strategy. The kept definitions
+/// remain in source order.
+fn deduplicate<T>(
+    definitions: Vec<T>,
+    name: impl Fn(&T) -> &str,
+    span: impl Fn(&T) -> &Span,
+    kind: &str,
+    strategy: DuplicateStrategy,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Vec<T> {
+    let mut kept: HashMap<&str, usize> = HashMap::new();
+    for (i, definition) in definitions.iter().enumerate() {
+        let Some(&first) = kept.get(name(definition)) else {
+            kept.insert(name(definition), i);
+            continue;
+        };
+        match strategy {
+            DuplicateStrategy::KeepFirst => {}
+            DuplicateStrategy::KeepLast => {
+                kept.insert(name(definition), i);
+            }
+            DuplicateStrategy::Error => diagnostics.push(
+                Diagnostic::error(
+                    format!("{kind} `{}` is defined more than once", name(definition)),
+                    span(definition).clone(),
+                )
+                .with_related(span(&definitions[first]).clone()),
+            ),
+        }
+    }
+
+    let kept: HashSet<usize> = kept.into_values().collect();
+    definitions
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| kept.contains(i))
+        .map(|(_, definition)| definition)
+        .collect()
+}
+
+/// Lowers constant definitions, resolving aliases to the value they ultimately refer to
+fn lower_constants(
+    constants: &[(&String, &ConstantValue, &Span)],
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Vec<ConstantDefinition> {
+    let definitions: HashMap<&str, &ConstantValue> = constants
+        .iter()
+        .map(|(name, value, _)| (name.as_str(), *value))
+        .collect();
+
+    constants
+        .iter()
+        .filter_map(|(name, value, span)| {
+            // Literals and expressions are folded into a single word, the only other value an
+            // alias can resolve to is a free storage pointer
+            let resolved =
+                resolve_constant(name, &definitions).map(|resolved| resolved.as_u256_bytes());
+            let value = match resolved {
+                Ok(Some(lit)) => ConstVal::Literal(lit),
+                Ok(None) if **value == ConstantValue::FreeStoragePointer => {
+                    ConstVal::FreeStoragePointer(FreeStoragePointer)
+                }
+                // Lowering the alias as another free storage pointer would assign it its own slot
+                Ok(None) => {
+                    diagnostics.push(Diagnostic::error(
+                        format!("constant `{name}` cannot alias a free storage pointer"),
+                        (*span).clone(),
+                    ));
+                    return None;
+                }
+                Err(message) => {
+                    diagnostics.push(Diagnostic::error(message, (*span).clone()));
+                    return None;
+                }
+            };
+            Some(ConstantDefinition {
+                name: (*name).clone(),
+                value,
+            })
+        })
+        .collect()
+}
+
+/// Follows a chain of constant aliases, failing on an undefined constant or a cycle
+fn resolve_constant<'a>(
+    name: &str,
+    definitions: &HashMap<&str, &'a ConstantValue>,
+) -> Result<&'a ConstantValue, String> {
+    let mut chain = vec![name];
+
+    loop {
+        let current = chain[chain.len() - 1];
+        let value = definitions.get(current).ok_or_else(|| {
+            format!(
+                "constant `{}` references undefined constant `{current}`",
+                chain[chain.len() - 2]
+            )
+        })?;
+
+        let ConstantValue::ConstantRef((next, _)) = value else {
+            return Ok(value);
+        };
+        if chain.contains(&next.as_str()) {
+            chain.push(next);
+            return Err(format!(
+                "constant `{name}` is defined in terms of itself: {}",
+                chain.join(" -> ")
+            ));
+        }
+        chain.push(next);
+    }
+}
+
+/// The keccak256 hash of a signature
+pub(crate) fn hash_signature(signature: &String) -> [u8; 32] {
+    keccak256(signature.as_bytes())
+}
+
+/// The first four bytes of the keccak256 hash of a signature
+pub(crate) fn selector(signature: &String) -> [u8; 4] {
+    let hash = hash_signature(signature);
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+fn lower_params(params: &[Spanned<FunctionParam>]) -> Vec<Argument> {
+    params.iter().map(|(param, _)| lower_param(param)).collect()
+}
+
+fn lower_param(param: &FunctionParam) -> Argument {
+    Argument {
+        arg_type: Some(param.kind.to_string()),
+        arg_location: param.location.clone(),
+        name: (!param.name.is_empty()).then(|| param.name.clone()),
+        indexed: false,
+    }
+}
+
+fn lower_macro_args(args: &Args) -> Vec<Argument> {
+    args.iter()
+        .filter_map(|(arg, _)| match arg {
+            Arg::Valid(name) | Arg::ConstantRef((name, _)) | Arg::ArgRef((name, _)) => {
+                Some(Argument {
+                    name: Some(name.clone()),
+                    ..Default::default()
+                })
+            }
+            Arg::Number(_) | Arg::Literal(_) | Arg::Invalid => None,
+        })
+        .collect()
+}
+
+/// Lowers the arguments of a macro or builtin invocation, where numbers become literals
+fn lower_invocation_args(args: &Args) -> Vec<MacroArg> {
+    args.iter()
+        .filter_map(|(arg, _)| match arg {
+            Arg::Valid(name) => Some(MacroArg::Ident(name.clone())),
+            Arg::ConstantRef((name, _)) => Some(MacroArg::ConstantRef(name.clone())),
+            Arg::ArgRef((name, _)) => Some(MacroArg::ArgCall(name.clone())),
+            Arg::Literal(lit) => Some(MacroArg::Literal(*lit)),
+            Arg::Number(num) => Some(MacroArg::Literal(number_to_literal(*num))),
+            Arg::Invalid => None,
+        })
+        .collect()
+}
+
+fn lower_statements(statements: &[Spanned<MacroBody>]) -> Vec<Statement> {
+    statements
+        .iter()
+        .filter_map(|(statement, span)| {
+            let ty = match statement {
+                MacroBody::Opcode(opcode) => StatementType::Opcode(*opcode),
+                MacroBody::HexLiteral(lit) => StatementType::Literal(*lit),
+                // Sized to the fewest bytes that hold the literal, zero is pushed as a single byte
+                MacroBody::AutoPush(lit) => {
+                    let (push, _) = minimal_push(lit);
+                    return Some(vec![
+                        Statement {
+                            ty: StatementType::Opcode(push),
+                            span: span.clone(),
+                        },
+                        Statement {
+                            ty: StatementType::Literal(*lit),
+                            span: span.clone(),
+                        },
+                    ]);
+                }
+                MacroBody::MacroInvocation { name, args } => {
+                    StatementType::MacroInvocation(MacroInvocation {
+                        macro_name: name.clone(),
+                        args: lower_invocation_args(args),
+                    })
+                }
+                MacroBody::ArgsInvocation((name, _)) => StatementType::ArgCall(name.clone()),
+                MacroBody::ConstantRef((name, _)) => StatementType::Constant(name.clone()),
+                MacroBody::BuiltinInvocation { name, args } => {
+                    // Unknown builtins are reported by the lexer
+                    let kind = builtin_kind(name)?;
+                    StatementType::BuiltinFunctionCall(BuiltinFunctionCall {
+                        kind,
+                        args: lower_invocation_args(args),
+                    })
+                }
+                MacroBody::JumpLabel(label) => StatementType::LabelCall(label.clone()),
+                // Labels are kept flat, the statements following a label remain in the macro's
+                // statement list rather than being nested inside of it
+                MacroBody::JumpLabelDest(label) => StatementType::Label(Label {
+                    name: label.clone(),
+                    inner: vec![],
+                }),
+                // Already reported by the parser
+                MacroBody::UnexpectedToken(_) => return None,
+            };
+            Some(vec![Statement {
+                ty,
+                span: span.clone(),
+            }])
+        })
+        .flatten()
+        .collect()
+}
+
+/// Resolves the name of a builtin, as lexed without its leading underscores
+fn builtin_kind(name: &str) -> Option<BuiltinFunctionKind> {
+    let kind = BUILTINS_MAP.get(format!("__{name}").as_str())?;
+    Some(match kind {
+        builtins::BuiltinFunctionKind::Tablesize => BuiltinFunctionKind::Tablesize,
+        builtins::BuiltinFunctionKind::Codesize => BuiltinFunctionKind::Codesize,
+        builtins::BuiltinFunctionKind::Tablestart => BuiltinFunctionKind::Tablestart,
+        builtins::BuiltinFunctionKind::FunctionSignature => BuiltinFunctionKind::FunctionSignature,
+        builtins::BuiltinFunctionKind::EventHash => BuiltinFunctionKind::EventHash,
+        builtins::BuiltinFunctionKind::Error => BuiltinFunctionKind::Error,
+        builtins::BuiltinFunctionKind::RightPad => BuiltinFunctionKind::RightPad,
+        builtins::BuiltinFunctionKind::DynConstructorArg => BuiltinFunctionKind::DynConstructorArg,
+    })
+}
+
+fn lower_table(
+    name: &str,
+    kind: &TableKind,
+    statements: &[Spanned<TableStatements>],
+) -> TableDefinition {
+    let statements: Vec<Statement> = statements
+        .iter()
+        .filter_map(|(statement, span)| {
+            let ty = match statement {
+                TableStatements::JumpLabel(label) => StatementType::LabelCall(label.clone()),
+                TableStatements::Code(code) => StatementType::Code(code.clone()),
+                TableStatements::Error(_) => return None,
+            };
+            Some(Statement {
+                ty,
+                span: span.clone(),
+            })
+        })
+        .collect();
+
+    let size = match kind.entry_size() {
+        Some(entry_size) => statements.len() * entry_size,
+        None => statements
+            .iter()
+            .map(|s| match &s.ty {
+                StatementType::Code(code) => code.len().div_ceil(2),
+                _ => 0,
+            })
+            .sum(),
+    };
+
+    TableDefinition::new(
+        name.to_string(),
+        kind.clone(),
+        statements,
+        str_to_bytes32(&format!("{size:x}")).expect("a usize always fits in 32 bytes"),
+    )
+}
@@ -1,56 +1,162 @@
 // TOOD: look in ast.rs of huff-rs and rip the types so that they can
 // be identical to the ones in the huff-rs crate
 
-use chumsky::{prelude::*, Stream};
+use std::{path::Path, process::ExitCode};
+
+use ariadne::{Label, Report, ReportKind, Source};
+use chumsky::prelude::*;
 use chumsky_huff::{
-    lexer::{lexer, token::Token},
-    parser::parser,
+    diagnostics,
+    error::{ChuffError, Diagnostic, Severity},
+    lexer::lexer,
+    lowering::lower,
+    parse_str,
+    parser::Ast,
+    span::Spanned,
+    utils::json_abi::json_abi,
 };
 
-/// Error strategies
-// skip_then_retry_with();
-// skip_then_retry_until
-// skip_until
-// nested_delimiters
-
-// Create a token mapping of keyword to opcode
-
-fn main() -> Result<(), String> {
-    let file_path = std::env::args().nth(1).unwrap();
-    let src = std::fs::read_to_string(file_path).unwrap();
-    let src_len = src.chars().count();
-
-    // let eoi = Span::new(file_path, src_len..src_len);
-
-    // .parse_recovery(src).
-    let (tokens, _lex_errors) = lexer().parse_recovery(src);
-    // let (tokens, _lex_errors) = lexer().parse_recovery(chumsky::Stream::from_iter(
-    //     eoi,
-    //     src.chars()
-    //         .enumerate()
-    //         .map(|index, span| (Span::new(file_path, i..i + 1))),
-    // ));
-    println!("TOKENS");
-    println!("{tokens:?}");
-
-    // TODO: remove newlines from the tokens
-
-    let (ast, parse_errs) = if let Some(tokens) = tokens {
-        let clean_tokens = tokens
-            .into_iter()
-            .filter(|(token, _)| token.clone() != Token::Newline);
-        let token_stream = Stream::from_iter(src_len..src_len + 1, clean_tokens);
-        parser().parse_recovery(token_stream)
-    } else {
-        (None, vec![])
+const USAGE: &str = "usage: chuffc <lex|parse|abi|interface|check> <file>";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (Some(command), Some(path), None) = (args.first(), args.get(1), args.get(2)) else {
+        eprintln!("{USAGE}");
+        return ExitCode::from(2);
+    };
+
+    let src = match std::fs::read_to_string(path) {
+        Ok(src) => src,
+        Err(error) => {
+            eprintln!("could not read {path}: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match command.as_str() {
+        "lex" => lex(path, &src),
+        "parse" => parse(path, &src),
+        "abi" => abi(path, &src),
+        "interface" => interface(path, &src),
+        "check" => check(path, &src),
+        _ => {
+            eprintln!("unknown command `{command}`\n{USAGE}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// Prints every token on its own line, along with its span
+fn lex(path: &str, src: &str) -> ExitCode {
+    let (tokens, errors) = lexer().parse_recovery(src);
+    for (token, span) in tokens.unwrap_or_default() {
+        println!("{}..{} {token:?}", span.start, span.end);
+    }
+
+    let errors: Vec<_> = errors.iter().map(Diagnostic::from_simple).collect();
+    report(path, src, &errors);
+    exit_code(&errors)
+}
+
+/// Prints each top level definition of the ast
+fn parse(path: &str, src: &str) -> ExitCode {
+    let Some(ast) = parse_or_report(path, src) else {
+        return ExitCode::FAILURE;
+    };
+    for (item, span) in ast {
+        println!("{}..{} {item:?}", span.start, span.end);
+    }
+    ExitCode::SUCCESS
+}
+
+/// Prints the JSON abi of the contract
+fn abi(path: &str, src: &str) -> ExitCode {
+    let Some(ast) = parse_or_report(path, src) else {
+        return ExitCode::FAILURE;
+    };
+    match serde_json::to_string_pretty(&json_abi(&ast)) {
+        Ok(abi) => {
+            println!("{abi}");
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("could not serialize the abi: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Prints a Solidity interface for the contract, named after the file
+fn interface(path: &str, src: &str) -> ExitCode {
+    let Some(ast) = parse_or_report(path, src) else {
+        return ExitCode::FAILURE;
     };
-    println!("AST");
-    println!("{ast:?}");
+    let (contract, diagnostics) = lower(&ast);
+    report(path, src, &diagnostics);
+    if diagnostics.iter().any(Diagnostic::is_error) {
+        return ExitCode::FAILURE;
+    }
 
-    println!("ERRS");
-    println!("{parse_errs:?}");
-    // let debug = parser().parse_recovery_verbose(src);
-    // println!("{:?}", debug);
+    let name = Path::new(path)
+        .file_stem()
+        .map(|stem| format!("I{}", stem.to_string_lossy()))
+        .unwrap_or_else(|| "IContract".to_string());
+    print!("{}", contract.to_solidity_interface(&name));
+    ExitCode::SUCCESS
+}
+
+/// Reports every diagnostic, failing if any is an error
+fn check(path: &str, src: &str) -> ExitCode {
+    let diagnostics = diagnostics(src);
+    report(path, src, &diagnostics);
+    exit_code(&diagnostics)
+}
+
+/// Parses the source, reporting any lexer or parser errors
+fn parse_or_report(path: &str, src: &str) -> Option<Vec<Spanned<Ast>>> {
+    match parse_str(src) {
+        Ok(ast) => Some(ast),
+        Err(error) => {
+            let diagnostics: Vec<_> = match &error {
+                ChuffError::Lex(errors) => errors.iter().map(Diagnostic::from_simple).collect(),
+                ChuffError::Parse(errors) => errors.iter().map(Diagnostic::from_simple).collect(),
+            };
+            report(path, src, &diagnostics);
+            None
+        }
+    }
+}
+
+fn report(path: &str, src: &str, diagnostics: &[Diagnostic]) {
+    for diagnostic in diagnostics {
+        let kind = match diagnostic.severity {
+            Severity::Error => ReportKind::Error,
+            Severity::Warning => ReportKind::Warning,
+            Severity::Info => ReportKind::Advice,
+        };
+        let span = (path.to_string(), diagnostic.span.clone());
+
+        let report = diagnostic
+            .related
+            .iter()
+            .fold(
+                Report::build(kind, path.to_string(), diagnostic.span.start)
+                    .with_message(&diagnostic.message)
+                    .with_label(Label::new(span)),
+                |report, related| {
+                    report.with_label(Label::new((path.to_string(), related.clone())))
+                },
+            )
+            .finish();
+
+        // A report that cannot be written to stderr cannot be reported either
+        let _ = report.eprint((path.to_string(), Source::from(src)));
+    }
+}
 
-    Ok(())
+fn exit_code(diagnostics: &[Diagnostic]) -> ExitCode {
+    match diagnostics.iter().any(Diagnostic::is_error) {
+        true => ExitCode::FAILURE,
+        false => ExitCode::SUCCESS,
+    }
 }
@@ -0,0 +1,101 @@
+//! Parser Components
+//!
+//! The individual parsers that make up the top level [`parser`](super::parser), for reuse by
+//! other tools. Each consumes a stream of [`Token`]s with newlines already removed, as produced by
+//! [`parse_str`](crate::parse_str). Definition parsers start at the keyword following `#define`.
+
+use chumsky::prelude::*;
+
+use super::{Arg, Args, Ast, ConstantValue, MacroBody, ParserOptions};
+use crate::{
+    lexer::token::Token,
+    span::Spanned,
+    utils::{
+        abi::{EventParam, FunctionParam},
+        ast::Decorator,
+    },
+};
+
+/// `#include "<path>"`, optionally followed by `{ MACRO_A, MACRO_B }`, producing an
+/// [`Ast::FileInclude`]
+pub fn parse_include() -> impl Parser<Token, Spanned<Ast>, Error = Simple<Token>> + Clone {
+    Ast::parse_include()
+}
+
+/// `macro NAME(<args>) = takes (n) returns (n) { <body> }`, or `fn` in place of `macro`,
+/// producing an [`Ast::MacroDefinition`]
+pub fn parse_macro() -> impl Parser<Token, Spanned<Ast>, Error = Simple<Token>> + Clone {
+    Ast::parse_macro()
+}
+
+/// `#[calldata("0x..."), value(0x01)]`, the decorator placed before a macro definition
+pub fn parse_decorator() -> impl Parser<Token, Decorator, Error = Simple<Token>> + Clone {
+    Ast::parse_decorator()
+}
+
+/// `constant NAME = <value>`, producing an [`Ast::ConstantDefinition`]
+pub fn parse_constants() -> impl Parser<Token, Spanned<Ast>, Error = Simple<Token>> + Clone {
+    Ast::parse_constants()
+}
+
+/// `jumptable NAME() = { ... }`, `jumptablepacked` or `codetable`, producing an
+/// [`Ast::TableDefinition`]
+pub fn table_parser() -> impl Parser<Token, Spanned<Ast>, Error = Simple<Token>> + Clone {
+    Ast::table_parser(ParserOptions::default())
+}
+
+/// `function name(<inputs>) <mutability> returns (<outputs>)`, producing an [`Ast::AbiFunction`]
+pub fn parse_abi_definition() -> impl Parser<Token, Spanned<Ast>, Error = Simple<Token>> + Clone {
+    Ast::parse_abi_definition(ParserOptions::default())
+}
+
+/// `event Name(<inputs>)`, producing an [`Ast::AbiEvent`]
+pub fn parse_abi_event_definition(
+) -> impl Parser<Token, Spanned<Ast>, Error = Simple<Token>> + Clone {
+    Ast::parse_abi_event_definition(ParserOptions::default())
+}
+
+/// `error Name(<inputs>)`, producing an [`Ast::AbiError`]
+pub fn parse_errors() -> impl Parser<Token, Spanned<Ast>, Error = Simple<Token>> + Clone {
+    Ast::parse_errors(ParserOptions::default())
+}
+
+/// `constructor(<inputs>)`, producing an [`Ast::AbiConstructor`]
+pub fn parse_constructor() -> impl Parser<Token, Spanned<Ast>, Error = Simple<Token>> + Clone {
+    Ast::parse_constructor(ParserOptions::default())
+}
+
+/// A comma separated list of abi parameters, `<type> <location>? <name>?`, without the enclosing
+/// parentheses
+pub fn parse_abi_inputs(
+) -> impl Parser<Token, Vec<Spanned<FunctionParam>>, Error = Simple<Token>> + Clone {
+    Ast::parse_abi_inputs(ParserOptions::default())
+}
+
+/// A comma separated list of event parameters, `<type> indexed? <name>?`, without the enclosing
+/// parentheses
+pub fn parse_event_inputs(
+) -> impl Parser<Token, Vec<Spanned<EventParam>>, Error = Simple<Token>> + Clone {
+    Ast::parse_event_inputs(ParserOptions::default())
+}
+
+/// The value of a constant definition, following the `=`
+pub fn parse_constant_value() -> impl Parser<Token, ConstantValue, Error = Simple<Token>> + Clone {
+    Ast::parse_constant_value()
+}
+
+/// A single argument of a macro or builtin invocation
+pub fn parse_invocation_arg() -> impl Parser<Token, Spanned<Arg>, Error = Simple<Token>> + Clone {
+    Ast::parse_invocation_arg()
+}
+
+/// The statements of a macro body, without the enclosing braces
+pub fn parse_macro_body(
+) -> impl Parser<Token, Vec<Spanned<MacroBody>>, Error = Simple<Token>> + Clone {
+    Ast::parse_macro_body()
+}
+
+/// The arguments of a macro definition or invocation, without the enclosing parentheses
+pub fn parse_args() -> impl Parser<Token, Args, Error = Simple<Token>> + Clone {
+    Ast::parse_args()
+}
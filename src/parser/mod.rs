@@ -1,7 +1,7 @@
 use chumsky::prelude::*;
 use std::hash::Hash;
 
-// TODO: parse constructor
+pub mod components;
 
 use crate::{
     lexer::token::{Literal, Token},
@@ -11,16 +11,39 @@ use crate::{
             Constructor, Error, Event, EventParam, Function, FunctionParam, FunctionParamType,
             FunctionType,
         },
-        ast::TableKind,
-        bytes_util::bytes32_to_string,
+        ast::{ArgumentLocation, Decorator, DecoratorFlag, TableKind},
+        bytes_util::{
+            bytes32_to_string, format_even_bytes, literal_to_hex, number_to_literal,
+            wrapping_add_bytes32, wrapping_div_bytes32, wrapping_mul_bytes32, wrapping_sub_bytes32,
+        },
         opcodes::Opcode,
-        types::PrimitiveEVMType,
     },
 };
 
 /// Public entry point to the ast parser
 pub fn parser() -> impl Parser<Token, Vec<Spanned<Ast>>, Error = Simple<Token>> {
-    Ast::parser().repeated().at_least(1).then_ignore(end())
+    parser_with_options(ParserOptions::default())
+}
+
+/// Entry point to the ast parser with non default options
+pub fn parser_with_options(
+    options: ParserOptions,
+) -> impl Parser<Token, Vec<Spanned<Ast>>, Error = Simple<Token>> {
+    Ast::parser_with_options(options)
+        .repeated()
+        .at_least(1)
+        .then_ignore(end())
+}
+
+/// Options that change which sources the parser accepts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParserOptions {
+    /// Require exactly one comma between abi parameters and forbid a trailing comma, as in
+    /// Solidity. By default commas between parameters are optional.
+    pub strict_abi_commas: bool,
+    /// Reject code table contents with an odd number of hex digits. By default they are padded
+    /// with a leading zero.
+    pub strict_code_tables: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Hash)]
@@ -31,21 +54,31 @@ pub enum Ast {
     },
     FileInclude {
         path: String,
+        /// The macros listed in `#include "<path>" { A, B }`, when only those are wanted
+        only: Option<Vec<String>>,
     },
     ConstantDefinition {
         name: String,
+        /// Where the name was written
+        name_span: Span,
         value: ConstantValue,
     },
     MacroDefinition {
         name: String,
+        /// Where the name was written
+        name_span: Span,
         macro_type: Spanned<MacroType>,
         takes: Spanned<usize>,
         returns: Spanned<usize>,
         statements: Vec<Spanned<MacroBody>>,
         args: Args,
+        /// The `#[...]` decorator written before the definition
+        decorator: Option<Decorator>,
     },
     TableDefinition {
         name: String,
+        /// Where the name was written
+        name_span: Span,
         kind: TableKind,
         statements: Vec<Spanned<TableStatements>>,
     },
@@ -69,12 +102,79 @@ pub enum TableStatements {
 pub enum MacroType {
     Macro,
     Fn,
+    /// A test, whose environment is set by its decorator
+    Test,
 }
 
 #[derive(Debug, Clone, PartialEq, Hash)]
 pub enum ConstantValue {
     Literal(Literal),
     FreeStoragePointer,
+    /// An alias of another constant, `[CONST]`, with the span of the name
+    ConstantRef(Spanned<String>),
+    /// Arithmetic on literals, `0x10 + 0x20`, applied with `*` and `/` binding tighter than `+`
+    /// and `-`
+    Expression(Literal, Vec<(ArithOp, Literal)>),
+}
+
+/// An arithmetic operator in a constant expression
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ArithOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl ArithOp {
+    /// Apply the operator with the EVM's wrapping 256 bit semantics
+    pub fn apply(&self, a: &Literal, b: &Literal) -> Literal {
+        match self {
+            ArithOp::Add => wrapping_add_bytes32(a, b),
+            ArithOp::Sub => wrapping_sub_bytes32(a, b),
+            ArithOp::Mul => wrapping_mul_bytes32(a, b),
+            ArithOp::Div => wrapping_div_bytes32(a, b),
+        }
+    }
+}
+
+impl ConstantValue {
+    /// The big endian bytes of the value, `None` for an unresolved free storage pointer or alias
+    pub fn as_u256_bytes(&self) -> Option<[u8; 32]> {
+        match self {
+            ConstantValue::Literal(lit) => Some(*lit),
+            ConstantValue::Expression(first, rest) => Some(evaluate_expression(first, rest)),
+            ConstantValue::FreeStoragePointer | ConstantValue::ConstantRef(_) => None,
+        }
+    }
+
+    /// The value as a `U256`, `None` for an unresolved free storage pointer or alias
+    #[cfg(feature = "u256")]
+    pub fn as_u256(&self) -> Option<primitive_types::U256> {
+        self.as_u256_bytes()
+            .map(|bytes| primitive_types::U256::from_big_endian(&bytes))
+    }
+}
+
+/// Folds a constant expression into a single word, evaluating `*` and `/` before `+` and `-`
+fn evaluate_expression(first: &Literal, rest: &[(ArithOp, Literal)]) -> Literal {
+    // Each term is a product, and is added to or subtracted from the total once complete
+    let mut total = [0u8; 32];
+    let mut term_op = ArithOp::Add;
+    let mut term = *first;
+
+    for (op, lit) in rest {
+        match op {
+            ArithOp::Mul | ArithOp::Div => term = op.apply(&term, lit),
+            ArithOp::Add | ArithOp::Sub => {
+                total = term_op.apply(&total, &term);
+                term_op = *op;
+                term = *lit;
+            }
+        }
+    }
+
+    term_op.apply(&total, &term)
 }
 
 #[derive(Debug, Clone, PartialEq, Hash)]
@@ -82,12 +182,24 @@ pub enum MacroBody {
     Opcode(Opcode),
 
     // TODO give each of these names and args
-    MacroInvocation { name: String, args: Args },
-    ArgsInvocation(String),
-    BuiltinInvocation { name: String, args: Args },
+    MacroInvocation {
+        name: String,
+        args: Args,
+    },
+    /// A reference to an argument of the macro, `<arg>`, with the span of the name
+    ArgsInvocation(Spanned<String>),
+    /// A constant reference, `[CONST]`, which pushes the constant's value, with the span of the
+    /// name
+    ConstantRef(Spanned<String>),
+    BuiltinInvocation {
+        name: String,
+        args: Args,
+    },
     JumpLabel(String),
     JumpLabelDest(String),
     HexLiteral(Literal),
+    /// A `push` without a size, sized to fit the literal when lowered
+    AutoPush(Literal),
 
     UnexpectedToken(String),
 }
@@ -96,9 +208,40 @@ pub type Args = Vec<Spanned<Arg>>;
 #[derive(Debug, Clone, PartialEq, Hash)]
 pub enum Arg {
     Valid(String),
+    /// A bracketed constant reference, `[CONST]`, with the span of the name
+    ConstantRef(Spanned<String>),
+    /// A reference to an argument of the enclosing macro, `<arg>`, with the span of the name
+    ArgRef(Spanned<String>),
+    /// A decimal number, only valid in invocations
+    Number(usize),
+    /// A hex literal, only valid in invocations
+    Literal(Literal),
     Invalid,
 }
 
+impl Ast {
+    /// Sort Key
+    ///
+    /// Orders definitions by kind, in the order they are emitted by
+    /// [`Contract::to_huff_source`](crate::utils::ast::Contract::to_huff_source), then by name.
+    /// Spans are not part of the key, so the same definition sorts identically wherever it was
+    /// parsed from. Use with a stable sort to keep definitions of the same kind and name in source
+    /// order.
+    pub fn sort_key(&self) -> (u8, &str) {
+        match self {
+            Ast::FileInclude { path, .. } => (0, path),
+            Ast::ConstantDefinition { name, .. } => (1, name),
+            Ast::AbiFunction(function) => (2, &function.name),
+            Ast::AbiEvent(event) => (3, &event.name),
+            Ast::AbiError(error) => (4, &error.name),
+            Ast::AbiConstructor(_) => (5, ""),
+            Ast::TableDefinition { name, .. } => (6, name),
+            Ast::MacroDefinition { name, .. } => (7, name),
+            Ast::ParsingError { message, .. } => (8, message),
+        }
+    }
+}
+
 impl Ast {
     /// Top level Parser
     ///
@@ -110,10 +253,36 @@ impl Ast {
     ///
     /// If any error occurs at this level, the parser will attempt to recover to the next DEFINE or INCLUDES token.
     pub fn parser() -> impl Parser<Token, Spanned<Self>, Error = Simple<Token>> + Clone {
-        let define_parser = Self::parse_define();
+        Self::parser_with_options(ParserOptions::default())
+    }
+
+    /// Top level Parser with non default options
+    pub fn parser_with_options(
+        options: ParserOptions,
+    ) -> impl Parser<Token, Spanned<Self>, Error = Simple<Token>> + Clone {
+        let define_parser = Self::parse_define(options);
         let include_parser = Self::parse_include();
 
-        define_parser.or(include_parser)
+        Self::parse_decorator()
+            .map_with_span(|decorator, span| (decorator, span))
+            .or_not()
+            .then(define_parser.or(include_parser))
+            .validate(|(decorator, (mut item, span)), _, emit| {
+                match (decorator, &mut item) {
+                    (
+                        Some((decorator, _)),
+                        Self::MacroDefinition {
+                            decorator: slot, ..
+                        },
+                    ) => *slot = Some(decorator),
+                    (Some((_, decorator_span)), _) => emit(Simple::custom(
+                        decorator_span,
+                        "Decorators can only be placed on macros",
+                    )),
+                    (None, _) => {}
+                }
+                (item, span)
+            })
         // .or(any().map_with_span(|token, span| {
         //     (
         //         Self::ParsingError {
@@ -129,12 +298,56 @@ impl Ast {
     ///
     /// Utility function for when a smaller parser is enclosed by delimiters, a recovery strategy is automatically implemented based on
     /// the provided delimiters
+    ///
+    /// Recovery needs a matching close delimiter, so a delimiter that is still open at the next
+    /// top level definition or the end of input is reported separately, pointing at where it was
+    /// opened.
     fn nested_parser<'a, T: 'a>(
         parser: impl Parser<Token, T, Error = Simple<Token>> + Clone + 'a,
         open_delimiter: Token,
         close_delimiter: Token,
         f: impl Fn(Span) -> T + Clone + 'a,
     ) -> impl Parser<Token, T, Error = Simple<Token>> + Clone + 'a {
+        let top_level = [Token::Define, Token::Include];
+        let contents = none_of([
+            close_delimiter.clone(),
+            top_level[0].clone(),
+            top_level[1].clone(),
+        ])
+        .repeated();
+
+        // Looks ahead without consuming anything, so that when the delimiter is closed this fails
+        // before the errors of the main parser and they take precedence
+        let is_unclosed = just(open_delimiter.clone())
+            .ignore_then(contents.clone())
+            .ignore_then(
+                just(close_delimiter.clone())
+                    .to(false)
+                    .or(end().to(true))
+                    .or(one_of(top_level).to(true)),
+            )
+            .rewind()
+            .try_map(|is_unclosed, span| {
+                is_unclosed
+                    .then_some(())
+                    .ok_or_else(|| Simple::custom(span, "Delimiter is closed"))
+            });
+
+        let unclosed = is_unclosed
+            .ignore_then(just(open_delimiter.clone()).map_with_span(|_, span: Span| span))
+            .then_ignore(contents)
+            .validate({
+                let open_delimiter = open_delimiter.clone();
+                let f = f.clone();
+                move |open_span, span, emit| {
+                    emit(Simple::custom(
+                        open_span,
+                        format!("Unclosed delimiter `{open_delimiter}`"),
+                    ));
+                    f(span)
+                }
+            });
+
         parser
             .delimited_by(just(open_delimiter.clone()), just(close_delimiter.clone()))
             .recover_with(nested_delimiters(
@@ -147,13 +360,19 @@ impl Ast {
                 ],
                 f,
             ))
+            .or(unclosed)
             .boxed()
     }
 
     // Parse high level functions
 
-    fn parse_include() -> impl Parser<Token, Spanned<Self>, Error = Simple<Token>> + Clone {
+    pub(crate) fn parse_include() -> impl Parser<Token, Spanned<Self>, Error = Simple<Token>> + Clone
+    {
         let extract_string = Self::extract_string();
+        let only = Self::extract_ident()
+            .separated_by(just(Token::Comma))
+            .allow_trailing()
+            .delimited_by(just(Token::OpenBrace), just(Token::CloseBrace));
 
         just(Token::Include)
             .ignore_then(extract_string.or_else(|_| Ok("____PARSING_ERROR".to_string())))
@@ -163,65 +382,102 @@ impl Ast {
                 }
                 string
             })
-            .map_with_span(|str: String, span| (Self::FileInclude { path: str }, span))
+            .then(only.or_not())
+            .map_with_span(|(path, only), span| (Self::FileInclude { path, only }, span))
         // Try and find other define / include tokens in case of failure
     }
 
-    fn parse_define() -> impl Parser<Token, Spanned<Self>, Error = Simple<Token>> + Clone {
-        let table_parser = Self::table_parser();
+    fn parse_define(
+        options: ParserOptions,
+    ) -> impl Parser<Token, Spanned<Self>, Error = Simple<Token>> + Clone {
+        let table_parser = Self::table_parser(options);
         let macro_parser = Self::parse_macro();
         let constant_parser = Self::parse_constants();
-        let abi_parser = Self::parse_abi_definition();
-        let event_parser = Self::parse_abi_event_definition();
-        let error_parser = Self::parse_errors();
+        let abi_parser = Self::parse_abi_definition(options);
+        let event_parser = Self::parse_abi_event_definition(options);
+        let error_parser = Self::parse_errors(options);
+        let constructor_parser = Self::parse_constructor(options);
 
         just(Token::Define).ignore_then(
             macro_parser
                 .or(error_parser)
+                .or(constructor_parser)
                 .or(abi_parser)
                 .or(event_parser)
                 .or(table_parser)
                 .or(constant_parser)
-                .or(any().map_with_span(|token, span| {
-                    (
-                        Self::ParsingError {
-                            token,
-                            message: "Expected keyword".to_string(),
-                        },
-                        span,
-                    )
-                })),
+                // The fallback must report an error too, otherwise it would be preferred over
+                // definitions that recovered from an error
+                .or(any()
+                    .validate(|token, span, emit| {
+                        emit(Simple::custom(span, "Expected keyword"));
+                        token
+                    })
+                    .map_with_span(|token, span| {
+                        (
+                            Self::ParsingError {
+                                token,
+                                message: "Expected keyword".to_string(),
+                            },
+                            span,
+                        )
+                    })),
         )
 
         // .or_else(|tok| Ok((Self::ParsingError("Unexpected".to_string()), tok.span())))
     }
 
-    fn parse_errors() -> impl Parser<Token, Spanned<Self>, Error = Simple<Token>> + Clone {
-        let parse_identifier = Self::extract_ident();
-        let func_params = Self::parse_abi_inputs();
+    pub(crate) fn parse_errors(
+        options: ParserOptions,
+    ) -> impl Parser<Token, Spanned<Self>, Error = Simple<Token>> + Clone {
+        let func_params = Self::parse_abi_inputs(options);
 
         just(Token::Error)
-            .ignore_then(parse_identifier)
+            .ignore_then(Self::parse_definition_name("error"))
             .then_ignore(just(Token::OpenParen))
             .then(func_params)
             .then_ignore(just(Token::CloseParen))
             .map_with_span(|(name, inputs), span| (Self::AbiError(Error { name, inputs }), span))
     }
 
+    /// Parses an abi constructor, `constructor(<inputs>)`
+    pub(crate) fn parse_constructor(
+        options: ParserOptions,
+    ) -> impl Parser<Token, Spanned<Self>, Error = Simple<Token>> + Clone {
+        let inputs = Self::parse_abi_inputs(options);
+
+        just(Token::Constructor)
+            .ignore_then(just(Token::OpenParen))
+            .ignore_then(inputs)
+            .then_ignore(just(Token::CloseParen))
+            .map_with_span(|inputs, span| {
+                (
+                    Self::AbiConstructor(Constructor {
+                        inputs: inputs.into_iter().map(|(input, _)| input).collect(),
+                    }),
+                    span,
+                )
+            })
+    }
+
     /// Table Parser
     ///
     /// Parses either a jump table or a code table, both are stored as the same root type, TableDefinition
     ///
-    fn table_parser() -> impl Parser<Token, Spanned<Self>, Error = Simple<Token>> + Clone {
+    /// A table may be named like an opcode, so that it can still be referenced from builtins such
+    /// as `__tablesize(add)`.
+    pub(crate) fn table_parser(
+        options: ParserOptions,
+    ) -> impl Parser<Token, Spanned<Self>, Error = Simple<Token>> + Clone {
         let jump_table = Self::parse_jump_table();
-        let code_table = Self::parse_code_table();
+        let code_table = Self::parse_code_table(options);
 
         jump_table.or(code_table)
     }
 
     fn parse_jump_table() -> impl Parser<Token, Spanned<Self>, Error = Simple<Token>> + Clone {
         let table_kind = Self::parse_jump_table_kind();
-        let ident = Self::extract_ident();
+        let ident = Self::extract_ident_or_opcode();
         let optional_parens = Self::parse_optional_paren();
 
         let table_contents = Self::nested_parser(
@@ -232,13 +488,14 @@ impl Ast {
         );
 
         table_kind
-            .then(ident)
-            .then_ignore(optional_parens.or_not())
+            .then(ident.map_with_span(|name, span| (name, span)))
+            .then_ignore(optional_parens)
             .then(table_contents)
-            .map_with_span(|((table_kind, name), contents), span| {
+            .map_with_span(|((table_kind, (name, name_span)), contents), span| {
                 (
                     Self::TableDefinition {
                         name,
+                        name_span,
                         kind: table_kind,
                         // TODO: parse these
                         statements: contents,
@@ -254,10 +511,13 @@ impl Ast {
             .or(just(Token::JumpTablePacked).to(TableKind::JumpTablePacked))
     }
 
+    /// Parses the optional `() =` between a table's name and its body. Either part may be
+    /// omitted, so `T() = {`, `T() {`, `T = {` and `T {` are all accepted.
     fn parse_optional_paren() -> impl Parser<Token, (), Error = Simple<Token>> + Clone {
         just(Token::OpenParen)
             .ignore_then(just(Token::CloseParen))
-            .ignore_then(just(Token::Assign))
+            .or_not()
+            .ignore_then(just(Token::Assign).or_not())
             .ignored()
     }
 
@@ -284,9 +544,11 @@ impl Ast {
             .repeated()
     }
 
-    fn parse_code_table() -> impl Parser<Token, Spanned<Self>, Error = Simple<Token>> + Clone {
-        let extract_code_table_code = Self::extract_code_table();
-        let ident = Self::extract_ident();
+    fn parse_code_table(
+        options: ParserOptions,
+    ) -> impl Parser<Token, Spanned<Self>, Error = Simple<Token>> + Clone {
+        let extract_code_table_code = Self::extract_code_table(options);
+        let ident = Self::extract_ident_or_opcode();
         let optional_parens = Self::parse_optional_paren();
 
         let code_table =
@@ -299,13 +561,14 @@ impl Ast {
 
         just(Token::CodeTable)
             .to(TableKind::CodeTable)
-            .ignore_then(ident)
-            .then_ignore(optional_parens.or_not())
+            .ignore_then(ident.map_with_span(|name, span| (name, span)))
+            .then_ignore(optional_parens)
             .then(code_table_parser)
-            .map_with_span(|(name, table_content), span| {
+            .map_with_span(|((name, name_span), table_content), span| {
                 (
                     Self::TableDefinition {
                         name,
+                        name_span,
                         kind: TableKind::CodeTable,
                         statements: vec![table_content],
                     },
@@ -314,20 +577,32 @@ impl Ast {
             })
     }
 
-    fn parse_constants() -> impl Parser<Token, Spanned<Self>, Error = Simple<Token>> + Clone {
-        let parse_identifier = Self::extract_ident();
+    pub(crate) fn parse_constants(
+    ) -> impl Parser<Token, Spanned<Self>, Error = Simple<Token>> + Clone {
         let constant_value = Self::parse_constant_value();
 
         just(Token::Constant)
-            .ignore_then(parse_identifier)
+            .ignore_then(
+                Self::parse_definition_name("constant").map_with_span(|name, span| (name, span)),
+            )
             .then_ignore(just(Token::Assign))
             .then(constant_value)
-            .map_with_span(|(name, value), span| (Self::ConstantDefinition { name, value }, span))
+            .map_with_span(|((name, name_span), value), span| {
+                (
+                    Self::ConstantDefinition {
+                        name,
+                        name_span,
+                        value,
+                    },
+                    span,
+                )
+            })
     }
 
-    fn parse_abi_event_definition(
+    pub(crate) fn parse_abi_event_definition(
+        options: ParserOptions,
     ) -> impl Parser<Token, Spanned<Self>, Error = Simple<Token>> + Clone {
-        let event_args = Self::parse_event_inputs();
+        let event_args = Self::parse_event_inputs(options);
         let ident = Self::extract_ident();
 
         let event_body =
@@ -343,28 +618,32 @@ impl Ast {
                 )]
             });
 
+        // `anonymous` is not a keyword, so it is lexed as an identifier
+        let anonymous = just(Token::Ident("anonymous".to_string())).or_not();
+
         just(Token::Event)
             .ignore_then(ident)
             .then(event_body)
-            .map_with_span(|(name, inputs), span| {
+            .then(anonymous)
+            .map_with_span(|((name, inputs), anonymous), span| {
                 (
                     Self::AbiEvent(Event {
                         name,
                         inputs,
-                        // TODO: work out what this is
-                        anonymous: false,
+                        anonymous: anonymous.is_some(),
                     }),
                     span,
                 )
             })
     }
 
-    fn parse_abi_definition() -> impl Parser<Token, Spanned<Self>, Error = Simple<Token>> + Clone {
-        let parse_identifier = Self::extract_ident();
-        let parse_return_types = Self::parse_return_type();
+    pub(crate) fn parse_abi_definition(
+        options: ParserOptions,
+    ) -> impl Parser<Token, Spanned<Self>, Error = Simple<Token>> + Clone {
+        let parse_return_types = Self::parse_return_type(options);
         let parse_visibility = Self::parse_abi_visibility();
 
-        let parse_abi_args = Self::parse_abi_inputs().or_not();
+        let parse_abi_args = Self::parse_abi_inputs(options).or_not();
         let nested_parse_abi_args = Self::nested_parser(
             parse_abi_args,
             Token::OpenParen,
@@ -373,7 +652,7 @@ impl Ast {
         );
 
         just(Token::Function)
-            .ignore_then(parse_identifier)
+            .ignore_then(Self::parse_definition_name("function"))
             .then(nested_parse_abi_args)
             .then(parse_visibility)
             .then(parse_return_types.or_not())
@@ -393,19 +672,50 @@ impl Ast {
             })
     }
 
+    /// Parse Abi Visibility
+    ///
+    /// Parses the state mutability of a function. Every keyword written is consumed so that a
+    /// contradiction such as `payable view` is reported as such, rather than as an unexpected
+    /// token. Only one keyword is allowed, the first is kept.
     fn parse_abi_visibility(
     ) -> impl Parser<Token, Spanned<FunctionType>, Error = Simple<Token>> + Clone {
         just(Token::View)
-            .map_with_span(|_, span| (FunctionType::View, span))
-            .or(just(Token::Payable).map_with_span(|_, span| (FunctionType::Payable, span)))
-            .or(just(Token::NonPayable).map_with_span(|_, span| (FunctionType::NonPayable, span)))
-            .or(just(Token::Pure).map_with_span(|_, span| (FunctionType::Pure, span)))
+            .or(just(Token::Payable))
+            .or(just(Token::NonPayable))
+            .or(just(Token::Pure))
+            .map_with_span(|token, span| (token, span))
+            .repeated()
+            .at_least(1)
+            .validate(|keywords: Vec<Spanned<Token>>, _, emit| {
+                let (first, first_span) = keywords[0].clone();
+                let keyword = |token: &Token| token.keyword().unwrap_or_default();
+                for (token, span) in &keywords[1..] {
+                    let message = match *token == first {
+                        true => format!("`{}` is repeated", keyword(token)),
+                        false => format!(
+                            "A function cannot be both `{}` and `{}`",
+                            keyword(&first),
+                            keyword(token)
+                        ),
+                    };
+                    emit(Simple::custom(span.clone(), message));
+                }
+
+                let state_mutability = match first {
+                    Token::View => FunctionType::View,
+                    Token::Payable => FunctionType::Payable,
+                    Token::NonPayable => FunctionType::NonPayable,
+                    _ => FunctionType::Pure,
+                };
+                (state_mutability, first_span)
+            })
     }
 
     fn parse_return_type(
+        options: ParserOptions,
     ) -> impl Parser<Token, Vec<Spanned<FunctionParam>>, Error = Simple<Token>> + Clone {
         let abi_outputs = Self::nested_parser(
-            Self::parse_abi_inputs(),
+            Self::parse_abi_inputs(options),
             Token::OpenParen,
             Token::CloseParen,
             |_span| Vec::new(),
@@ -414,75 +724,150 @@ impl Ast {
         just(Token::Returns).ignore_then(abi_outputs)
     }
 
-    fn parse_parameter_kind() -> impl Parser<Token, String, Error = Simple<Token>> + Clone {
+    fn parse_parameter_kind() -> impl Parser<Token, ArgumentLocation, Error = Simple<Token>> + Clone
+    {
         just(Token::Memory)
-            .map(|_| "memory".to_string())
-            .or(just(Token::Storage).map(|_| "storage".to_string()))
-            .or(just(Token::Calldata).map(|_| "calldata".to_string()))
+            .to(ArgumentLocation::Memory)
+            .or(just(Token::Storage).to(ArgumentLocation::Storage))
+            .or(just(Token::Calldata).to(ArgumentLocation::Calldata))
     }
 
     // // TODO: change return type
     /// Parse a function abi input
     ///
     /// This parses a grammar in the following format
-    /// (<type> <kind(memory|storage)>? <name>)
-    fn parse_abi_inputs(
+    /// (<type> <location(memory|storage|calldata)>? <name>)
+    pub(crate) fn parse_abi_inputs(
+        options: ParserOptions,
     ) -> impl Parser<Token, Vec<Spanned<FunctionParam>>, Error = Simple<Token>> + Clone {
         let primitive = Self::extract_primitive();
         let param_kind = Self::parse_parameter_kind();
         let ident = Self::extract_ident();
 
-        primitive
+        let param = primitive
             .then(param_kind.or_not())
             .then(ident.or_not())
-            .map_with_span(
-                // TODO: workout what the internal type field is in huff-rs
-                |((param_kind, internal_type), name), span| {
-                    (
-                        FunctionParam {
-                            name: name.unwrap_or("".to_string()),
-                            // TODO: handle arrays / tuples
-                            kind: param_kind,
-                            // TODO: implement this properly
-                            internal_type,
-                        },
-                        span,
-                    )
-                },
-            )
-            .then_ignore(just(Token::Comma).or_not())
-            .repeated()
+            .map_with_span(|((param_kind, location), name), span| {
+                (
+                    FunctionParam {
+                        name: name.unwrap_or("".to_string()),
+                        // TODO: handle arrays / tuples
+                        kind: param_kind,
+                        // TODO: workout what the internal type field is in huff-rs
+                        internal_type: None,
+                        location,
+                    },
+                    span,
+                )
+            });
+
+        Self::parse_param_list(param, options)
     }
 
-    fn parse_event_inputs(
+    pub(crate) fn parse_event_inputs(
+        options: ParserOptions,
     ) -> impl Parser<Token, Vec<Spanned<EventParam>>, Error = Simple<Token>> + Clone {
         let primitive = Self::extract_primitive();
         let ident = Self::extract_ident();
 
-        primitive
+        // `indexed` is only valid between the type and the name, but is also accepted after the name
+        // so that a clear error can be reported
+        let misplaced_indexed = just(Token::Indexed)
+            .map_with_span(|_, span: Span| span)
+            .or_not();
+
+        let param = primitive
             .then(just(Token::Indexed).or_not())
             .then(ident.or_not())
-            .map_with_span(|((kind, indexed), name), span| {
+            .then(misplaced_indexed)
+            .validate(|(((kind, indexed), name), misplaced), _span, emit| {
+                if let Some(span) = &misplaced {
+                    emit(Simple::custom(
+                        span.clone(),
+                        "`indexed` must be placed between the parameter type and name",
+                    ))
+                }
+                (kind, indexed.is_some() || misplaced.is_some(), name)
+            })
+            .map_with_span(|(kind, indexed, name), span| {
                 (
                     EventParam {
                         name: name.unwrap_or("".to_string()),
                         kind,
-                        indexed: indexed.is_some(),
+                        indexed,
                     },
                     span,
                 )
-            })
-            .then_ignore(just(Token::Comma).or_not())
-            .repeated()
+            });
+
+        Self::parse_param_list(param, options)
+    }
+
+    /// Parse a list of abi parameters
+    ///
+    /// Commas between parameters are optional, unless `strict_abi_commas` is set, in which case
+    /// exactly one is required between parameters and none may follow the last.
+    fn parse_param_list<T: Clone>(
+        param: impl Parser<Token, Spanned<T>, Error = Simple<Token>> + Clone,
+        options: ParserOptions,
+    ) -> impl Parser<Token, Vec<Spanned<T>>, Error = Simple<Token>> + Clone {
+        let comma = just(Token::Comma).map_with_span(|_, span: Span| span);
+
+        param.then(comma.or_not()).repeated().validate(
+            move |params: Vec<(Spanned<T>, Option<Span>)>, _span, emit| {
+                if options.strict_abi_commas {
+                    for (index, (_, comma)) in params.iter().enumerate() {
+                        match (comma, params.get(index + 1)) {
+                            (Some(comma), None) => {
+                                emit(Simple::custom(comma.clone(), "Unexpected trailing `,`"))
+                            }
+                            (None, Some(((_, next), _))) => emit(Simple::custom(
+                                next.clone(),
+                                "Expected `,` between parameters",
+                            )),
+                            _ => {}
+                        }
+                    }
+                }
+                params.into_iter().map(|(param, _)| param).collect()
+            },
+        )
     }
 
-    fn parse_constant_value() -> impl Parser<Token, ConstantValue, Error = Simple<Token>> + Clone {
+    pub(crate) fn parse_constant_value(
+    ) -> impl Parser<Token, ConstantValue, Error = Simple<Token>> + Clone {
         let parse_literal = Self::extract_literal();
         let parse_fsp = Self::parse_fsp();
+        let parse_constant_ref = Self::extract_ident()
+            .map_with_span(|name, span| (name, span))
+            .delimited_by(just(Token::OpenBracket), just(Token::CloseBracket));
+
+        let operator = just(Token::Add)
+            .to(ArithOp::Add)
+            .or(just(Token::Sub).to(ArithOp::Sub))
+            .or(just(Token::Mul).to(ArithOp::Mul))
+            .or(just(Token::Div).to(ArithOp::Div));
+        // Checked here, as evaluating the expression follows the EVM and gives zero
+        let operand = operator
+            .then(Self::extract_literal())
+            .validate(|(op, lit), span, emit| {
+                if op == ArithOp::Div && lit == [0u8; 32] {
+                    emit(Simple::custom(
+                        span,
+                        "Division by zero in constant expression",
+                    ));
+                }
+                (op, lit)
+            });
 
         parse_literal
-            .map(ConstantValue::Literal)
+            .then(operand.repeated())
+            .map(|(first, rest)| match rest.is_empty() {
+                true => ConstantValue::Literal(first),
+                false => ConstantValue::Expression(first, rest),
+            })
             .or(parse_fsp.to(ConstantValue::FreeStoragePointer))
+            .or(parse_constant_ref.map(ConstantValue::ConstantRef))
     }
 
     fn parse_fsp() -> impl Parser<Token, (), Error = Simple<Token>> + Clone {
@@ -491,7 +876,8 @@ impl Ast {
             .ignored()
     }
 
-    fn parse_macro() -> impl Parser<Token, Spanned<Self>, Error = Simple<Token>> + Clone {
+    pub(crate) fn parse_macro() -> impl Parser<Token, Spanned<Self>, Error = Simple<Token>> + Clone
+    {
         let parse_macro_type = Self::parse_macro_type();
         let parse_identifier = Self::extract_ident();
 
@@ -502,8 +888,7 @@ impl Ast {
             |span| vec![(Arg::Invalid, span)],
         );
 
-        let parse_takes = Self::parse_takes();
-        let parse_returns = Self::parse_returns();
+        let parse_annotations = Self::parse_stack_annotations();
 
         let macro_body = Self::nested_parser(
             Self::parse_macro_body(),
@@ -513,24 +898,26 @@ impl Ast {
         );
 
         parse_macro_type
-            .then(parse_identifier)
+            .then(parse_identifier.map_with_span(|name, span| (name, span)))
             .then(parse_args)
             .then_ignore(just(Token::Assign))
-            .then(parse_takes.or_not())
-            .then(parse_returns.or_not())
+            .then(parse_annotations)
             .then(macro_body)
             // TODO: recover with open and close delimiters
             .map_with_span(
-                |(((((macro_type, name), args), takes), returns), body), span| {
+                |((((macro_type, (name, name_span)), args), (takes, returns)), body), span| {
                     (
                         Self::MacroDefinition {
                             name,
+                            name_span: name_span.clone(),
                             macro_type,
-                            // Todo: more suitable default for takes and returns
-                            takes: takes.unwrap_or((0, 0..0)),
-                            returns: returns.unwrap_or((0, 0..0)),
+                            // Omitted annotations are zero, and point at the macro's name so that
+                            // diagnostics about them have somewhere sensible to point
+                            takes: takes.unwrap_or((0, name_span.clone())),
+                            returns: returns.unwrap_or((0, name_span)),
                             statements: body,
                             args,
+                            decorator: None,
                         },
                         span,
                     )
@@ -538,85 +925,267 @@ impl Ast {
             )
     }
 
+    /// Parse Decorator
+    ///
+    /// Parses a decorator, `#[calldata("0x..."), value(0x01)]`. The `value` flag takes a literal
+    /// and the `calldata` flag takes a string. A flag given the wrong kind of payload is reported
+    /// and kept with an empty payload, an unknown flag is reported and dropped.
+    pub(crate) fn parse_decorator() -> impl Parser<Token, Decorator, Error = Simple<Token>> + Clone
+    {
+        let payload = select! {
+            token @ Token::Literal(_) => token,
+            token @ Token::Str(_) => token,
+        }
+        .labelled("decorator_payload")
+        .delimited_by(just(Token::OpenParen), just(Token::CloseParen));
+
+        // Opcodes are accepted so that a flag such as `gas` is reported as unknown rather than
+        // unexpected. Data locations are only keywords within ABI declarations, so `calldata` is
+        // an identifier here.
+        let name = Self::extract_ident_or_opcode();
+
+        let flag = name.then(payload).validate(|(name, payload), span, emit| {
+            let Ok(flag) = DecoratorFlag::try_from(&name) else {
+                emit(Simple::custom(
+                    span,
+                    format!("Unknown decorator flag `{name}`"),
+                ));
+                return None;
+            };
+            Some(match (flag, payload) {
+                (DecoratorFlag::Value(_), Token::Literal(value)) => DecoratorFlag::Value(value),
+                (DecoratorFlag::Calldata(_), Token::Str(calldata)) => {
+                    DecoratorFlag::Calldata(calldata)
+                }
+                (flag, _) => {
+                    let expected = match flag {
+                        DecoratorFlag::Value(_) => "a literal",
+                        DecoratorFlag::Calldata(_) => "a string",
+                    };
+                    emit(Simple::custom(
+                        span,
+                        format!("The `{name}` flag takes {expected}"),
+                    ));
+                    flag
+                }
+            })
+        });
+
+        just(Token::Pound)
+            .ignore_then(
+                flag.separated_by(just(Token::Comma))
+                    .allow_trailing()
+                    .delimited_by(just(Token::OpenBracket), just(Token::CloseBracket)),
+            )
+            .map(|flags| Decorator {
+                flags: flags.into_iter().flatten().collect(),
+            })
+    }
+
     fn parse_macro_type() -> impl Parser<Token, Spanned<MacroType>, Error = Simple<Token>> + Clone {
         just(Token::Macro)
             .to(MacroType::Macro)
             .or(just(Token::Fn).to(MacroType::Fn))
+            .or(just(Token::Test).to(MacroType::Test))
             .map_with_span(|tok, span| (tok, span))
     }
 
     // TODO: Morph parse takes and parse returns into one
     fn parse_takes() -> impl Parser<Token, Spanned<usize>, Error = Simple<Token>> + Clone {
-        let number = Self::extract_number();
-
-        just(Token::Takes)
-            .ignore_then(just(Token::OpenParen))
-            .ignore_then(number.or_not())
-            .then_ignore(just(Token::CloseParen))
-            .map_with_span(|num_takes: Option<usize>, span| {
-                let takes = num_takes.unwrap_or(0);
-                (takes, span)
-            })
+        Self::parse_stack_annotation(Token::Takes, "takes")
     }
     fn parse_returns() -> impl Parser<Token, Spanned<usize>, Error = Simple<Token>> + Clone {
+        Self::parse_stack_annotation(Token::Returns, "returns")
+    }
+
+    /// Parse Stack Annotations
+    ///
+    /// Parses the optional `takes (n)` and `returns (n)` of a macro, which may be written in
+    /// either order. Each is `None` when it is omitted.
+    fn parse_stack_annotations(
+    ) -> impl Parser<Token, (Option<Spanned<usize>>, Option<Spanned<usize>>), Error = Simple<Token>>
+           + Clone {
+        let takes_first = Self::parse_takes()
+            .then(Self::parse_returns().or_not())
+            .map(|(takes, returns)| (Some(takes), returns));
+        let returns_first = Self::parse_returns()
+            .then(Self::parse_takes().or_not())
+            .map(|(returns, takes)| (takes, Some(returns)));
+
+        takes_first
+            .or(returns_first)
+            .or_not()
+            .map(|annotations| annotations.unwrap_or((None, None)))
+    }
+
+    /// Parse Stack Annotation
+    ///
+    /// Parses `takes (n)` or `returns (n)`, where an empty annotation is zero. More than one
+    /// number is reported rather than failing the macro definition, and the first is kept.
+    fn parse_stack_annotation(
+        keyword: Token,
+        name: &'static str,
+    ) -> impl Parser<Token, Spanned<usize>, Error = Simple<Token>> + Clone {
         let number = Self::extract_number();
 
-        just(Token::Returns)
+        just(keyword)
             .ignore_then(just(Token::OpenParen))
-            .ignore_then(number.or_not())
+            .ignore_then(number.separated_by(just(Token::Comma)))
             .then_ignore(just(Token::CloseParen))
-            .map_with_span(|num_returns: Option<usize>, span| {
-                let takes = num_returns.unwrap_or(0);
-                (takes, span)
+            .validate(move |numbers: Vec<usize>, span, emit| {
+                if numbers.len() > 1 {
+                    emit(Simple::custom(
+                        span.clone(),
+                        format!(
+                            "`{name}` expects a single stack size, found {}",
+                            numbers.len()
+                        ),
+                    ));
+                }
+                (numbers.first().copied().unwrap_or(0), span)
             })
     }
 
     /// Parse arguments to macro calls
     ///
     /// Note: This cannot be used in abi function calls
-    fn parse_args() -> impl Parser<Token, Args, Error = Simple<Token>> + Clone {
-        let ident = Self::extract_ident();
-        let num = Self::extract_number();
-        let literal = Self::extract_literal();
+    pub(crate) fn parse_args() -> impl Parser<Token, Args, Error = Simple<Token>> + Clone {
+        // Parameters of a definition must be names
+        let invalid = Self::extract_number()
+            .ignored()
+            .or(Self::extract_literal().ignored())
+            .to(Arg::Invalid);
+
+        // References only make sense when invoking a macro. They are reported together, as a
+        // definition with more errors loses to the fallback for an unknown definition
+        Self::parse_named_arg(Self::extract_ident())
+            .or(invalid)
+            .map_with_span(|arg, span| (arg, span))
+            .then_ignore(just(Token::Comma).or_not())
+            .repeated()
+            .validate(|mut args: Args, _, emit| {
+                let mut references = Vec::new();
+                let mut first = None;
+                for (arg, span) in &mut args {
+                    let reference = match arg {
+                        Arg::ConstantRef((name, _)) => format!("[{name}]"),
+                        Arg::ArgRef((name, _)) => format!("<{name}>"),
+                        _ => continue,
+                    };
+                    references.push(format!("`{reference}`"));
+                    first.get_or_insert(span.clone());
+                    *arg = Arg::Invalid;
+                }
+                if let Some(span) = first {
+                    emit(Simple::custom(
+                        span,
+                        format!(
+                            "Macro parameters must be names, found {}",
+                            references.join(", ")
+                        ),
+                    ));
+                }
+                args
+            })
+    }
 
-        ident
-            .map_with_span(|arg, span| (Arg::Valid(arg), span))
-            // Handle invalid cases
-            .or(num.map_with_span(|_, span| (Arg::Invalid, span)))
-            .or(literal.map_with_span(|_, span| (Arg::Invalid, span)))
+    /// Parse the arguments of a macro or builtin invocation
+    fn parse_invocation_args() -> impl Parser<Token, Args, Error = Simple<Token>> + Clone {
+        Self::parse_invocation_arg()
             .then_ignore(just(Token::Comma).or_not())
             .repeated()
     }
 
-    fn parse_macro_body(
+    /// Parse a single argument of a macro or builtin invocation
+    ///
+    /// Accepts a name, a number, a literal, a constant reference `[CONST]` or an argument
+    /// reference `<arg>`. Opcodes are lexed as [`Token::Opcode`] rather than identifiers, so an
+    /// argument naming one, `FOO(add)`, is accepted as its mnemonic.
+    pub(crate) fn parse_invocation_arg(
+    ) -> impl Parser<Token, Spanned<Arg>, Error = Simple<Token>> + Clone {
+        Self::parse_named_arg(Self::extract_ident_or_opcode())
+            .or(Self::extract_number().map(Arg::Number))
+            .or(Self::extract_literal().map(Arg::Literal))
+            .map_with_span(|arg, span| (arg, span))
+    }
+
+    /// Parse an argument that is a name, a constant reference `[CONST]` or an argument reference
+    /// `<arg>`
+    fn parse_named_arg(
+        ident: impl Parser<Token, String, Error = Simple<Token>> + Clone,
+    ) -> impl Parser<Token, Arg, Error = Simple<Token>> + Clone {
+        let constant_ref = Self::extract_ident()
+            .map_with_span(|name, span| (name, span))
+            .delimited_by(just(Token::OpenBracket), just(Token::CloseBracket))
+            .map(Arg::ConstantRef);
+        let arg_ref = Self::extract_ident()
+            .map_with_span(|name, span| (name, span))
+            .delimited_by(just(Token::LeftAngle), just(Token::RightAngle))
+            .map(Arg::ArgRef);
+
+        ident.map(Arg::Valid).or(constant_ref).or(arg_ref)
+    }
+
+    pub(crate) fn parse_macro_body(
     ) -> impl Parser<Token, Vec<Spanned<MacroBody>>, Error = Simple<Token>> + Clone {
         let opcode = Self::extract_opcode();
         let macro_invocation = Self::parse_macro_invocation();
         let builtin_invocation = Self::parse_builtin_invocation();
         let jump_label = Self::parse_jump_label();
         let arg_invocation = Self::parse_arg_invocation();
+        let constant_ref = Self::parse_constant_ref();
         let hex_literal = Self::parse_hex_literal();
+        let auto_push = Self::parse_auto_push();
 
-        let extract_num = Self::extract_number();
-
-        let unexpected_macro_chars = extract_num
-            .map_with_span(|num: usize, span| (MacroBody::UnexpectedToken(num.to_string()), span));
+        // Decimal numbers are literals too, such as the immediate of `push1 32`. Those too large
+        // for a number were already lexed as literals
+        let decimal_literal = Self::extract_number()
+            .map_with_span(|num, span| (MacroBody::HexLiteral(number_to_literal(num)), span));
         let unexpected_keyword = filter::<Token, _, Simple<Token>>(|token| {
             // TODO: create a vector of keywords invalid inside a macro
-            let invalid_keywords = [Token::Macro, Token::Function, Token::Storage];
+            // Data locations such as `storage` are identifiers outside of ABI declarations, so they
+            // can name labels
+            let invalid_keywords = [Token::Macro, Token::Function];
             invalid_keywords.contains(token)
         })
         .map_with_span(|token: Token, span| (MacroBody::UnexpectedToken(token.to_string()), span));
+        // Unknown tokens, such as an unrecognised `__builtin(..)`, were already reported by the
+        // lexer
+        let unknown = select! { Token::Unknown(name) => name }
+            .then_ignore(
+                Self::parse_invocation_args()
+                    .delimited_by(just(Token::OpenParen), just(Token::CloseParen))
+                    .or_not(),
+            )
+            .map_with_span(|name, span| (MacroBody::UnexpectedToken(name), span));
+        let misplaced_fsp = Self::parse_fsp()
+            .or(just(Token::FreeStoragePointer).ignored())
+            .validate(|_, span, emit| {
+                emit(Simple::custom(
+                    span,
+                    "FREE_STORAGE_POINTER is only valid in constant definitions",
+                ))
+            })
+            .map_with_span(|_, span| {
+                (
+                    MacroBody::UnexpectedToken(Token::FreeStoragePointer.to_string()),
+                    span,
+                )
+            });
 
         opcode
             .map_with_span(|tok, span| (MacroBody::Opcode(tok), span))
             .or(macro_invocation)
             .or(hex_literal)
+            .or(decimal_literal)
             .or(arg_invocation)
+            .or(constant_ref)
             .or(builtin_invocation)
+            .or(auto_push)
             .or(jump_label)
-            .or(unexpected_macro_chars)
             .or(unexpected_keyword)
+            .or(unknown)
+            .or(misplaced_fsp)
             .repeated()
     }
 
@@ -627,6 +1196,19 @@ impl Ast {
         get_literal.map_with_span(|lit, span| (MacroBody::HexLiteral(lit), span))
     }
 
+    /// Parse Auto Push
+    ///
+    /// `push` is not an opcode on its own, so it is lexed as an identifier. When followed by a
+    /// literal or a decimal number it is the sizeless push pseudo-opcode rather than a jump label.
+    fn parse_auto_push() -> impl Parser<Token, Spanned<MacroBody>, Error = Simple<Token>> + Clone {
+        let get_literal = Self::extract_literal();
+        let get_number = Self::extract_number().map(number_to_literal);
+
+        just(Token::Ident("push".to_string()))
+            .ignore_then(get_literal.or(get_number))
+            .map_with_span(|lit, span| (MacroBody::AutoPush(lit), span))
+    }
+
     /// Parse Jump Label
     ///
     /// Parses jump labels in the pattern (ident, Option<:>). If the option resolves to have a value
@@ -650,15 +1232,25 @@ impl Ast {
         let ident = Self::extract_ident();
 
         just(Token::LeftAngle)
-            .ignore_then(ident)
+            .ignore_then(ident.map_with_span(|name, span| (name, span)))
             .then_ignore(just(Token::RightAngle))
             .map_with_span(|arg, span| (MacroBody::ArgsInvocation(arg), span))
     }
 
+    fn parse_constant_ref() -> impl Parser<Token, Spanned<MacroBody>, Error = Simple<Token>> + Clone
+    {
+        let ident = Self::extract_ident();
+
+        just(Token::OpenBracket)
+            .ignore_then(ident.map_with_span(|name, span| (name, span)))
+            .then_ignore(just(Token::CloseBracket))
+            .map_with_span(|name, span| (MacroBody::ConstantRef(name), span))
+    }
+
     fn parse_builtin_invocation(
     ) -> impl Parser<Token, Spanned<MacroBody>, Error = Simple<Token>> + Clone {
         let builtin_ident = Self::extract_builtin_ident();
-        let parse_args = Self::parse_args();
+        let parse_args = Self::parse_invocation_args();
 
         builtin_ident
             .then_ignore(just(Token::OpenParen))
@@ -670,7 +1262,7 @@ impl Ast {
     fn parse_macro_invocation(
     ) -> impl Parser<Token, Spanned<MacroBody>, Error = Simple<Token>> + Clone {
         let ident = Self::extract_ident();
-        let parse_args = Self::parse_args();
+        let parse_args = Self::parse_invocation_args();
 
         ident
             .then_ignore(just(Token::OpenParen))
@@ -690,6 +1282,39 @@ impl Ast {
         select! { Token::Ident(str) => str}.labelled("identifier")
     }
 
+    /// An identifier, or an opcode standing in for one. Opcodes are lexed as [`Token::Opcode`]
+    /// rather than identifiers, so a name that is also a mnemonic is taken as that mnemonic.
+    fn extract_ident_or_opcode() -> impl Parser<Token, String, Error = Simple<Token>> + Clone {
+        let opcode = Self::extract_opcode().map(|opcode| opcode.mnemonic().to_string());
+
+        Self::extract_ident().or(opcode)
+    }
+
+    /// Parse Definition Name
+    ///
+    /// Parses the identifier following a definition keyword. When the identifier is missing the
+    /// error points at the token found in its place, as the span of input consumed by a parser that
+    /// matched nothing is empty. Parsing continues with an empty name so that the rest of the
+    /// definition is still checked.
+    fn parse_definition_name(
+        keyword: &'static str,
+    ) -> impl Parser<Token, String, Error = Simple<Token>> + Clone {
+        let ident = Self::extract_ident();
+
+        // The span is taken while the token is consumed, before rewinding
+        let found = any().map_with_span(|token, span| (token, span)).rewind();
+
+        ident.or(
+            found.validate(move |(token, span): Spanned<Token>, _, emit| {
+                emit(Simple::custom(
+                    span,
+                    format!("Expected a name after `{keyword}`, found `{token}`"),
+                ));
+                String::new()
+            }),
+        )
+    }
+
     fn extract_opcode() -> impl Parser<Token, Opcode, Error = Simple<Token>> + Clone {
         select! {Token::Opcode(opcode) => opcode}.labelled("opcode")
     }
@@ -706,71 +1331,91 @@ impl Ast {
         select! { Token::Num(val) => val}.labelled("number")
     }
 
-    // TODO: handle tuple definitions
+    /// Parses an abi type. Tuples are written as a parenthesised list of component types, such as
+    /// `(uint256,address)`, and may be followed by array dimensions.
     fn extract_primitive() -> impl Parser<Token, FunctionParamType, Error = Simple<Token>> + Clone {
-        let fixed_primitive = Self::extract_fixed_primitive();
-        let array_primitive = Self::extract_array_primitive();
-
-        fixed_primitive.or(array_primitive)
+        recursive(|primitive| {
+            let fixed_primitive = Self::extract_fixed_primitive();
+            let array_primitive = Self::extract_array_primitive();
+
+            // A dynamic dimension, `[]`, is stored as zero, matching array types
+            let dimension = Self::extract_number()
+                .or_not()
+                .delimited_by(just(Token::OpenBracket), just(Token::CloseBracket))
+                .map(|size| size.unwrap_or(0));
+
+            let tuple = primitive
+                .separated_by(just(Token::Comma))
+                .at_least(1)
+                .delimited_by(just(Token::OpenParen), just(Token::CloseParen))
+                .map(FunctionParamType::Tuple)
+                .then(dimension.repeated())
+                .map(|(tuple, dimensions)| {
+                    if dimensions.is_empty() {
+                        tuple
+                    } else {
+                        FunctionParamType::Array(Box::new(tuple), dimensions)
+                    }
+                })
+                .labelled("tuple");
+
+            fixed_primitive.or(array_primitive).or(tuple)
+        })
     }
 
     fn extract_fixed_primitive(
     ) -> impl Parser<Token, FunctionParamType, Error = Simple<Token>> + Clone {
         select! {Token::PrimitiveType(prim_type) => prim_type}
             .labelled("primitive_type")
-            .map(|token| match token {
-                PrimitiveEVMType::Address => FunctionParamType::Address,
-                PrimitiveEVMType::DynBytes => FunctionParamType::Bytes,
-                PrimitiveEVMType::Bool => FunctionParamType::Bool,
-                PrimitiveEVMType::String => FunctionParamType::String,
-                PrimitiveEVMType::Int(v) => FunctionParamType::Int(v),
-                PrimitiveEVMType::Bytes(v) => FunctionParamType::FixedBytes(v),
-                PrimitiveEVMType::Uint(v) => FunctionParamType::Uint(v),
-            })
+            .map(FunctionParamType::from)
     }
 
-    // TODO: shorten
     fn extract_array_primitive(
     ) -> impl Parser<Token, FunctionParamType, Error = Simple<Token>> + Clone {
         select! { Token::ArrayType(primitive, array) => (primitive, array)}
             .labelled("array_primitive")
-            .map(|(primitive, arr)| match primitive {
-                PrimitiveEVMType::Address => {
-                    FunctionParamType::Array(Box::new(FunctionParamType::Address), arr)
-                }
-                PrimitiveEVMType::DynBytes => {
-                    FunctionParamType::Array(Box::new(FunctionParamType::Bytes), arr)
-                }
-                PrimitiveEVMType::String => {
-                    FunctionParamType::Array(Box::new(FunctionParamType::String), arr)
-                }
-                PrimitiveEVMType::Bool => {
-                    FunctionParamType::Array(Box::new(FunctionParamType::Bool), arr)
-                }
-                PrimitiveEVMType::Int(v) => {
-                    FunctionParamType::Array(Box::new(FunctionParamType::Int(v)), arr)
-                }
-                PrimitiveEVMType::Uint(v) => {
-                    FunctionParamType::Array(Box::new(FunctionParamType::Uint(v)), arr)
-                }
-                PrimitiveEVMType::Bytes(v) => {
-                    FunctionParamType::Array(Box::new(FunctionParamType::FixedBytes(v)), arr)
-                }
-            })
+            .map(|(primitive, arr)| FunctionParamType::Array(Box::new(primitive.into()), arr))
     }
 
-    fn extract_code_table() -> impl Parser<Token, String, Error = Simple<Token>> + Clone {
+    /// Code table contents, normalized to lowercase hex with a whole number of bytes. Whether it
+    /// was lexed as a literal or as code, the same digits produce the same string.
+    fn extract_code_table(
+        options: ParserOptions,
+    ) -> impl Parser<Token, String, Error = Simple<Token>> + Clone {
         let code_from_literal = Self::extract_code_from_literal();
         let code = Self::extract_code();
 
-        code_from_literal.or(code)
+        code_from_literal
+            .or(code)
+            .validate(move |code, span, emit| {
+                if code.len() % 2 == 1 && options.strict_code_tables {
+                    emit(Simple::custom(
+                        span,
+                        format!(
+                            "Code table contents must be whole bytes, found {} hex digits",
+                            code.len()
+                        ),
+                    ));
+                }
+                format_even_bytes(code)
+            })
+            .labelled("codetable_code")
     }
 
     fn extract_code_from_literal() -> impl Parser<Token, String, Error = Simple<Token>> + Clone {
-        select! { Token::Literal(lit) => bytes32_to_string(&lit, false)}.labelled("codetable_code")
+        select! { Token::Literal(lit) => lit }.map_with_span(|lit, span: Span| {
+            // A literal only keeps its value, so the digits as written are recovered from the
+            // length of its `0x` prefixed span. Leading zero bytes are part of the code.
+            match span.len().checked_sub(2) {
+                Some(digits @ 1..=64) => {
+                    literal_to_hex(&lit, false, false)[64 - digits..].to_string()
+                }
+                _ => bytes32_to_string(&lit, false),
+            }
+        })
     }
 
     fn extract_code() -> impl Parser<Token, String, Error = Simple<Token>> + Clone {
-        select! { Token::Code(string) => string}.labelled("codetable_code")
+        select! { Token::Code(string) => string.to_ascii_lowercase() }
     }
 }
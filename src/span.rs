@@ -8,6 +8,47 @@ pub type Span = std::ops::Range<usize>;
 /// A pair of (`T`, [`Span`]).
 pub type Spanned<T> = (T, Span);
 
+/// A one based line and column in source code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LineCol {
+    /// The line, starting at 1
+    pub line: usize,
+    /// The column in characters, starting at 1
+    pub col: usize,
+}
+
+/// Converts a character offset, as used by spans, into a line and column. Offsets past the end of
+/// the source point just past its last character.
+///
+/// Line endings are counted the same way the lexer reads them, so `\n`, `\r\n` and a lone `\r`
+/// each start a new line.
+pub fn offset_to_line_col(src: &str, offset: usize) -> LineCol {
+    let mut position = LineCol { line: 1, col: 1 };
+    let mut chars = src.chars().peekable();
+    for _ in 0..offset {
+        let Some(c) = chars.next() else { break };
+        if c == '\n' || (c == '\r' && chars.peek() != Some(&'\n')) {
+            position.line += 1;
+            position.col = 1;
+        } else {
+            position.col += 1;
+        }
+    }
+    position
+}
+
+/// Renders a spanned value with its location as `line:col-line:col` rather than a raw range,
+/// such as `Ident("FOO") @ 2:5-2:8`. The end is the position just past the last character.
+pub fn debug_with_source(spanned: &Spanned<impl std::fmt::Debug>, src: &str) -> String {
+    let (value, span) = spanned;
+    let start = offset_to_line_col(src, span.start);
+    let end = offset_to_line_col(src, span.end);
+    format!(
+        "{value:?} @ {}:{}-{}:{}",
+        start.line, start.col, end.line, end.col
+    )
+}
+
 // use std::{fmt, ops::Range};
 
 // /// A span of source code corrseponding to a token (or something).
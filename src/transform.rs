@@ -0,0 +1,174 @@
+//! Transforms
+//!
+//! Rewrites of the parsed ast, or of a lowered [`Contract`], that do not change its meaning. Nodes
+//! are updated in place, so the spans of rewritten definitions still point at their original
+//! source.
+
+use std::collections::HashMap;
+
+use crate::{
+    error::Diagnostic,
+    lexer::token::Literal,
+    parser::{Ast, ConstantValue, MacroBody},
+    span::{Span, Spanned},
+    utils::{
+        ast::{ConstVal, Contract, MacroArg, Statement, StatementType},
+        bytes_util::minimal_push,
+    },
+};
+
+/// Fold Constants
+///
+/// Replaces constant definitions that are arithmetic on literals with the literal they evaluate
+/// to, then replaces aliases, `[CONST]`, of constants with a literal value by that literal.
+/// Chains of aliases are followed. Constant pushes in macro bodies become pushes of the literal.
+/// Aliases of free storage pointers, undefined constants and cycles are left untouched, as are
+/// constant references in macro arguments.
+pub fn fold_constants(ast: &mut [Spanned<Ast>]) {
+    for (item, _) in ast.iter_mut() {
+        if let Ast::ConstantDefinition { value, .. } = item {
+            if let (ConstantValue::Expression(..), Some(lit)) = (&value, value.as_u256_bytes()) {
+                *value = ConstantValue::Literal(lit);
+            }
+        }
+    }
+
+    // The first definition of a name is the one used by lowering
+    let mut literals: HashMap<String, Literal> = HashMap::new();
+    for (item, _) in ast.iter() {
+        if let Ast::ConstantDefinition {
+            name,
+            value: ConstantValue::Literal(lit),
+            ..
+        } = item
+        {
+            literals.entry(name.clone()).or_insert(*lit);
+        }
+    }
+
+    // Each pass resolves one more link of every alias chain
+    loop {
+        let mut changed = false;
+        for (item, _) in ast.iter_mut() {
+            let Ast::ConstantDefinition { name, value, .. } = item else {
+                continue;
+            };
+            let ConstantValue::ConstantRef((target, _)) = value else {
+                continue;
+            };
+            if let Some(lit) = literals.get(target).copied() {
+                *value = ConstantValue::Literal(lit);
+                literals.entry(name.clone()).or_insert(lit);
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    for (item, _) in ast.iter_mut() {
+        let Ast::MacroDefinition { statements, .. } = item else {
+            continue;
+        };
+        for (statement, _) in statements.iter_mut() {
+            if let MacroBody::ConstantRef((name, _)) = statement {
+                if let Some(lit) = literals.get(name) {
+                    *statement = MacroBody::HexLiteral(*lit);
+                }
+            }
+        }
+    }
+}
+
+/// Inline Constants
+///
+/// Replaces each constant push in the macros of a lowered contract, including those nested inside
+/// labels, with the smallest push of the constant's value. Both the push and its literal keep the
+/// span of the constant reference. Constants passed as macro arguments, `MACRO([CONST])`, are
+/// replaced by their value. Free storage pointers are not resolved until compilation and
+/// undefined constants have no value, so references to either are left untouched and reported.
+pub fn inline_constants(contract: &mut Contract) -> Vec<Diagnostic> {
+    // The first definition of a name is the one used by lowering
+    let mut values: HashMap<String, ConstVal> = HashMap::new();
+    for constant in contract.constants.lock().unwrap().iter() {
+        values
+            .entry(constant.name.clone())
+            .or_insert_with(|| constant.value.clone());
+    }
+
+    let mut diagnostics = Vec::new();
+    for definition in &mut contract.macros {
+        definition.statements = inline_statements(
+            std::mem::take(&mut definition.statements),
+            &values,
+            &mut diagnostics,
+        );
+    }
+    contract.update_invocations();
+    diagnostics
+}
+
+fn inline_statements(
+    statements: Vec<Statement>,
+    values: &HashMap<String, ConstVal>,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Vec<Statement> {
+    let mut inlined = Vec::with_capacity(statements.len());
+    for mut statement in statements {
+        match &mut statement.ty {
+            StatementType::Constant(name) => {
+                if let Some(lit) = literal_value(name, &statement.span, values, diagnostics) {
+                    let (push, _) = minimal_push(&lit);
+                    inlined.push(Statement {
+                        ty: StatementType::Opcode(push),
+                        span: statement.span.clone(),
+                    });
+                    inlined.push(Statement {
+                        ty: StatementType::Literal(lit),
+                        span: statement.span,
+                    });
+                    continue;
+                }
+            }
+            StatementType::MacroInvocation(invocation) => {
+                for arg in &mut invocation.args {
+                    let MacroArg::ConstantRef(name) = arg else {
+                        continue;
+                    };
+                    if let Some(lit) = literal_value(name, &statement.span, values, diagnostics) {
+                        *arg = MacroArg::Literal(lit);
+                    }
+                }
+            }
+            StatementType::Label(label) => {
+                label.inner =
+                    inline_statements(std::mem::take(&mut label.inner), values, diagnostics);
+            }
+            _ => {}
+        }
+        inlined.push(statement);
+    }
+    inlined
+}
+
+/// The value of a constant that can be inlined, reporting references to any other constant
+fn literal_value(
+    name: &str,
+    span: &Span,
+    values: &HashMap<String, ConstVal>,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Option<Literal> {
+    match values.get(name) {
+        Some(ConstVal::Literal(lit)) => return Some(*lit),
+        Some(ConstVal::FreeStoragePointer(_)) => diagnostics.push(Diagnostic::warning(
+            format!("`{name}` is a free storage pointer, which is not resolved until compilation"),
+            span.clone(),
+        )),
+        None => diagnostics.push(Diagnostic::error(
+            format!("reference to undefined constant `[{name}]`"),
+            span.clone(),
+        )),
+    }
+    None
+}
@@ -1,10 +1,13 @@
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
-use crate::span::Spanned;
+use crate::{
+    span::Spanned,
+    utils::{ast::ArgumentLocation, types::PrimitiveEVMType},
+};
 
-/// Ripped from huff-rss
-/// Module that contains helper functions to parse ABI types
+// Ripped from huff-rss
+// Module that contains helper functions to parse ABI types
 
 /// #### Function
 ///
@@ -93,6 +96,8 @@ pub struct FunctionParam {
     pub kind: FunctionParamType,
     /// The internal type of the parameter
     pub internal_type: Option<String>,
+    /// The data location of the parameter, if one was declared
+    pub location: Option<ArgumentLocation>,
 }
 
 /// #### FunctionParamType
@@ -136,9 +141,11 @@ impl FunctionParamType {
                 fpt,
                 sizes
                     .iter()
-                    .map(|s| (!s.eq(&0))
-                        .then(|| format!("[{s}]"))
-                        .unwrap_or_else(|| "[]".to_string()))
+                    .map(|s| if s.eq(&0) {
+                        "[]".to_string()
+                    } else {
+                        format!("[{s}]")
+                    })
                     .collect::<Vec<_>>()
                     .join("")
             ),
@@ -150,7 +157,7 @@ impl FunctionParamType {
                     .iter()
                     .map(|fpt| fpt.to_string())
                     .collect::<Vec<_>>()
-                    .join(", ")
+                    .join(",")
             ),
         }
     }
@@ -243,6 +250,21 @@ impl FunctionParamType {
     }
 }
 
+/// The abi type of a primitive lexed from source. Both display as the same Solidity type name.
+impl From<PrimitiveEVMType> for FunctionParamType {
+    fn from(primitive: PrimitiveEVMType) -> Self {
+        match primitive {
+            PrimitiveEVMType::Address => FunctionParamType::Address,
+            PrimitiveEVMType::DynBytes => FunctionParamType::Bytes,
+            PrimitiveEVMType::Bool => FunctionParamType::Bool,
+            PrimitiveEVMType::String => FunctionParamType::String,
+            PrimitiveEVMType::Int(size) => FunctionParamType::Int(size),
+            PrimitiveEVMType::Uint(size) => FunctionParamType::Uint(size),
+            PrimitiveEVMType::Bytes(size) => FunctionParamType::FixedBytes(size),
+        }
+    }
+}
+
 impl From<&str> for FunctionParamType {
     fn from(string: &str) -> Self {
         FunctionParamType::convert_string_to_type(string).unwrap()
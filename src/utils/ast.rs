@@ -1,12 +1,26 @@
 use serde::{Deserialize, Serialize};
 
 use std::{
+    collections::{BTreeMap, BTreeSet},
     fmt::{Display, Formatter},
     path::PathBuf,
     sync::{Arc, Mutex},
 };
 
-use super::{bytes_util::bytes32_to_string, opcodes::Opcode};
+use chumsky::Parser;
+
+use crate::{
+    error::{BuildError, MergeError},
+    lexer::{lexer, token::Token},
+    lowering::{hash_signature, selector, DuplicateStrategy},
+    span::Span,
+};
+
+use super::{
+    abi::FunctionParamType,
+    bytes_util::{bytes32_to_string, literal_to_hex},
+    opcodes::Opcode,
+};
 
 /// A contained literal
 pub type Literal = [u8; 32];
@@ -36,6 +50,8 @@ pub struct Contract {
     pub errors: Vec<ErrorDefinition>,
     /// Functions
     pub functions: Vec<Function>,
+    /// Constructor inputs, `None` if no constructor is defined
+    pub constructor: Option<Vec<Argument>>,
     /// Events
     pub events: Vec<Event>,
     /// Tables
@@ -52,10 +68,645 @@ impl Contract {
     pub fn find_table_by_name(&self, name: &str) -> Option<TableDefinition> {
         self.tables.iter().find(|t| t.name == name).cloned()
     }
+
+    /// Appends the definitions of another contract, failing without changing this contract if
+    /// both define something with the same name
+    pub fn merge(&mut self, other: Contract) -> Result<(), MergeError> {
+        self.merge_with_strategy(other, DuplicateStrategy::Error)
+    }
+
+    /// Appends the definitions of another contract. Definitions that share a name with one in
+    /// this contract are resolved by the strategy, where [`DuplicateStrategy::KeepLast`] replaces
+    /// the definition in this contract with the one from `other`.
+    pub fn merge_with_strategy(
+        &mut self,
+        other: Contract,
+        strategy: DuplicateStrategy,
+    ) -> Result<(), MergeError> {
+        // Copied out before locking our own constants, as both contracts may share them
+        let other_constants = other.constants.lock().unwrap().clone();
+        let mut constants = self.constants.lock().unwrap();
+
+        let mut collisions = Vec::new();
+        collisions.extend(colliding_names("macro", &self.macros, &other.macros, |m| {
+            &m.name
+        }));
+        collisions.extend(colliding_names(
+            "constant",
+            &constants,
+            &other_constants,
+            |c| &c.name,
+        ));
+        collisions.extend(colliding_names("table", &self.tables, &other.tables, |t| {
+            &t.name
+        }));
+        collisions.extend(colliding_names(
+            "function",
+            &self.functions,
+            &other.functions,
+            |f| &f.name,
+        ));
+        collisions.extend(colliding_names("event", &self.events, &other.events, |e| {
+            &e.name
+        }));
+        collisions.extend(colliding_names("error", &self.errors, &other.errors, |e| {
+            &e.name
+        }));
+        if self.constructor.is_some() && other.constructor.is_some() {
+            collisions.push(("constructor", "constructor".to_string()));
+        }
+        if strategy == DuplicateStrategy::Error && !collisions.is_empty() {
+            return Err(MergeError { collisions });
+        }
+
+        merge_definitions(&mut self.macros, other.macros, |m| &m.name, strategy);
+        merge_definitions(&mut constants, other_constants, |c| &c.name, strategy);
+        merge_definitions(&mut self.tables, other.tables, |t| &t.name, strategy);
+        merge_definitions(&mut self.functions, other.functions, |f| &f.name, strategy);
+        merge_definitions(&mut self.events, other.events, |e| &e.name, strategy);
+        merge_definitions(&mut self.errors, other.errors, |e| &e.name, strategy);
+        if self.constructor.is_none() || strategy == DuplicateStrategy::KeepLast {
+            self.constructor = other.constructor.or(self.constructor.take());
+        }
+        for import in other.imports {
+            if !self.imports.contains(&import) {
+                self.imports.push(import);
+            }
+        }
+
+        drop(constants);
+
+        // Replaced macros take their invocations with them
+        self.update_invocations();
+
+        Ok(())
+    }
+
+    /// Recomputes the invocations from the statements of every macro
+    pub(crate) fn update_invocations(&mut self) {
+        self.invocations = self
+            .macros
+            .iter()
+            .flat_map(|definition| &definition.statements)
+            .filter_map(|statement| match &statement.ty {
+                StatementType::MacroInvocation(invocation) => Some(invocation.clone()),
+                _ => None,
+            })
+            .collect();
+    }
+
+    /// Regenerates huff source from the contract, with every definition separated by a blank
+    /// line. Free storage pointers are emitted unresolved, so the source lowers back to the same
+    /// contract.
+    pub fn to_huff_source(&self) -> String {
+        let mut items: Vec<String> = self
+            .imports
+            .iter()
+            .map(|path| format!("#include \"{}\"", path.display()))
+            .collect();
+
+        items.extend(self.constants.lock().unwrap().iter().map(|constant| {
+            let value = match &constant.value {
+                ConstVal::Literal(lit) => literal_to_hex(lit, true, true),
+                ConstVal::FreeStoragePointer(_) => "FREE_STORAGE_POINTER()".to_string(),
+            };
+            format!("#define constant {} = {value}", constant.name)
+        }));
+
+        items.extend(self.functions.iter().map(|function| {
+            let mut out = format!(
+                "#define function {}({}) {}",
+                function.name,
+                arguments_to_huff_source(&function.inputs),
+                function.fn_type.keyword()
+            );
+            if !function.outputs.is_empty() {
+                out.push_str(&format!(
+                    " returns ({})",
+                    arguments_to_huff_source(&function.outputs)
+                ));
+            }
+            out
+        }));
+
+        items.extend(self.events.iter().map(|event| {
+            format!(
+                "#define event {}({})",
+                event.name,
+                arguments_to_huff_source(&event.parameters)
+            )
+        }));
+
+        items.extend(self.errors.iter().map(|error| {
+            format!(
+                "#define error {}({})",
+                error.name,
+                arguments_to_huff_source(&error.parameters)
+            )
+        }));
+
+        items.extend(
+            self.constructor
+                .iter()
+                .map(|inputs| format!("#define constructor({})", arguments_to_huff_source(inputs))),
+        );
+
+        items.extend(self.tables.iter().map(|table| {
+            let keyword = match table.kind {
+                TableKind::JumpTable => "jumptable",
+                TableKind::JumpTablePacked => "jumptablepacked",
+                TableKind::CodeTable => "codetable",
+            };
+            let mut out = format!("#define {keyword} {}() = {{\n", table.name);
+            for statement in &table.statements {
+                let entry = match &statement.ty {
+                    StatementType::Code(code) => format!("0x{code}"),
+                    ty => ty.to_huff_source(),
+                };
+                out.push_str(&format!("    {entry}\n"));
+            }
+            out + "}"
+        }));
+
+        items.extend(self.macros.iter().map(|definition| {
+            let keyword = match (definition.test, definition.outlined) {
+                (true, _) => "test",
+                (false, true) => "fn",
+                (false, false) => "macro",
+            };
+            let mut out = definition
+                .decorator
+                .as_ref()
+                .map(|decorator| decorator.to_huff_source() + "\n")
+                .unwrap_or_default();
+            out += &format!(
+                "#define {keyword} {}({}) = takes ({}) returns ({}) {{\n",
+                definition.name,
+                definition
+                    .parameters
+                    .iter()
+                    .filter_map(|param| param.name.clone())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                definition.takes,
+                definition.returns
+            );
+            for statement in &definition.statements {
+                out.push_str(&format!("    {}\n", statement.ty.to_huff_source()));
+            }
+            out + "}"
+        }));
+
+        items.join("\n\n") + "\n"
+    }
+
+    /// Generates a Solidity interface named `name` declaring the contract's events, errors and
+    /// functions. Dynamic function parameters without a declared location are given `memory`, as
+    /// Solidity requires one.
+    pub fn to_solidity_interface(&self, name: &str) -> String {
+        let events = self.events.iter().map(|event| {
+            format!(
+                "event {}({});",
+                event.name,
+                interface_params(&event.parameters, false)
+            )
+        });
+        let errors = self.errors.iter().map(|error| {
+            format!(
+                "error {}({});",
+                error.name,
+                interface_params(&error.parameters, false)
+            )
+        });
+        let functions = self.functions.iter().map(|function| {
+            let mut out = format!(
+                "function {}({}) external{}",
+                function.name,
+                interface_params(&function.inputs, true),
+                function.fn_type.interface_mutability()
+            );
+            if !function.outputs.is_empty() {
+                out.push_str(&format!(
+                    " returns ({})",
+                    interface_params(&function.outputs, true)
+                ));
+            }
+            out + ";"
+        });
+
+        let body: String = events
+            .chain(errors)
+            .chain(functions)
+            .map(|line| format!("    {line}\n"))
+            .collect();
+        format!("interface {name} {{\n{body}}}\n")
+    }
+}
+
+/// Formats parameters for a Solidity interface, adding `memory` to dynamic types that were declared
+/// without a location when `locations` is set
+fn interface_params(args: &[Argument], locations: bool) -> String {
+    args.iter()
+        .map(|arg| {
+            let mut parts: Vec<String> = arg.arg_type.iter().cloned().collect();
+            if arg.indexed {
+                parts.push("indexed".to_string());
+            }
+            if locations {
+                let is_dynamic = arg.arg_type.as_deref().is_some_and(|ty| {
+                    FunctionParamType::convert_string_to_type(ty)
+                        .is_ok_and(|ty| ty.is_memory_type())
+                });
+                match &arg.arg_location {
+                    Some(location) => parts.push(location.to_string()),
+                    None if is_dynamic => parts.push(ArgumentLocation::Memory.to_string()),
+                    None => {}
+                }
+            }
+            parts.extend(arg.name.clone());
+            parts.join(" ")
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Contract Builder
+///
+/// Builds a [`Contract`] from definitions constructed in code, as an alternative to parsing huff
+/// source. Names are validated when the contract is built, and function selectors and event
+/// hashes are computed from the definitions' signatures.
+#[derive(Debug, Default, Clone)]
+pub struct ContractBuilder {
+    macros: Vec<MacroDefinition>,
+    constants: Vec<ConstantDefinition>,
+    functions: Vec<Function>,
+    events: Vec<Event>,
+    tables: Vec<TableDefinition>,
+}
+
+impl ContractBuilder {
+    /// Create a builder for an empty contract
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a macro or fn definition
+    pub fn add_macro(mut self, definition: MacroDefinition) -> Self {
+        self.macros.push(definition);
+        self
+    }
+
+    /// Add a constant definition
+    pub fn add_constant(mut self, constant: ConstantDefinition) -> Self {
+        self.constants.push(constant);
+        self
+    }
+
+    /// Add an abi function, its selector is computed when the contract is built
+    pub fn add_function(mut self, function: Function) -> Self {
+        self.functions.push(function);
+        self
+    }
+
+    /// Add an abi event, its hash is computed when the contract is built
+    pub fn add_event(mut self, event: Event) -> Self {
+        self.events.push(event);
+        self
+    }
+
+    /// Add a jump or code table
+    pub fn add_table(mut self, table: TableDefinition) -> Self {
+        self.tables.push(table);
+        self
+    }
+
+    /// Builds the contract, failing if any name could not be written in huff source, such as an
+    /// opcode or keyword, or is used by two definitions of the same kind
+    pub fn build(self) -> Result<Contract, BuildError> {
+        let mut error = BuildError::default();
+        check_names(&mut error, "macro", &self.macros, |m| &m.name);
+        check_names(&mut error, "constant", &self.constants, |c| &c.name);
+        check_names(&mut error, "function", &self.functions, |f| &f.name);
+        check_names(&mut error, "event", &self.events, |e| &e.name);
+        check_names(&mut error, "table", &self.tables, |t| &t.name);
+        if !error.invalid_names.is_empty() || !error.duplicates.is_empty() {
+            return Err(error);
+        }
+
+        let mut contract = Contract {
+            macros: self.macros,
+            constants: Arc::new(Mutex::new(self.constants)),
+            functions: self
+                .functions
+                .into_iter()
+                .map(|mut function| {
+                    function.signature = selector(&function.canonical_signature());
+                    function
+                })
+                .collect(),
+            events: self
+                .events
+                .into_iter()
+                .map(|mut event| {
+                    event.hash = hash_signature(&event.canonical_signature());
+                    event
+                })
+                .collect(),
+            tables: self.tables,
+            ..Default::default()
+        };
+        contract.update_invocations();
+        Ok(contract)
+    }
+}
+
+/// Records the names of `definitions` that are not identifiers, or that repeat an earlier name
+fn check_names<T>(
+    error: &mut BuildError,
+    kind: &'static str,
+    definitions: &[T],
+    name: impl Fn(&T) -> &String,
+) {
+    let mut seen = BTreeSet::new();
+    for definition in definitions {
+        let name = name(definition);
+        // The name must lex as a single identifier to be usable in source
+        let tokens = lexer().parse(name.as_str()).unwrap_or_default();
+        if !matches!(tokens.first(), Some((Token::Ident(ident), _)) if ident == name)
+            || tokens.len() != 2
+        {
+            error.invalid_names.push((kind, name.clone()));
+        } else if !seen.insert(name) {
+            error.duplicates.push((kind, name.clone()));
+        }
+    }
+}
+
+/// Macro Statements
+///
+/// Returns the lowered statements of the first macro with the given name, for inspecting a macro
+/// without generating its bytecode.
+pub fn macro_statements(contract: &Contract, name: &str) -> Option<Vec<StatementType>> {
+    contract
+        .macros
+        .iter()
+        .find(|m| m.name == name)
+        .map(|m| m.statements.iter().map(|s| s.ty.clone()).collect())
+}
+
+/// Describes the statements of a macro, one per line, using the [`Display`] implementation of
+/// [`StatementType`]
+pub fn macro_statements_pretty(contract: &Contract, name: &str) -> Option<String> {
+    macro_statements(contract, name).map(|statements| {
+        statements
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n")
+    })
+}
+
+/// Literal Histogram
+///
+/// Counts how many times each literal is pushed across every macro of the contract, including
+/// statements nested inside labels. Literals that are pushed often are candidates for extraction
+/// into constants.
+pub fn literal_histogram(contract: &Contract) -> BTreeMap<Literal, usize> {
+    fn count(statements: &[Statement], histogram: &mut BTreeMap<Literal, usize>) {
+        for statement in statements {
+            match &statement.ty {
+                StatementType::Literal(lit) => *histogram.entry(*lit).or_default() += 1,
+                StatementType::Label(label) => count(&label.inner, histogram),
+                _ => {}
+            }
+        }
+    }
+
+    let mut histogram = BTreeMap::new();
+    for definition in &contract.macros {
+        count(&definition.statements, &mut histogram);
+    }
+    histogram
+}
+
+/// An argument of a builtin call, resolved against the definitions of a [`Contract`] by the kind
+/// of definition the builtin expects
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum BuiltinArg {
+    /// A jump or code table, passed to `__tablesize` or `__tablestart`
+    Table(String),
+    /// A macro, passed to `__codesize`
+    Macro(String),
+    /// An abi function, passed to `__FUNC_SIG`
+    Function(String),
+    /// An abi event, passed to `__EVENT_HASH`
+    Event(String),
+    /// An abi error, passed to `__ERROR`
+    Error(String),
+    /// A literal, such as the value passed to `__RIGHTPAD`
+    Literal(Literal),
+    /// A name that is not a definition of the expected kind, or an argument of a builtin that does
+    /// not reference definitions
+    Unresolved(String),
+}
+
+/// Builtin Calls
+///
+/// Lists every builtin call across the macros of the contract, including those nested inside
+/// labels, with the span of the call. Arguments are resolved to the definitions they name.
+pub fn builtin_calls(contract: &Contract) -> Vec<(BuiltinFunctionKind, Vec<BuiltinArg>, Span)> {
+    fn collect(
+        contract: &Contract,
+        statements: &[Statement],
+        calls: &mut Vec<(BuiltinFunctionKind, Vec<BuiltinArg>, Span)>,
+    ) {
+        for statement in statements {
+            match &statement.ty {
+                StatementType::BuiltinFunctionCall(call) => {
+                    let args = call
+                        .args
+                        .iter()
+                        .map(|arg| match arg {
+                            MacroArg::Literal(lit) => BuiltinArg::Literal(*lit),
+                            MacroArg::Ident(name)
+                            | MacroArg::ConstantRef(name)
+                            | MacroArg::ArgCall(name) => {
+                                resolve_builtin_arg(contract, &call.kind, name.clone())
+                            }
+                        })
+                        .collect();
+                    calls.push((call.kind.clone(), args, statement.span.clone()));
+                }
+                StatementType::Label(label) => collect(contract, &label.inner, calls),
+                _ => {}
+            }
+        }
+    }
+
+    let mut calls = Vec::new();
+    for definition in &contract.macros {
+        collect(contract, &definition.statements, &mut calls);
+    }
+    calls
+}
+
+fn resolve_builtin_arg(
+    contract: &Contract,
+    kind: &BuiltinFunctionKind,
+    name: String,
+) -> BuiltinArg {
+    let (defined, resolved): (bool, fn(String) -> BuiltinArg) = match kind {
+        BuiltinFunctionKind::Tablesize | BuiltinFunctionKind::Tablestart => (
+            contract.tables.iter().any(|t| t.name == name),
+            BuiltinArg::Table,
+        ),
+        BuiltinFunctionKind::Codesize => (
+            contract.macros.iter().any(|m| m.name == name),
+            BuiltinArg::Macro,
+        ),
+        BuiltinFunctionKind::FunctionSignature => (
+            contract.functions.iter().any(|f| f.name == name),
+            BuiltinArg::Function,
+        ),
+        BuiltinFunctionKind::EventHash => (
+            contract.events.iter().any(|e| e.name == name),
+            BuiltinArg::Event,
+        ),
+        BuiltinFunctionKind::Error => (
+            contract.errors.iter().any(|e| e.name == name),
+            BuiltinArg::Error,
+        ),
+        BuiltinFunctionKind::RightPad | BuiltinFunctionKind::DynConstructorArg => {
+            (false, BuiltinArg::Unresolved)
+        }
+    };
+
+    if defined {
+        resolved(name)
+    } else {
+        BuiltinArg::Unresolved(name)
+    }
+}
+
+/// Constructor Signature
+///
+/// The canonical types of the constructor's inputs, e.g. `(address,uint256)`, used to abi encode
+/// the arguments of a deployment. `None` if the contract has no constructor.
+pub fn constructor_signature(contract: &Contract) -> Option<String> {
+    contract
+        .constructor
+        .as_ref()
+        .map(|inputs| canonical_signature("", inputs))
+}
+
+/// Counts of the definitions and opcodes in a [`Contract`], as returned by [`stats`]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ContractStats {
+    /// Inlined macros
+    pub macros: usize,
+    /// Outlined (fn) macros
+    pub fns: usize,
+    /// Constants
+    pub constants: usize,
+    /// Jump and code tables
+    pub tables: usize,
+    /// Abi functions
+    pub functions: usize,
+    /// Abi events
+    pub events: usize,
+    /// Abi errors
+    pub errors: usize,
+    /// Opcodes across every macro, including those nested inside labels
+    pub opcodes: usize,
+    /// Distinct opcodes used by any macro
+    pub distinct_opcodes: usize,
+}
+
+/// Stats
+///
+/// Counts the definitions of the contract and the opcodes used by its macros.
+pub fn stats(contract: &Contract) -> ContractStats {
+    fn count(statements: &[Statement], opcodes: &mut usize, distinct: &mut BTreeSet<Opcode>) {
+        for statement in statements {
+            match &statement.ty {
+                StatementType::Opcode(opcode) => {
+                    *opcodes += 1;
+                    distinct.insert(*opcode);
+                }
+                StatementType::Label(label) => count(&label.inner, opcodes, distinct),
+                _ => {}
+            }
+        }
+    }
+
+    let mut opcodes = 0;
+    let mut distinct = BTreeSet::new();
+    for definition in &contract.macros {
+        count(&definition.statements, &mut opcodes, &mut distinct);
+    }
+
+    let fns = contract.macros.iter().filter(|m| m.outlined).count();
+    ContractStats {
+        macros: contract.macros.len() - fns,
+        fns,
+        constants: contract.constants.lock().unwrap().len(),
+        tables: contract.tables.len(),
+        functions: contract.functions.len(),
+        events: contract.events.len(),
+        errors: contract.errors.len(),
+        opcodes,
+        distinct_opcodes: distinct.len(),
+    }
+}
+
+/// The kind and name of each definition in `theirs` that shares a name with one in `ours`
+fn colliding_names<T>(
+    kind: &'static str,
+    ours: &[T],
+    theirs: &[T],
+    name: impl Fn(&T) -> &String,
+) -> Vec<(&'static str, String)> {
+    theirs
+        .iter()
+        .filter(|definition| ours.iter().any(|ours| name(ours) == name(definition)))
+        .map(|definition| (kind, name(definition).clone()))
+        .collect()
+}
+
+/// Appends `theirs` to `ours`, resolving definitions with the same name by the strategy
+fn merge_definitions<T>(
+    ours: &mut Vec<T>,
+    theirs: Vec<T>,
+    name: impl Fn(&T) -> &String,
+    strategy: DuplicateStrategy,
+) {
+    for definition in theirs {
+        match ours.iter().position(|ours| name(ours) == name(&definition)) {
+            Some(i) if strategy == DuplicateStrategy::KeepLast => ours[i] = definition,
+            Some(_) => {}
+            None => ours.push(definition),
+        }
+    }
+}
+
+/// Formats abi arguments as `type location name`, omitting the parts that are not present
+fn arguments_to_huff_source(args: &[Argument]) -> String {
+    args.iter()
+        .map(|arg| {
+            let mut parts: Vec<String> = arg.arg_type.iter().cloned().collect();
+            if arg.indexed {
+                parts.push("indexed".to_string());
+            }
+            parts.extend(arg.arg_location.as_ref().map(ToString::to_string));
+            parts.extend(arg.name.clone());
+            parts.join(" ")
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 /// An argument's location
-#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum ArgumentLocation {
     /// Memory location
     #[default]
@@ -66,6 +717,16 @@ pub enum ArgumentLocation {
     Calldata,
 }
 
+impl Display for ArgumentLocation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArgumentLocation::Memory => write!(f, "memory"),
+            ArgumentLocation::Storage => write!(f, "storage"),
+            ArgumentLocation::Calldata => write!(f, "calldata"),
+        }
+    }
+}
+
 /// A function, event, or macro argument
 #[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
 pub struct Argument {
@@ -94,6 +755,14 @@ pub struct Function {
     pub outputs: Vec<Argument>,
 }
 
+impl Function {
+    /// The canonical signature of the function, e.g. `transfer(address,uint256)`, from which the
+    /// selector is derived
+    pub fn canonical_signature(&self) -> String {
+        canonical_signature(&self.name, &self.inputs)
+    }
+}
+
 /// Function Types
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub enum FunctionType {
@@ -117,6 +786,16 @@ impl FunctionType {
             _ => "", // payable / nonpayable types not valid in Solidity interfaces
         }
     }
+
+    /// Get the huff keyword of the function type
+    pub fn keyword(&self) -> &str {
+        match self {
+            FunctionType::View => "view",
+            FunctionType::Payable => "payable",
+            FunctionType::NonPayable => "nonpayable",
+            FunctionType::Pure => "pure",
+        }
+    }
 }
 
 /// An Event Signature
@@ -130,6 +809,13 @@ pub struct Event {
     pub hash: Literal,
 }
 
+impl Event {
+    /// The canonical signature of the event, from which the event hash is derived
+    pub fn canonical_signature(&self) -> String {
+        canonical_signature(&self.name, &self.parameters)
+    }
+}
+
 /// A Table Definition
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct TableDefinition {
@@ -153,6 +839,30 @@ impl TableDefinition {
             size,
         }
     }
+
+    /// The labels of a jump table, with where each is written in the table. The labels are left
+    /// unresolved, as their addresses are only known once the bytecode is laid out. Code tables
+    /// have no entries.
+    pub fn jump_entries(&self) -> Vec<JumpTableEntry> {
+        let Some(size) = self.kind.entry_size() else {
+            return Vec::new();
+        };
+
+        self.statements
+            .iter()
+            .filter_map(|statement| match &statement.ty {
+                StatementType::LabelCall(label) => Some((label, &statement.span)),
+                _ => None,
+            })
+            .enumerate()
+            .map(|(index, (label, span))| JumpTableEntry {
+                label: label.clone(),
+                offset: index * size,
+                size,
+                span: span.clone(),
+            })
+            .collect()
+    }
 }
 
 /// A Table Kind
@@ -166,6 +876,33 @@ pub enum TableKind {
     CodeTable,
 }
 
+impl TableKind {
+    /// The number of bytes each jump table entry occupies, a full word for a regular jump table
+    /// and two bytes for a packed one. Code tables are not made of entries.
+    pub fn entry_size(&self) -> Option<usize> {
+        match self {
+            TableKind::JumpTable => Some(32),
+            TableKind::JumpTablePacked => Some(2),
+            TableKind::CodeTable => None,
+        }
+    }
+}
+
+/// A Jump Table Entry
+///
+/// A label whose address is written into a jump table.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct JumpTableEntry {
+    /// The label whose address is written
+    pub label: String,
+    /// The offset of the entry from the start of the table, in bytes
+    pub offset: usize,
+    /// The number of bytes the address is written in
+    pub size: usize,
+    /// The span of the entry in source
+    pub span: Span,
+}
+
 /// A Macro Definition
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct MacroDefinition {
@@ -229,10 +966,24 @@ pub enum MacroArg {
     Literal(Literal),
     /// Macro Iden String Argument
     Ident(String),
+    /// A bracketed constant reference, `[CONST]`
+    ConstantRef(String),
     /// An Arg Call
     ArgCall(String),
 }
 
+impl MacroArg {
+    /// Renders the argument as huff source, as it is written in an invocation
+    pub fn to_huff_source(&self) -> String {
+        match self {
+            MacroArg::Literal(l) => literal_to_hex(l, true, true),
+            MacroArg::Ident(name) => name.clone(),
+            MacroArg::ConstantRef(name) => format!("[{name}]"),
+            MacroArg::ArgCall(name) => format!("<{name}>"),
+        }
+    }
+}
+
 /// Free Storage Pointer Unit Struct
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct FreeStoragePointer;
@@ -246,6 +997,23 @@ pub enum ConstVal {
     FreeStoragePointer(FreeStoragePointer),
 }
 
+impl ConstVal {
+    /// The big endian bytes of the value, `None` for an unresolved free storage pointer
+    pub fn as_u256_bytes(&self) -> Option<[u8; 32]> {
+        match self {
+            ConstVal::Literal(lit) => Some(*lit),
+            ConstVal::FreeStoragePointer(_) => None,
+        }
+    }
+
+    /// The value as a `U256`, `None` for an unresolved free storage pointer
+    #[cfg(feature = "u256")]
+    pub fn as_u256(&self) -> Option<primitive_types::U256> {
+        self.as_u256_bytes()
+            .map(|bytes| primitive_types::U256::from_big_endian(&bytes))
+    }
+}
+
 /// A Constant Definition
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ConstantDefinition {
@@ -266,6 +1034,23 @@ pub struct ErrorDefinition {
     pub parameters: Vec<Argument>,
 }
 
+impl ErrorDefinition {
+    /// The canonical signature of the error, from which the selector is derived
+    pub fn canonical_signature(&self) -> String {
+        canonical_signature(&self.name, &self.parameters)
+    }
+}
+
+/// Formats `name(type,type,...)` from the argument types
+fn canonical_signature(name: &str, args: &[Argument]) -> String {
+    let types = args
+        .iter()
+        .map(|arg| arg.arg_type.clone().unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join(",");
+    format!("{name}({types})")
+}
+
 /// A Jump Destination
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Label {
@@ -280,10 +1065,9 @@ pub struct Label {
 pub struct BuiltinFunctionCall {
     /// The Builtin Kind
     pub kind: BuiltinFunctionKind,
-    /// Arguments for the builtin function call.
-    /// TODO: Maybe make a better type for this other than `Argument`? Would be nice if it pointed
-    ///       directly to the macro/table.
-    pub args: Vec<Argument>,
+    /// Arguments for the builtin function call, taking the same forms as those of a macro
+    /// invocation. Names are resolved to the definitions they point to by [`builtin_calls`].
+    pub args: Vec<MacroArg>,
 }
 
 /// A Builtin Function Kind
@@ -312,6 +1096,8 @@ pub enum BuiltinFunctionKind {
 pub struct Statement {
     /// The type of statement
     pub ty: StatementType,
+    /// The span of source the statement was lowered from
+    pub span: Span,
 }
 
 /// The Statement Type
@@ -337,6 +1123,61 @@ pub enum StatementType {
     BuiltinFunctionCall(BuiltinFunctionCall),
 }
 
+impl BuiltinFunctionKind {
+    /// The name the builtin is invoked by, including its leading underscores
+    pub fn name(&self) -> &'static str {
+        match self {
+            BuiltinFunctionKind::Tablesize => "__tablesize",
+            BuiltinFunctionKind::Codesize => "__codesize",
+            BuiltinFunctionKind::Tablestart => "__tablestart",
+            BuiltinFunctionKind::FunctionSignature => "__FUNC_SIG",
+            BuiltinFunctionKind::EventHash => "__EVENT_HASH",
+            BuiltinFunctionKind::Error => "__ERROR",
+            BuiltinFunctionKind::RightPad => "__RIGHTPAD",
+            BuiltinFunctionKind::DynConstructorArg => "__DYN_CONSTRUCTOR_ARG",
+        }
+    }
+}
+
+impl StatementType {
+    /// Renders the statement as huff source. Unlike [`Display`], which describes the statement,
+    /// the output can be parsed back into the same statement.
+    pub fn to_huff_source(&self) -> String {
+        match self {
+            StatementType::Literal(l) => literal_to_hex(l, true, true),
+            StatementType::Opcode(o) => o.mnemonic().to_string(),
+            StatementType::Code(s) => s.clone(),
+            StatementType::MacroInvocation(m) => {
+                let args = m
+                    .args
+                    .iter()
+                    .map(MacroArg::to_huff_source)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("{}({args})", m.macro_name)
+            }
+            StatementType::Constant(c) => format!("[{c}]"),
+            StatementType::ArgCall(c) => format!("<{c}>"),
+            StatementType::Label(l) => l
+                .inner
+                .iter()
+                .fold(format!("{}:", l.name), |out, statement| {
+                    format!("{out}\n        {}", statement.ty.to_huff_source())
+                }),
+            StatementType::LabelCall(l) => l.clone(),
+            StatementType::BuiltinFunctionCall(b) => {
+                let args = b
+                    .args
+                    .iter()
+                    .map(MacroArg::to_huff_source)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("{}({args})", b.kind.name())
+            }
+        }
+    }
+}
+
 impl Display for StatementType {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -359,15 +1200,32 @@ impl Display for StatementType {
 
 /// A decorator tag
 ///
-/// At the moment, the decorator tag can only be placed over test definitions. Developers
-/// can use decorators to define environment variables and other metadata for their individual
-/// tests.
+/// Written as `#[...]` before a macro definition. Developers can use decorators to define
+/// environment variables and other metadata for their individual tests.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Decorator {
     /// Vector of flags passed within the decorator
     pub flags: Vec<DecoratorFlag>,
 }
 
+impl Decorator {
+    /// Renders the decorator as huff source, `#[calldata("0x..."), value(0x01)]`
+    pub fn to_huff_source(&self) -> String {
+        let flags = self
+            .flags
+            .iter()
+            .map(|flag| match flag {
+                DecoratorFlag::Calldata(calldata) => format!("calldata(\"{calldata}\")"),
+                DecoratorFlag::Value(value) => {
+                    format!("value({})", literal_to_hex(value, true, true))
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("#[{flags}]")
+    }
+}
+
 /// A decorator flag
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum DecoratorFlag {
@@ -2,25 +2,58 @@ use std::num::ParseIntError;
 
 use tiny_keccak::{Hasher, Keccak};
 
-/// Convert a string slice to a `[u8; 32]`
+use super::{ast::Literal, opcodes::Opcode};
+
+/// An error converting a string into a `[u8; 32]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LiteralError {
+    /// A character is not a digit of the radix
+    InvalidDigit(char),
+    /// The value does not fit in 256 bits
+    Overflow,
+}
+
+impl std::fmt::Display for LiteralError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LiteralError::InvalidDigit(c) => write!(f, "invalid digit `{c}` in literal"),
+            LiteralError::Overflow => write!(f, "literal does not fit in 32 bytes"),
+        }
+    }
+}
+
+impl std::error::Error for LiteralError {}
+
+/// Convert a hex string slice to a `[u8; 32]`
 /// Pads zeros to the left of significant bytes in the `[u8; 32]` slice.
 /// i.e. 0xa57b becomes `[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
 /// 0, 0, 0, 0, 0, 165, 123]`
-pub fn str_to_bytes32(s: &str) -> [u8; 32] {
-    let s = format_even_bytes(String::from(s));
+/// Values that do not fit in 256 bits are an error rather than being truncated.
+pub fn str_to_bytes32(s: &str) -> Result<[u8; 32], LiteralError> {
+    str_to_bytes32_radix(s, 16)
+}
 
-    let bytes: Vec<u8> = (0..s.len())
-        .step_by(2)
-        .map(|c| u8::from_str_radix(&s[c..c + 2], 16).unwrap())
-        .collect();
+/// Convert a string slice of digits in the given radix, such as a decimal or binary literal, to a
+/// big endian `[u8; 32]`. An empty string is zero.
+pub fn str_to_bytes32_radix(s: &str, radix: u32) -> Result<[u8; 32], LiteralError> {
+    let mut limbs = [0u64; 4];
 
-    let mut padded = [0u8; 32];
+    for c in s.chars() {
+        let digit = c.to_digit(radix).ok_or(LiteralError::InvalidDigit(c))?;
 
-    for i in 32 - bytes.len()..32 {
-        padded[i] = bytes[bytes.len() - (32 - i)];
+        // limbs = limbs * radix + digit, failing if anything carries out of the top limb
+        let mut carry = digit as u128;
+        for limb in limbs.iter_mut() {
+            let value = *limb as u128 * radix as u128 + carry;
+            *limb = value as u64;
+            carry = value >> 64;
+        }
+        if carry != 0 {
+            return Err(LiteralError::Overflow);
+        }
     }
 
-    padded
+    Ok(from_limbs(limbs))
 }
 
 /// Convert a `[u8; 32]` to a bytes string.
@@ -36,6 +69,22 @@ pub fn bytes32_to_string(bytes: &[u8; 32], prefixed: bool) -> String {
     format!("{}{s}", if prefixed { "0x" } else { "" })
 }
 
+/// Convert a literal to a hex string.
+/// When `strip_leading_zeros` is set the minimal representation is produced, i.e. `1` rather than
+/// `0000...0001`, with zero rendered as `0`. Otherwise all 64 characters are kept.
+pub fn literal_to_hex(lit: &Literal, strip_leading_zeros: bool, prefix: bool) -> String {
+    let hex: String = lit.iter().map(|b| format!("{b:02x}")).collect();
+    let hex = if strip_leading_zeros {
+        match hex.trim_start_matches('0') {
+            "" => "0",
+            trimmed => trimmed,
+        }
+    } else {
+        &hex
+    };
+    format!("{}{hex}", if prefix { "0x" } else { "" })
+}
+
 /// Wrapper to convert a hex string to a usize.
 pub fn hex_to_usize(s: &str) -> Result<usize, ParseIntError> {
     usize::from_str_radix(s, 16)
@@ -69,9 +118,136 @@ pub fn str_to_vec(s: &str) -> Result<Vec<u8>, std::num::ParseIntError> {
     bytes
 }
 
-/// Hash a string with Keccak256
-pub fn hash_bytes(dest: &mut [u8], to_hash: &String) {
+/// The Keccak256 hash of some data, as used for selectors, event topics and the `sha3` opcode
+pub fn keccak256(data: &[u8]) -> [u8; 32] {
+    let mut hash = [0u8; 32];
     let mut hasher = Keccak::v256();
-    hasher.update(to_hash.as_bytes());
-    hasher.finalize(dest);
+    hasher.update(data);
+    hasher.finalize(&mut hash);
+    hash
+}
+
+/// Hash a string with Keccak256, writing as much of the hash as fits into `dest`
+pub fn hash_bytes(dest: &mut [u8], to_hash: &String) {
+    let hash = keccak256(to_hash.as_bytes());
+    let len = dest.len().min(hash.len());
+    dest[..len].copy_from_slice(&hash[..len]);
+}
+
+/// Returns if two literals hold the same value. Literals are stored big endian and padded to 32
+/// bytes, so `0x1` and `0x0001` are already the same array.
+pub fn literals_equal(a: &Literal, b: &Literal) -> bool {
+    a == b
+}
+
+/// The smallest push opcode that pushes the literal, along with its immediate bytes with leading
+/// zeros stripped. There is no `push0`, so zero is pushed by `push1` with a single zero byte.
+pub fn minimal_push(lit: &Literal) -> (Opcode, Vec<u8>) {
+    let first = lit.iter().position(|b| *b != 0).unwrap_or(31);
+    let immediate = lit[first..].to_vec();
+    let push = Opcode::push_of_size(immediate.len()).expect("a literal is at most 32 bytes");
+    (push, immediate)
+}
+
+/// A number as a big endian word
+pub fn number_to_literal(num: usize) -> Literal {
+    let bytes = num.to_be_bytes();
+    let mut lit = [0u8; 32];
+    lit[32 - bytes.len()..].copy_from_slice(&bytes);
+    lit
+}
+
+/// The number of bits needed to hold a literal, ignoring leading zeros. Zero needs no bits.
+pub fn literal_bit_length(lit: &Literal) -> usize {
+    match lit.iter().position(|b| *b != 0) {
+        Some(first) => (32 - first) * 8 - lit[first].leading_zeros() as usize,
+        None => 0,
+    }
+}
+
+/// Split a big endian 256 bit word into little endian 64 bit limbs
+fn to_limbs(word: &Literal) -> [u64; 4] {
+    let mut limbs = [0u64; 4];
+    for (i, limb) in limbs.iter_mut().enumerate() {
+        let start = 32 - (i + 1) * 8;
+        *limb = u64::from_be_bytes(word[start..start + 8].try_into().unwrap());
+    }
+    limbs
+}
+
+/// Join little endian 64 bit limbs into a big endian 256 bit word
+fn from_limbs(limbs: [u64; 4]) -> Literal {
+    let mut word = [0u8; 32];
+    for (i, limb) in limbs.iter().enumerate() {
+        let start = 32 - (i + 1) * 8;
+        word[start..start + 8].copy_from_slice(&limb.to_be_bytes());
+    }
+    word
+}
+
+/// Add two 256 bit words, wrapping on overflow as the EVM does
+pub fn wrapping_add_bytes32(a: &Literal, b: &Literal) -> Literal {
+    let (a, b) = (to_limbs(a), to_limbs(b));
+    let mut out = [0u64; 4];
+    let mut carry = false;
+    for i in 0..4 {
+        let (sum, overflow_a) = a[i].overflowing_add(b[i]);
+        let (sum, overflow_b) = sum.overflowing_add(carry as u64);
+        out[i] = sum;
+        carry = overflow_a || overflow_b;
+    }
+    from_limbs(out)
+}
+
+/// Subtract two 256 bit words, wrapping on underflow as the EVM does
+pub fn wrapping_sub_bytes32(a: &Literal, b: &Literal) -> Literal {
+    let (a, b) = (to_limbs(a), to_limbs(b));
+    let mut out = [0u64; 4];
+    let mut borrow = false;
+    for i in 0..4 {
+        let (diff, underflow_a) = a[i].overflowing_sub(b[i]);
+        let (diff, underflow_b) = diff.overflowing_sub(borrow as u64);
+        out[i] = diff;
+        borrow = underflow_a || underflow_b;
+    }
+    from_limbs(out)
+}
+
+/// Multiply two 256 bit words, keeping the low 256 bits as the EVM does
+pub fn wrapping_mul_bytes32(a: &Literal, b: &Literal) -> Literal {
+    let (a, b) = (to_limbs(a), to_limbs(b));
+    let mut out = [0u64; 4];
+    for i in 0..4 {
+        let mut carry = 0u128;
+        for j in 0..4 - i {
+            let product = a[i] as u128 * b[j] as u128 + out[i + j] as u128 + carry;
+            out[i + j] = product as u64;
+            carry = product >> 64;
+        }
+    }
+    from_limbs(out)
+}
+
+/// Divide two 256 bit words, rounding down. Dividing by zero gives zero, as the EVM does
+pub fn wrapping_div_bytes32(a: &Literal, b: &Literal) -> Literal {
+    let mut quotient = [0u8; 32];
+    if *b == [0u8; 32] {
+        return quotient;
+    }
+
+    // Long division a bit at a time, from the most significant bit of the dividend
+    let mut remainder = [0u8; 32];
+    for bit in 0..256 {
+        let carry = remainder[0] & 0x80 != 0;
+        for i in 0..32 {
+            remainder[i] = remainder[i] << 1 | remainder.get(i + 1).map_or(0, |next| next >> 7);
+        }
+        remainder[31] |= (a[bit / 8] >> (7 - bit % 8)) & 1;
+        // Big endian words compare like the numbers they hold
+        if carry || remainder >= *b {
+            remainder = wrapping_sub_bytes32(&remainder, b);
+            quotient[bit / 8] |= 0x80 >> (bit % 8);
+        }
+    }
+    quotient
 }
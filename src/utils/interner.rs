@@ -0,0 +1,58 @@
+use std::{collections::HashMap, sync::Arc};
+
+/// An interned string, cheap to copy, compare and hash
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Symbol(u32);
+
+/// String Interner
+///
+/// Maps each distinct string to a [`Symbol`]. Interning the same string twice returns the same
+/// symbol, so repeated identifiers are only stored once. The lookup map and the list of strings
+/// share a single allocation per string.
+#[derive(Debug, Default, Clone)]
+pub struct Interner {
+    symbols: HashMap<Arc<str>, Symbol>,
+    strings: Vec<Arc<str>>,
+}
+
+impl Interner {
+    /// Create an empty interner
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the symbol of the string, interning it if it has not been seen before
+    pub fn intern(&mut self, s: &str) -> Symbol {
+        if let Some(symbol) = self.symbols.get(s) {
+            return *symbol;
+        }
+
+        let symbol = Symbol(self.strings.len() as u32);
+        let s: Arc<str> = s.into();
+        self.strings.push(s.clone());
+        self.symbols.insert(s, symbol);
+        symbol
+    }
+
+    /// Returns the symbol of the string without interning it
+    pub fn get(&self, s: &str) -> Option<Symbol> {
+        self.symbols.get(s).copied()
+    }
+
+    /// Returns the string a symbol was interned from
+    ///
+    /// Panics if the symbol was created by a different interner.
+    pub fn resolve(&self, symbol: Symbol) -> &str {
+        &self.strings[symbol.0 as usize]
+    }
+
+    /// The number of distinct strings interned
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    /// Returns if no strings have been interned
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+}
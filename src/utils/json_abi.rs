@@ -0,0 +1,109 @@
+//! Json Abi
+//!
+//! Generates the Solidity JSON ABI of the functions, events, errors and constructor defined in a
+//! parsed ast.
+
+use serde_json::{json, Value};
+
+use crate::{
+    parser::Ast,
+    span::Spanned,
+    utils::abi::{FunctionParam, FunctionParamType, FunctionType},
+};
+
+/// Json Abi
+///
+/// Returns the JSON ABI as an array with one entry per abi definition, in source order. Event
+/// inputs carry their `indexed` flag and events their `anonymous` flag. Tuple components are
+/// unnamed, as huff does not name them.
+pub fn json_abi(ast: &[Spanned<Ast>]) -> Value {
+    let entries = ast
+        .iter()
+        .filter_map(|(item, _)| match item {
+            Ast::AbiFunction(function) => Some(json!({
+                "type": "function",
+                "name": function.name,
+                "inputs": params(function.inputs.iter().map(|(param, _)| param)),
+                "outputs": params(function.outputs.iter().map(|(param, _)| param)),
+                "stateMutability": state_mutability(&function.state_mutability.0),
+            })),
+            Ast::AbiEvent(event) => {
+                let inputs: Vec<Value> = event
+                    .inputs
+                    .iter()
+                    .map(|(param, _)| {
+                        let mut input = param_type(&param.name, &param.kind);
+                        input["indexed"] = Value::Bool(param.indexed);
+                        input
+                    })
+                    .collect();
+                Some(json!({
+                    "type": "event",
+                    "name": event.name,
+                    "inputs": inputs,
+                    "anonymous": event.anonymous,
+                }))
+            }
+            Ast::AbiError(error) => Some(json!({
+                "type": "error",
+                "name": error.name,
+                "inputs": params(error.inputs.iter().map(|(param, _)| param)),
+            })),
+            Ast::AbiConstructor(constructor) => Some(json!({
+                "type": "constructor",
+                "inputs": params(constructor.inputs.iter()),
+                "stateMutability": "nonpayable",
+            })),
+            _ => None,
+        })
+        .collect();
+
+    Value::Array(entries)
+}
+
+fn params<'a>(params: impl Iterator<Item = &'a FunctionParam>) -> Vec<Value> {
+    params
+        .map(|param| param_type(&param.name, &param.kind))
+        .collect()
+}
+
+/// A named abi parameter. Tuples, and arrays of tuples, are written as `tuple` with their
+/// component types listed separately.
+fn param_type(name: &str, kind: &FunctionParamType) -> Value {
+    let (components, dimensions) = match kind {
+        FunctionParamType::Tuple(components) => (components, String::new()),
+        FunctionParamType::Array(inner, sizes) => match inner.as_ref() {
+            // A dynamic dimension is stored as zero
+            FunctionParamType::Tuple(components) => (
+                components,
+                sizes
+                    .iter()
+                    .map(|size| match size {
+                        0 => "[]".to_string(),
+                        size => format!("[{size}]"),
+                    })
+                    .collect(),
+            ),
+            _ => return json!({ "name": name, "type": kind.to_string() }),
+        },
+        _ => return json!({ "name": name, "type": kind.to_string() }),
+    };
+
+    json!({
+        "name": name,
+        "type": format!("tuple{dimensions}"),
+        "components": components
+            .iter()
+            .map(|component| param_type("", component))
+            .collect::<Vec<_>>(),
+    })
+}
+
+fn state_mutability(function_type: &FunctionType) -> &'static str {
+    match function_type {
+        FunctionType::View => "view",
+        FunctionType::Payable => "payable",
+        FunctionType::NonPayable => "nonpayable",
+        FunctionType::Pure => "pure",
+    }
+}
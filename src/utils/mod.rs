@@ -2,5 +2,7 @@ pub mod abi;
 pub mod ast;
 pub mod builtins;
 pub mod bytes_util;
+pub mod interner;
+pub mod json_abi;
 pub mod opcodes;
 pub mod types;
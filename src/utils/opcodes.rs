@@ -2,9 +2,10 @@ use phf::phf_map;
 use std::fmt;
 
 /// All the EVM opcodes as a static array
-/// They are arranged in a particular order such that all the opcodes that have common
-/// prefixes are ordered by decreasing length to avoid mismatch when parseing.
-/// Example : [origin, or] or [push32, ..., push3]
+///
+/// The order carries no meaning. The lexer reads a whole identifier before looking it up in
+/// [`OPCODES_MAP`], so mnemonics that are prefixes of others, such as `or` and `origin`, never
+/// clash.
 pub const OPCODES: [&str; 146] = [
     "lt",
     "gt",
@@ -304,6 +305,13 @@ pub static OPCODES_MAP: phf::Map<&'static str, Opcode> = phf_map! {
     "selfdestruct" => Opcode::Selfdestruct
 };
 
+/// Looks up an opcode by its mnemonic in any case, e.g. `ADD` as written in EVM traces.
+///
+/// The lexer only accepts lowercase mnemonics, so use [`OPCODES_MAP`] when matching source code.
+pub fn opcode_from_str_ci(s: &str) -> Option<Opcode> {
+    OPCODES_MAP.get(s.to_lowercase().as_str()).copied()
+}
+
 /// EVM Opcodes
 /// References <https://evm.codes>
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -603,6 +611,43 @@ pub enum Opcode {
     Extcodehash,
 }
 
+/// An Ethereum hard fork that introduced new opcodes, in chronological order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HardFork {
+    /// The original release of the EVM
+    Frontier,
+    /// Introduced `delegatecall`
+    Homestead,
+    /// Introduced `returndatasize`, `returndatacopy`, `staticcall` and `revert`
+    Byzantium,
+    /// Introduced the shift opcodes, `extcodehash` and `create2`
+    Constantinople,
+    /// Introduced `chainid` and `selfbalance`
+    Istanbul,
+    /// Introduced `basefee`
+    London,
+    /// Introduced `prevrandao`, replacing `difficulty`
+    Paris,
+    /// Introduced transient storage, `tload` and `tstore`
+    Cancun,
+}
+
+impl fmt::Display for HardFork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            HardFork::Frontier => "Frontier",
+            HardFork::Homestead => "Homestead",
+            HardFork::Byzantium => "Byzantium",
+            HardFork::Constantinople => "Constantinople",
+            HardFork::Istanbul => "Istanbul",
+            HardFork::London => "London",
+            HardFork::Paris => "Paris",
+            HardFork::Cancun => "Cancun",
+        };
+        write!(f, "{name}")
+    }
+}
+
 impl Opcode {
     /// Translates an Opcode into a string
     pub fn string(&self) -> String {
@@ -757,6 +802,254 @@ impl Opcode {
         opcode_str.to_string()
     }
 
+    /// Returns the mnemonic the opcode is written as in huff source
+    pub fn mnemonic(&self) -> &'static str {
+        OPCODES_MAP
+            .entries()
+            .find(|(_, opcode)| *opcode == self)
+            .map(|(mnemonic, _)| *mnemonic)
+            .expect("every opcode has a mnemonic")
+    }
+
+    /// Returns the number of stack items the opcode consumes and produces
+    pub fn stack_io(&self) -> (usize, usize) {
+        let byte = u8::from_str_radix(&self.string(), 16).expect("opcodes are valid hex");
+        match self {
+            // push1..=push32
+            _ if self.is_push() => (0, 1),
+            // dup1..=dup16 and swap1..=swap16
+            _ if (0x80..=0x8f).contains(&byte) => {
+                let n = (byte - 0x7f) as usize;
+                (n, n + 1)
+            }
+            _ if (0x90..=0x9f).contains(&byte) => {
+                let n = (byte - 0x8f) as usize;
+                (n + 1, n + 1)
+            }
+            Opcode::Log0 | Opcode::Log1 | Opcode::Log2 | Opcode::Log3 | Opcode::Log4 => {
+                ((byte - 0xa0) as usize + 2, 0)
+            }
+            Opcode::Stop | Opcode::Jumpdest | Opcode::Invalid => (0, 0),
+            Opcode::Address
+            | Opcode::Origin
+            | Opcode::Caller
+            | Opcode::Callvalue
+            | Opcode::Calldatasize
+            | Opcode::Codesize
+            | Opcode::Gasprice
+            | Opcode::Returndatasize
+            | Opcode::Coinbase
+            | Opcode::Timestamp
+            | Opcode::Number
+            | Opcode::Difficulty
+            | Opcode::Prevrandao
+            | Opcode::Gaslimit
+            | Opcode::Chainid
+            | Opcode::Selfbalance
+            | Opcode::Basefee
+            | Opcode::Pc
+            | Opcode::Msize
+            | Opcode::Gas => (0, 1),
+            Opcode::Iszero
+            | Opcode::Not
+            | Opcode::Balance
+            | Opcode::Calldataload
+            | Opcode::Extcodesize
+            | Opcode::Extcodehash
+            | Opcode::Blockhash
+            | Opcode::Mload
+            | Opcode::Sload
+            | Opcode::TLoad => (1, 1),
+            Opcode::Pop | Opcode::Jump | Opcode::Selfdestruct => (1, 0),
+            Opcode::Mstore
+            | Opcode::Mstore8
+            | Opcode::Sstore
+            | Opcode::TStore
+            | Opcode::Jumpi
+            | Opcode::Return
+            | Opcode::Revert => (2, 0),
+            Opcode::Calldatacopy | Opcode::Codecopy | Opcode::Returndatacopy => (3, 0),
+            Opcode::Extcodecopy => (4, 0),
+            Opcode::Addmod | Opcode::Mulmod | Opcode::Create => (3, 1),
+            Opcode::Create2 => (4, 1),
+            Opcode::Call | Opcode::Callcode => (7, 1),
+            Opcode::Delegatecall | Opcode::Staticcall => (6, 1),
+            // Every remaining opcode is a binary operation
+            _ => (2, 1),
+        }
+    }
+
+    /// Returns the hard fork that introduced the opcode
+    pub fn introduced_in(&self) -> HardFork {
+        match self {
+            Opcode::Stop
+            | Opcode::Add
+            | Opcode::Mul
+            | Opcode::Sub
+            | Opcode::Div
+            | Opcode::Sdiv
+            | Opcode::Mod
+            | Opcode::Smod
+            | Opcode::Addmod
+            | Opcode::Mulmod
+            | Opcode::Exp
+            | Opcode::Signextend
+            | Opcode::Lt
+            | Opcode::Gt
+            | Opcode::Slt
+            | Opcode::Sgt
+            | Opcode::Eq
+            | Opcode::Iszero
+            | Opcode::And
+            | Opcode::Or
+            | Opcode::Xor
+            | Opcode::Not
+            | Opcode::Byte
+            | Opcode::Sha3
+            | Opcode::Address
+            | Opcode::Balance
+            | Opcode::Origin
+            | Opcode::Caller
+            | Opcode::Callvalue
+            | Opcode::Calldataload
+            | Opcode::Calldatasize
+            | Opcode::Calldatacopy
+            | Opcode::Codesize
+            | Opcode::Codecopy
+            | Opcode::Gasprice
+            | Opcode::Extcodesize
+            | Opcode::Extcodecopy
+            | Opcode::Blockhash
+            | Opcode::Coinbase
+            | Opcode::Timestamp
+            | Opcode::Number
+            | Opcode::Difficulty
+            | Opcode::Gaslimit
+            | Opcode::Pop
+            | Opcode::Mload
+            | Opcode::Mstore
+            | Opcode::Mstore8
+            | Opcode::Sload
+            | Opcode::Sstore
+            | Opcode::Jump
+            | Opcode::Jumpi
+            | Opcode::Pc
+            | Opcode::Msize
+            | Opcode::Gas
+            | Opcode::Jumpdest
+            | Opcode::Push1
+            | Opcode::Push2
+            | Opcode::Push3
+            | Opcode::Push4
+            | Opcode::Push5
+            | Opcode::Push6
+            | Opcode::Push7
+            | Opcode::Push8
+            | Opcode::Push9
+            | Opcode::Push10
+            | Opcode::Push11
+            | Opcode::Push12
+            | Opcode::Push13
+            | Opcode::Push14
+            | Opcode::Push15
+            | Opcode::Push16
+            | Opcode::Push17
+            | Opcode::Push18
+            | Opcode::Push19
+            | Opcode::Push20
+            | Opcode::Push21
+            | Opcode::Push22
+            | Opcode::Push23
+            | Opcode::Push24
+            | Opcode::Push25
+            | Opcode::Push26
+            | Opcode::Push27
+            | Opcode::Push28
+            | Opcode::Push29
+            | Opcode::Push30
+            | Opcode::Push31
+            | Opcode::Push32
+            | Opcode::Dup1
+            | Opcode::Dup2
+            | Opcode::Dup3
+            | Opcode::Dup4
+            | Opcode::Dup5
+            | Opcode::Dup6
+            | Opcode::Dup7
+            | Opcode::Dup8
+            | Opcode::Dup9
+            | Opcode::Dup10
+            | Opcode::Dup11
+            | Opcode::Dup12
+            | Opcode::Dup13
+            | Opcode::Dup14
+            | Opcode::Dup15
+            | Opcode::Dup16
+            | Opcode::Swap1
+            | Opcode::Swap2
+            | Opcode::Swap3
+            | Opcode::Swap4
+            | Opcode::Swap5
+            | Opcode::Swap6
+            | Opcode::Swap7
+            | Opcode::Swap8
+            | Opcode::Swap9
+            | Opcode::Swap10
+            | Opcode::Swap11
+            | Opcode::Swap12
+            | Opcode::Swap13
+            | Opcode::Swap14
+            | Opcode::Swap15
+            | Opcode::Swap16
+            | Opcode::Log0
+            | Opcode::Log1
+            | Opcode::Log2
+            | Opcode::Log3
+            | Opcode::Log4
+            | Opcode::Create
+            | Opcode::Call
+            | Opcode::Callcode
+            | Opcode::Return
+            | Opcode::Invalid
+            | Opcode::Selfdestruct => HardFork::Frontier,
+            Opcode::Delegatecall => HardFork::Homestead,
+            Opcode::Returndatasize
+            | Opcode::Returndatacopy
+            | Opcode::Staticcall
+            | Opcode::Revert => HardFork::Byzantium,
+            Opcode::Shl | Opcode::Shr | Opcode::Sar | Opcode::Extcodehash | Opcode::Create2 => {
+                HardFork::Constantinople
+            }
+            Opcode::Chainid | Opcode::Selfbalance => HardFork::Istanbul,
+            Opcode::Basefee => HardFork::London,
+            Opcode::Prevrandao => HardFork::Paris,
+            Opcode::TLoad | Opcode::TStore => HardFork::Cancun,
+        }
+    }
+
+    /// Returns if the opcode ends execution of the current context
+    pub fn is_halting(&self) -> bool {
+        matches!(
+            self,
+            Opcode::Stop | Opcode::Return | Opcode::Revert | Opcode::Invalid | Opcode::Selfdestruct
+        )
+    }
+
+    /// The push opcode that pushes `size` bytes, `None` if there is no such opcode
+    pub fn push_of_size(size: usize) -> Option<Opcode> {
+        OPCODES_MAP.get(format!("push{size}").as_str()).copied()
+    }
+
+    /// The number of bytes pushed by a push opcode, `None` for every other opcode
+    pub fn push_size(&self) -> Option<usize> {
+        if !self.is_push() {
+            return None;
+        }
+        u8::from_str_radix(&self.string(), 16)
+            .ok()
+            .map(|byte| (byte - 0x5f) as usize)
+    }
+
     /// Returns if the current opcode is a push opcode
     pub fn is_push(&self) -> bool {
         matches!(
@@ -127,7 +127,9 @@ impl TryFrom<String> for EToken {
                     }
                     _ => {
                         return Ok(EToken(Token::FixedBytes(
-                            str_to_bytes32(cleaned_input).to_vec(),
+                            str_to_bytes32(cleaned_input)
+                                .map_err(|e| e.to_string())?
+                                .to_vec(),
                         )))
                     }
                 }
@@ -0,0 +1,744 @@
+use chumsky_huff::{
+    analysis::{analyze, stack::check_fn_stack},
+    error::Diagnostic,
+    parse_str,
+};
+
+fn analyze_src(src: &str) -> Vec<Diagnostic> {
+    analyze(&parse_str(src).unwrap())
+}
+
+#[test]
+fn codesize_accepts_a_macro() {
+    let src = "#define macro A() = takes(0) returns(0) { 0x01 }
+#define macro MAIN() = takes(0) returns(0) { __codesize(A) }";
+    assert!(analyze_src(src).is_empty());
+}
+
+#[test]
+fn codesize_rejects_constants_and_undefined_names() {
+    let src = "#define constant C = 0x01
+#define macro MAIN() = takes(0) returns(0) { __codesize(C) __codesize(Z) }";
+    let diagnostics = analyze_src(src);
+    let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        [
+            "__codesize expects a macro, but `C` is a constant",
+            "__codesize references undefined macro `Z`",
+        ]
+    );
+    assert!(diagnostics.iter().all(Diagnostic::is_error));
+    assert_eq!(&src[diagnostics[0].span.clone()], "C");
+}
+
+#[test]
+fn three_indexed_event_params_are_allowed() {
+    let src = "#define event A(uint256 indexed a, uint256 indexed b, address indexed c, uint256 d)";
+    assert!(analyze_src(src).is_empty());
+}
+
+#[test]
+fn four_indexed_event_params_are_an_error() {
+    let src = "#define event A(uint256 indexed a, uint256 indexed b, address indexed c, uint256 indexed d)";
+    let diagnostics = analyze_src(src);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0].message,
+        "event `A` has 4 indexed parameters, but at most 3 are allowed"
+    );
+    assert_eq!(&src[diagnostics[0].span.clone()], "uint256 indexed d");
+
+    // Anonymous events have no topic reserved for the event hash
+    let anonymous = src.to_string() + " anonymous";
+    assert!(analyze_src(&anonymous).is_empty());
+}
+
+fn lower_src(src: &str) -> chumsky_huff::utils::ast::Contract {
+    let (contract, diagnostics) = chumsky_huff::lowering::lower(&parse_str(src).unwrap());
+    assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    contract
+}
+
+#[test]
+fn abi_diff_reports_a_changed_signature() {
+    use chumsky_huff::analysis::abi_diff::{abi_diff, AbiChange, AbiItemKind};
+
+    let old = lower_src(
+        "#define function f(uint256) view returns (uint256)
+#define event E(uint256 indexed)",
+    );
+    let new = lower_src(
+        "#define function f(address) view returns (uint256)
+#define event E(uint256 indexed)",
+    );
+    assert_eq!(
+        abi_diff(&old, &new),
+        [AbiChange::SignatureChanged {
+            kind: AbiItemKind::Function,
+            name: "f".to_string(),
+            old: "f(uint256)".to_string(),
+            new: "f(address)".to_string(),
+        }]
+    );
+    assert!(abi_diff(&old, &old).is_empty());
+}
+
+#[test]
+fn peephole_flags_swap_swap() {
+    use chumsky_huff::error::Severity;
+
+    let src = "#define macro A() = takes(2) returns(2) { swap1 swap1 add }";
+    let diagnostics = analyze_src(src);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].severity, Severity::Info);
+    assert_eq!(diagnostics[0].message, "`swap1 swap1` has no effect");
+    assert_eq!(&src[diagnostics[0].span.clone()], "swap1 swap1");
+}
+
+#[test]
+fn peephole_patterns_are_configurable() {
+    use chumsky_huff::{
+        analysis::{
+            analyze_with_options,
+            peephole::{PatternItem, RedundantPattern},
+            AnalysisOptions,
+        },
+        utils::opcodes::Opcode,
+    };
+
+    let ast = parse_str("#define macro A() = takes(1) returns(1) { not not swap1 swap1 }").unwrap();
+    let options = AnalysisOptions {
+        redundant_patterns: vec![RedundantPattern::new(
+            vec![
+                PatternItem::Opcode(Opcode::Not),
+                PatternItem::Opcode(Opcode::Not),
+            ],
+            "double negation",
+        )],
+        ..Default::default()
+    };
+    let messages: Vec<_> = analyze_with_options(&ast, &options)
+        .into_iter()
+        .map(|d| d.message)
+        .collect();
+    assert_eq!(messages, ["double negation"]);
+}
+
+#[test]
+fn opcode_typos_suggest_close_opcodes() {
+    let src = "#define macro A() = takes(0) returns(0) { addr lbl jump lbl: }";
+    let diagnostics = analyze_src(src);
+    assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+    assert_eq!(
+        diagnostics[0].message,
+        "unknown opcode `addr`, did you mean `add`?"
+    );
+    assert_eq!(&src[diagnostics[0].span.clone()], "addr");
+}
+
+#[test]
+fn opcode_typo_severity_is_configurable() {
+    use chumsky_huff::{
+        analysis::{analyze_with_options, AnalysisOptions},
+        error::Severity,
+    };
+
+    let ast = parse_str("#define macro A() = takes(0) returns(0) { sstor }").unwrap();
+    let error = AnalysisOptions {
+        opcode_typos: Some(Severity::Error),
+        ..Default::default()
+    };
+    let diagnostics = analyze_with_options(&ast, &error);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].severity, Severity::Error);
+
+    let disabled = AnalysisOptions {
+        opcode_typos: None,
+        ..Default::default()
+    };
+    assert!(analyze_with_options(&ast, &disabled).is_empty());
+}
+
+#[test]
+fn invocation_references_are_resolved() {
+    let src = "#define constant OWNER = 0x01
+#define macro FOO(a, b) = takes(0) returns(0) { <a> <b> }
+#define macro BAR(x) = takes(0) returns(0) { FOO([OWNER], <x>) FOO([NOPE], <y>) }";
+    let messages: Vec<_> = analyze_src(src).into_iter().map(|d| d.message).collect();
+    assert_eq!(
+        messages,
+        [
+            "reference to undefined constant `[NOPE]`",
+            "`<y>` is not an argument of the enclosing macro",
+        ]
+    );
+}
+
+#[test]
+fn symbol_table_groups_definitions_by_name() {
+    use chumsky_huff::analysis::symbols::{SymbolKind, SymbolTable};
+
+    let src = "#define constant A = 0x01
+#define macro A() = takes(0) returns(0) { 0x01 }
+#define fn B() = takes(0) returns(0) { 0x01 }";
+    let symbols = SymbolTable::from_ast(&parse_str(src).unwrap());
+
+    let kinds: Vec<_> = symbols.lookup("A").iter().map(|s| s.kind).collect();
+    assert_eq!(kinds, [SymbolKind::Constant, SymbolKind::Macro]);
+    let b = symbols.find_macro("B").unwrap();
+    assert_eq!((symbols.name(b), b.kind), ("B", SymbolKind::Fn));
+    assert!(symbols.lookup("C").is_empty());
+
+    // Definitions sharing a name share its symbol
+    let a = symbols.symbol("A").unwrap();
+    assert!(symbols
+        .lookup("A")
+        .iter()
+        .all(|definition| definition.name == a));
+    assert_ne!(Some(a), symbols.symbol("B"));
+    assert_eq!(symbols.symbol("C"), None);
+}
+
+#[test]
+fn fn_stack_reports_misdeclared_fns() {
+    let src = "#define fn BAD() = takes(0) returns(0) { add 0x01 }
+#define fn GOOD() = takes(2) returns(1) { add }
+#define fn LEAK() = takes(1) returns(0) { 0x01 add }
+#define macro MAIN() = takes(0) returns(0) { 0x01 GOOD() pop }";
+    let diagnostics = check_fn_stack(&parse_str(src).unwrap());
+    let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        [
+            "fn `BAD` declares takes (0) but its body consumes 2 stack item(s)",
+            "fn `LEAK` declares returns (0) but its body leaves 1 stack item(s)",
+            "fn `GOOD` takes 2 stack item(s), but only 1 are available",
+        ]
+    );
+}
+
+#[test]
+fn fn_stack_counts_a_push_and_its_immediate_once() {
+    let src = "#define fn F() = takes(0) returns(1) { push1 0x20 }
+#define fn G() = takes(0) returns(2) { push 0x20 0x40 }";
+    assert!(check_fn_stack(&parse_str(src).unwrap()).is_empty());
+}
+
+#[test]
+fn duplicate_named_params_are_reported_at_the_second_occurrence() {
+    use chumsky_huff::analysis::params::check_duplicate_params;
+
+    let src = "#define function transfer(address to, address to) nonpayable returns (uint256)
+#define event E(address, address)
+#define error Er(uint256 a, uint256 a)";
+    let diagnostics = check_duplicate_params(&parse_str(src).unwrap());
+    let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        [
+            "function `transfer` has more than one parameter named `to`",
+            "error `Er` has more than one parameter named `a`",
+        ]
+    );
+    assert_eq!(&src[diagnostics[0].span.clone()], "address to");
+    assert!(diagnostics[0].span.start > src.find("to,").unwrap());
+}
+
+#[test]
+fn free_storage_pointer_colliding_with_a_literal_is_reported() {
+    use chumsky_huff::analysis::storage::check_storage_slots;
+
+    let src = "#define constant SLOT = FREE_STORAGE_POINTER()
+#define constant ZERO = 0x00
+#define constant ONE = 0x01
+#define constant ALSO_ONE = 0x01";
+    let diagnostics = check_storage_slots(&parse_str(src).unwrap());
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0].message,
+        "constants `SLOT` and `ZERO` both resolve to storage slot 0x0"
+    );
+    assert_eq!(&src[diagnostics[0].span.clone()], "constant ZERO = 0x00");
+    assert_eq!(diagnostics[0].related.len(), 1);
+}
+
+#[test]
+fn aliases_resolve_to_the_slot_of_their_target() {
+    use chumsky_huff::analysis::storage::{check_storage_slots, resolve_constants};
+
+    let src = "#define constant ZERO = 0x00
+#define constant SLOT = FREE_STORAGE_POINTER()
+#define constant OTHER = FREE_STORAGE_POINTER()
+#define constant ALIAS = [OTHER]
+#define constant ALIAS_OF_ALIAS = [ALIAS]
+#define constant LOOP = [LOOP]";
+    let ast = parse_str(src).unwrap();
+
+    let resolved: Vec<_> = resolve_constants(&ast)
+        .into_iter()
+        .map(|c| (c.name, c.value[31], c.alias_of))
+        .collect();
+    assert_eq!(
+        resolved,
+        [
+            ("ZERO".to_string(), 0, None),
+            ("SLOT".to_string(), 0, None),
+            ("OTHER".to_string(), 1, None),
+            ("ALIAS".to_string(), 1, Some("OTHER".to_string())),
+            ("ALIAS_OF_ALIAS".to_string(), 1, Some("OTHER".to_string())),
+        ]
+    );
+
+    // Sharing the slot of the aliased constant is not a collision
+    let messages: Vec<_> = check_storage_slots(&ast)
+        .into_iter()
+        .map(|d| d.message)
+        .collect();
+    assert_eq!(
+        messages,
+        ["constants `ZERO` and `SLOT` both resolve to storage slot 0x0"]
+    );
+
+    let src = "#define constant ONE = 0x01
+#define constant ZERO = FREE_STORAGE_POINTER()
+#define constant SLOT = FREE_STORAGE_POINTER()
+#define constant ALIAS = [SLOT]";
+    let messages: Vec<_> = check_storage_slots(&parse_str(src).unwrap())
+        .into_iter()
+        .map(|d| d.message)
+        .collect();
+    assert_eq!(
+        messages,
+        [
+            "constants `ONE` and `SLOT` both resolve to storage slot 0x1",
+            "constants `ONE` and `ALIAS` both resolve to storage slot 0x1",
+        ]
+    );
+}
+
+#[test]
+fn reachability_follows_invocations_constants_and_tables() {
+    use chumsky_huff::analysis::reachability::reachable_from;
+
+    let contract = lower_src(
+        "#define constant C = 0x01
+#define constant D = 0x02
+#define constant UNUSED = 0x03
+#define jumptable T() = { a }
+#define macro HELPER(x) = takes(0) returns(0) { <x> [D] __tablesize(T) }
+#define macro ORPHAN() = takes(0) returns(0) { [UNUSED] }
+#define macro MAIN() = takes(0) returns(0) { HELPER([C]) a: }",
+    );
+    let mut reachable: Vec<_> = reachable_from(&contract, "MAIN").into_iter().collect();
+    reachable.sort();
+    assert_eq!(reachable, ["C", "D", "HELPER", "MAIN", "T"]);
+    assert!(reachable_from(&contract, "MISSING").is_empty());
+}
+
+#[test]
+fn undefined_constant_references_in_bodies_are_reported() {
+    let src = "#define constant C = 0x01
+#define macro MAIN() = takes(0) returns(0) { [C] [NOPE] }";
+    let diagnostics = analyze_src(src);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0].message,
+        "reference to undefined constant `[NOPE]`"
+    );
+    assert_eq!(&src[diagnostics[0].span.clone()], "[NOPE]");
+}
+
+#[test]
+fn data_locations_are_checked_per_context() {
+    use chumsky_huff::analysis::locations::check_locations;
+
+    let src = "#define function a(bytes storage x) nonpayable returns ()
+#define function b(bytes memory x, uint256 calldata y) nonpayable returns (string calldata)
+#define error E(string memory s)";
+    let diagnostics = check_locations(&parse_str(src).unwrap());
+    let messages: Vec<_> = diagnostics
+        .iter()
+        .map(|d| (d.message.as_str(), &src[d.span.clone()]))
+        .collect();
+    assert_eq!(
+        messages,
+        [
+            (
+                "`storage` is not a valid location for an abi parameter",
+                "bytes storage x"
+            ),
+            (
+                "`calldata` is only valid on dynamic types, not `uint256`",
+                "uint256 calldata y"
+            ),
+            (
+                "`calldata` is not a valid location for a return value",
+                "string calldata"
+            ),
+            (
+                "error parameters cannot have a data location",
+                "string memory s"
+            ),
+        ]
+    );
+}
+
+#[test]
+fn jump_tables_must_reference_defined_labels() {
+    use chumsky_huff::analysis::tables::check_jump_tables;
+
+    let src = "#define jumptable T() = { a missing }
+#define macro M() = takes(0) returns(0) { a: 0x01 }";
+    let diagnostics = check_jump_tables(&parse_str(src).unwrap());
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0].message,
+        "table `T` references undefined label `missing`"
+    );
+    assert_eq!(&src[diagnostics[0].span.clone()], "missing");
+}
+
+#[test]
+fn entry_points_are_checked() {
+    use chumsky_huff::{
+        analysis::{entry_points::check_entry_points, symbols::SymbolTable},
+        error::Severity,
+    };
+
+    let check = |src: &str| -> Vec<String> {
+        let ast = parse_str(src).unwrap();
+        check_entry_points(&ast, &SymbolTable::from_ast(&ast), Severity::Warning)
+            .into_iter()
+            .map(|d| d.message)
+            .collect()
+    };
+    let main = "#define macro MAIN() = takes(0) returns(0) {}\n";
+    let ctor_macro = "#define macro CONSTRUCTOR() = takes(0) returns(0) {}\n";
+    let ctor_abi = "#define constructor(address owner)\n";
+
+    assert!(check(main).is_empty());
+    assert_eq!(
+        check(ctor_macro),
+        [
+            "contract has no `MAIN` macro",
+            "`CONSTRUCTOR` macro is defined, but no constructor is declared",
+        ]
+    );
+    assert_eq!(
+        check(&format!("{main}{ctor_abi}")),
+        ["a constructor is declared, but there is no `CONSTRUCTOR` macro"]
+    );
+    assert_eq!(
+        check(&format!("{main}{ctor_macro}")),
+        ["`CONSTRUCTOR` macro is defined, but no constructor is declared"]
+    );
+    assert!(check(&format!("{main}{ctor_macro}{ctor_abi}")).is_empty());
+}
+
+#[test]
+fn code_after_a_terminator_is_unreachable_until_a_label() {
+    use chumsky_huff::analysis::dead_code::check_dead_code;
+
+    let src = "#define macro MAIN() = takes (0) returns (0) {
+    0x00 0x00 revert
+    0x01 pop
+    ok:
+    0x00 0x00 revert
+    label:
+    stop
+}";
+    let diagnostics = check_dead_code(&parse_str(src).unwrap());
+    assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+    assert_eq!(diagnostics[0].message, "unreachable code after `revert`");
+    assert_eq!(&src[diagnostics[0].span.clone()], "0x01 pop");
+}
+
+#[test]
+fn stack_annotations_beyond_the_stack_limit_are_reported() {
+    use chumsky_huff::analysis::stack::check_stack_limit;
+
+    let src = "#define macro A() = takes (2000) returns (0) { stop }";
+    let diagnostics = check_stack_limit(&parse_str(src).unwrap());
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0].message,
+        "`A` declares takes (2000), but the stack holds at most 1024 items"
+    );
+    assert_eq!(&src[diagnostics[0].span.clone()], "takes (2000)");
+
+    let src = "#define macro A() = takes (16) returns (1024) { stop }";
+    assert!(check_stack_limit(&parse_str(src).unwrap()).is_empty());
+}
+
+#[test]
+fn max_stack_depth_stops_at_invocations() {
+    use chumsky_huff::analysis::stack::max_stack_depth;
+
+    let contract = lower_src(
+        "#define macro A() = takes (1) returns (0) { 0x01 0x02 0x03 add add add pop }
+#define macro B() = takes (0) returns (0) { 0x01 A() 0x01 0x02 0x03 }
+#define macro C() = takes (0) returns (0) { pop }",
+    );
+    assert_eq!(max_stack_depth(&contract.macros[0]), Some(4));
+    assert_eq!(max_stack_depth(&contract.macros[1]), Some(1));
+    assert_eq!(max_stack_depth(&contract.macros[2]), None);
+}
+
+#[test]
+fn max_stack_depth_counts_a_push_and_its_immediate_once() {
+    use chumsky_huff::analysis::stack::max_stack_depth;
+
+    let contract = lower_src(
+        "#define macro SIZED() = takes (0) returns (1) { push1 0x01 push1 0x02 add }
+#define macro SIZELESS() = takes (0) returns (1) { push 0x01 push 0x02 add }",
+    );
+    assert_eq!(max_stack_depth(&contract.macros[0]), Some(2));
+    assert_eq!(max_stack_depth(&contract.macros[1]), Some(2));
+}
+
+#[test]
+fn definitions_named_like_opcodes_are_reported() {
+    use chumsky_huff::analysis::shadowing::check_opcode_shadowing;
+
+    let src = "#define macro ADD() = takes(0) returns(0) {}
+#define constant Mstore = 0x01
+#define macro MAIN() = takes(0) returns(0) { ADD() }";
+    let diagnostics = check_opcode_shadowing(&parse_str(src).unwrap());
+    let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        [
+            "macro `ADD` has the same name as the opcode `add`",
+            "constant `Mstore` has the same name as the opcode `mstore`",
+        ]
+    );
+}
+
+#[test]
+fn functions_sharing_a_selector_are_reported() {
+    use chumsky_huff::analysis::selectors::check_selectors;
+
+    // `burn(uint256)` and `collate_propagate_storage(bytes16)` are a known 4 byte collision, and
+    // overloads differing only in return type share a selector
+    let ast = parse_str(
+        "#define function burn(uint256) nonpayable returns ()
+#define function collate_propagate_storage(bytes16) nonpayable returns ()
+#define function f(uint256) view returns (uint256)
+#define function f(uint256) view returns (bool)
+#define function g(address) view returns ()",
+    )
+    .unwrap();
+    let diagnostics = check_selectors(&ast);
+    assert_eq!(diagnostics.len(), 2, "{diagnostics:?}");
+    assert_eq!(
+        diagnostics[0].message,
+        "function `collate_propagate_storage` has the same selector, 0x42966c68, as function `burn`"
+    );
+    assert_eq!(diagnostics[0].span, ast[1].1);
+    assert_eq!(diagnostics[0].related, [ast[0].1.clone()]);
+    assert_eq!(diagnostics[1].span, ast[3].1);
+}
+
+#[test]
+fn abi_declarations_sharing_a_name_are_reported() {
+    use chumsky_huff::analysis::abi_names::check_abi_names;
+
+    let ast = parse_str(
+        "#define event Oops(uint256)
+#define error Oops(uint256)
+#define function f(uint256) view returns ()
+#define function f(address) view returns ()
+#define error E()
+#define error E()",
+    )
+    .unwrap();
+    let diagnostics = check_abi_names(&ast);
+    let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+    // Function overloads are allowed
+    assert_eq!(
+        messages,
+        [
+            "error `Oops` has the same name as event `Oops`",
+            "error `E` has the same name as error `E`",
+        ]
+    );
+    assert_eq!(diagnostics[0].span, ast[1].1);
+    assert_eq!(diagnostics[0].related, [ast[0].1.clone()]);
+}
+
+#[test]
+fn tables_named_like_opcodes_can_be_referenced() {
+    use chumsky_huff::{
+        lowering::lower,
+        parser::Ast,
+        utils::ast::{builtin_calls, BuiltinArg},
+    };
+
+    let ast = parse_str(
+        "#define jumptable add { lbl }
+#define codetable sub { 0x01 }
+#define macro MAIN() = takes(0) returns(0) { __tablesize(add) __tablestart(sub) lbl: }",
+    )
+    .unwrap();
+    assert!(matches!(&ast[0].0, Ast::TableDefinition { name, .. } if name == "add"));
+    assert!(matches!(&ast[1].0, Ast::TableDefinition { name, .. } if name == "sub"));
+
+    let (contract, diagnostics) = lower(&ast);
+    assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    let calls = builtin_calls(&contract);
+    assert_eq!(calls.len(), 2);
+    for (_, args, _) in &calls {
+        assert!(matches!(args[0], BuiltinArg::Table(_)), "{args:?}");
+    }
+
+    // Allowed, but still warned about
+    let diagnostics = analyze(&ast);
+    assert!(diagnostics.iter().all(|d| !d.is_error()), "{diagnostics:?}");
+    assert!(
+        diagnostics
+            .iter()
+            .any(|d| d.message.contains("same name as the opcode `add`")),
+        "{diagnostics:?}"
+    );
+}
+
+#[test]
+fn segments_share_helpers_reachable_from_both_entry_points() {
+    use chumsky_huff::{analysis::reachability::segment_reachability, lowering::lower};
+    use std::collections::HashSet;
+
+    let ast = parse_str(
+        "#define macro SHARED() = takes(0) returns(0) { add }
+#define macro DEPLOY() = takes(0) returns(0) { SHARED() }
+#define macro RUN() = takes(0) returns(0) { SHARED() }
+#define constant C = 0x01
+#define macro CONSTRUCTOR() = takes(0) returns(0) { DEPLOY() }
+#define macro MAIN() = takes(0) returns(0) { RUN() }
+#define macro UNUSED() = takes(0) returns(0) { }",
+    )
+    .unwrap();
+    let (contract, _) = lower(&ast);
+    let (constructor, runtime) = segment_reachability(&contract);
+    let set = |names: &[&str]| -> HashSet<String> { names.iter().map(|s| s.to_string()).collect() };
+    assert_eq!(constructor, set(&["CONSTRUCTOR", "DEPLOY", "SHARED"]));
+    assert_eq!(runtime, set(&["MAIN", "RUN", "SHARED"]));
+}
+
+#[test]
+fn outlined_macros_cannot_use_caller_arguments() {
+    use chumsky_huff::analysis::outlined::check_fn_arg_invocations;
+
+    let src = "#define fn F(a) = takes(0) returns(0) { <a> <x> }
+#define macro M(y) = takes(0) returns(0) { <y> <z> F(<y>) }";
+    let ast = parse_str(src).unwrap();
+    // Inline macros are left to the invocation checks
+    let diagnostics = check_fn_arg_invocations(&ast);
+    assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+    assert_eq!(
+        diagnostics[0].message,
+        "`<x>` is not an argument of fn `F`, outlined macros cannot use the arguments of their caller"
+    );
+    assert_eq!(&src[diagnostics[0].span.clone()], "<x>");
+    assert!(analyze(&ast).iter().any(|d| d.message.starts_with("`<x>`")));
+}
+
+#[test]
+fn macros_used_as_jump_labels_are_reported() {
+    use chumsky_huff::analysis::{invocations::check_bare_macro_references, symbols::SymbolTable};
+
+    let src = "#define macro FOO(a, b) = takes(0) returns(0) { <a> <b> }
+#define macro BAR() = takes(0) returns(0) { add }
+#define macro MAIN() = takes(0) returns(0) { FOO BAR lbl jump lbl: }";
+    let diagnostics: Vec<_> = analyze_src(src)
+        .into_iter()
+        .filter(|d| d.message.contains("used as a jump label"))
+        .collect();
+    assert_eq!(diagnostics.len(), 2, "{diagnostics:?}");
+    assert_eq!(
+        diagnostics[0].message,
+        "`FOO` is a macro but is used as a jump label, did you mean `FOO(a, b)`?"
+    );
+    assert_eq!(&src[diagnostics[0].span.clone()], "FOO");
+    assert_eq!(
+        diagnostics[1].message,
+        "`BAR` is a macro but is used as a jump label, did you mean `BAR()`?"
+    );
+
+    // A label sharing the macro's name is a real label
+    let ast = parse_str("#define macro FOO() = takes(0) returns(0) { FOO jump FOO: }").unwrap();
+    assert!(check_bare_macro_references(&ast, &SymbolTable::from_ast(&ast)).is_empty());
+}
+
+#[test]
+fn decimal_immediates_are_sized_against_their_push() {
+    use chumsky_huff::{parser::MacroBody, utils::opcodes::Opcode};
+
+    let src = "#define macro MAIN() = takes(0) returns(0) { push1 32 push1 256 push2 256 }";
+    let ast = parse_str(src).unwrap();
+    let chumsky_huff::parser::Ast::MacroDefinition { statements, .. } = &ast[0].0 else {
+        panic!("expected a macro, found {:?}", ast[0].0);
+    };
+    let mut literal = [0u8; 32];
+    literal[31] = 32;
+    assert_eq!(statements[0].0, MacroBody::Opcode(Opcode::Push1));
+    assert_eq!(statements[1].0, MacroBody::HexLiteral(literal));
+
+    let diagnostics: Vec<_> = analyze(&ast)
+        .into_iter()
+        .filter(|d| d.message.contains("does not fit"))
+        .collect();
+    assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+    assert_eq!(
+        diagnostics[0].message,
+        "`0x100` needs 2 bytes, which does not fit in `push1`"
+    );
+    assert_eq!(&src[diagnostics[0].span.clone()], "256");
+}
+
+#[test]
+fn raw_jumpdests_are_reported() {
+    let src = "#define macro MAIN() = takes(0) returns(0) { jumpdest done: stop }";
+    let diagnostics: Vec<_> = analyze_src(src)
+        .into_iter()
+        .filter(|d| d.message.contains("jumpdest"))
+        .collect();
+    assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+    assert!(!diagnostics[0].is_error());
+    assert_eq!(&src[diagnostics[0].span.clone()], "jumpdest");
+}
+
+#[test]
+fn test_cases_take_their_environment_from_decorators() {
+    use chumsky_huff::{
+        analysis::test_cases::{test_cases, TestCase},
+        lowering::lower,
+    };
+
+    // A macro named `test` is not a test
+    let ast = parse_str(
+        "#[calldata(\"0xabcd\"), value(0x10)]
+#define test MY_TEST() = { stop }
+#define macro test() = takes(0) returns(0) { test jump test: }
+#define test PLAIN() = { stop }",
+    )
+    .unwrap();
+    let (contract, _) = lower(&ast);
+    let mut value = [0u8; 32];
+    value[31] = 0x10;
+    assert_eq!(
+        test_cases(&contract),
+        [
+            TestCase {
+                name: "MY_TEST".to_string(),
+                calldata: Some("0xabcd".to_string()),
+                value: Some(value),
+            },
+            TestCase {
+                name: "PLAIN".to_string(),
+                calldata: None,
+                value: None,
+            },
+        ]
+    );
+}
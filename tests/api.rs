@@ -0,0 +1,121 @@
+use chumsky_huff::{
+    definition_at, definitions_overlapping, diagnostics_with_positions, expected_at, parse_str,
+    parser::{Ast, MacroBody},
+    references,
+    span::LineCol,
+    statement_at,
+    utils::opcodes::Opcode,
+};
+
+#[test]
+fn diagnostics_are_positioned_by_line_and_column() {
+    let src = "#define macro A() = takes(0) returns(0) {\n    0x01\n    __notreal(A)\n}\n";
+    let diagnostics = diagnostics_with_positions(src);
+    assert_eq!(diagnostics.len(), 1);
+    let (start, end, message) = &diagnostics[0];
+    assert_eq!(*start, LineCol { line: 3, col: 5 });
+    assert_eq!(*end, LineCol { line: 3, col: 14 });
+    assert_eq!(message, "Unknown builtin function `__notreal`");
+}
+
+#[test]
+fn expected_tokens_after_define_include_keywords() {
+    let expected = expected_at("#define ", 8);
+    for keyword in ["macro", "fn", "constant", "function", "event", "jumptable"] {
+        assert!(
+            expected.iter().any(|e| e == keyword),
+            "{keyword} in {expected:?}"
+        );
+    }
+
+    // Nothing is expected where the source parses
+    assert!(expected_at("#define macro A() = takes(0) returns(0) {}", 3).is_empty());
+}
+
+#[test]
+fn offsets_resolve_to_the_enclosing_definition_and_statement() {
+    let src = "#define constant X = 0x01\n#define macro A() = takes (0) returns (0) {\n    caller\n    0x01 add\n}\n";
+    let ast = parse_str(src).unwrap();
+    let offset = src.find("add").unwrap() + 1;
+
+    let Some(Ast::MacroDefinition { name, .. }) = definition_at(&ast, offset) else {
+        panic!("expected a macro at {offset}");
+    };
+    assert_eq!(name, "A");
+    let (statement, span) = statement_at(&ast, offset).unwrap();
+    assert_eq!(*statement, MacroBody::Opcode(Opcode::Add));
+    assert_eq!(&src[span.clone()], "add");
+
+    assert!(matches!(
+        definition_at(&ast, 10),
+        Some(Ast::ConstantDefinition { .. })
+    ));
+    assert!(statement_at(&ast, 10).is_none());
+    assert!(definition_at(&ast, src.len() + 5).is_none());
+}
+
+#[test]
+fn references_cover_definitions_and_uses() {
+    let src = "#define macro HELPER() = takes(0) returns(0) { add }
+#define macro MAIN() = takes(0) returns(0) {
+  HELPER() lbl jump HELPER() lbl:
+  __tablesize(T)
+}
+#define macro WRAP(a) = takes(0) returns(0) { <a> [C] FOO([ C ], <a>) }
+#define constant C = 0x01
+#define constant D = [C]
+#define jumptable T { lbl }";
+    let ast = parse_str(src).unwrap();
+    let texts = |name: &str| -> Vec<&str> {
+        references(&ast, name)
+            .into_iter()
+            .map(|span| &src[span])
+            .collect()
+    };
+
+    // Only the names are covered, for definitions as well as uses
+    assert_eq!(texts("HELPER"), ["HELPER", "HELPER", "HELPER"]);
+    // A jump, the label itself and the table entry
+    assert_eq!(texts("lbl"), ["lbl", "lbl", "lbl"]);
+    assert_eq!(texts("T"), ["T", "T"]);
+    // Brackets are left out, even with space inside them, and aliases are uses too
+    assert_eq!(texts("C"), ["C", "C", "C", "C"]);
+    // The parameter and both argument references
+    assert_eq!(texts("a"), ["a", "a", "a"]);
+    assert!(texts("nothing").is_empty());
+}
+
+#[test]
+fn edits_overlap_only_the_definitions_they_touch() {
+    let src = "#define macro A() = takes(0) returns(0) { add }
+
+#define macro B() = takes(0) returns(0) { sub }
+
+#define constant C = 0x01";
+    let ast = parse_str(src).unwrap();
+    let sub = src.find("sub").unwrap();
+    let add = src.find("add").unwrap();
+    assert_eq!(definitions_overlapping(&ast, sub..sub + 3), [1]);
+    // An insertion point inside a definition touches it
+    assert_eq!(definitions_overlapping(&ast, sub..sub), [1]);
+    assert_eq!(definitions_overlapping(&ast, add..sub), [0, 1]);
+    // The blank line between definitions touches neither
+    assert!(definitions_overlapping(&ast, 49..50).is_empty());
+}
+
+#[test]
+fn debug_with_source_renders_line_and_column() {
+    use chumsky::Parser;
+    use chumsky_huff::{
+        lexer::{lexer, token::Token},
+        span::debug_with_source,
+    };
+
+    let src = "#define macro MAIN() = takes(0) returns(0) {\n    FOO\n}";
+    let tokens = lexer().parse(src).unwrap();
+    let foo = tokens
+        .iter()
+        .find(|(token, _)| *token == Token::Ident("FOO".to_string()))
+        .unwrap();
+    assert_eq!(debug_with_source(foo, src), "Ident(\"FOO\") @ 2:5-2:8");
+}
@@ -0,0 +1,36 @@
+use std::process::Command;
+
+fn chuffc(args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_chuffc"))
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn abi_prints_the_json_abi_of_a_file() {
+    let output = chuffc(&["abi", "tests/fixtures/token.huff"]);
+    assert!(output.status.success(), "{output:?}");
+
+    let abi: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(abi[0]["type"], "function");
+    assert_eq!(abi[0]["name"], "transfer");
+    assert_eq!(abi[0]["outputs"][0]["type"], "bool");
+    assert_eq!(abi[1]["type"], "event");
+    assert_eq!(abi[1]["inputs"][1]["indexed"], true);
+}
+
+#[test]
+fn bad_usage_and_missing_files_fail() {
+    assert_eq!(chuffc(&[]).status.code(), Some(2));
+    assert_eq!(
+        chuffc(&["frobnicate", "tests/fixtures/token.huff"])
+            .status
+            .code(),
+        Some(2)
+    );
+    assert!(!chuffc(&["abi", "tests/fixtures/missing.huff"])
+        .status
+        .success());
+}
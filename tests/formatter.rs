@@ -0,0 +1,94 @@
+#![cfg(feature = "fmt")]
+
+use chumsky_huff::formatter::reparse;
+
+const SAMPLE: &str = r#"
+#include "./lib.huff"
+#define function transfer(address to, uint256 amount) nonpayable returns (bool)
+#define function balanceOf(address) view returns (uint256)
+#define event Transfer(address indexed from, address indexed to, uint256 value)
+#define error Bad(uint256)
+#define constant OWNER = FREE_STORAGE_POINTER()
+#define constant X = 0x0100
+#define jumptable TABLE() = {
+    a b
+}
+#define codetable CODE() = {
+    0xdeadbeef
+}
+#define macro HELP(x) = takes (1) returns (2) {
+    <x> dup1
+}
+#define fn F() = takes(0) returns(0) { 0x01 }
+#define macro MAIN() = takes (0) returns (0) {
+    0x00 calldataload 0xe0 shr
+    HELP(a) __tablesize(TABLE)
+    a jumpi
+    a:
+        0x00 0x00 revert
+    b:
+        stop
+}
+"#;
+
+#[test]
+fn sample_contract_reparses_equal() {
+    let (original, reparsed) = reparse(SAMPLE).unwrap();
+    assert_eq!(original.len(), 12);
+    assert_eq!(original, reparsed);
+}
+
+/// Asserts that formatting the source and parsing it again gives the same ast
+fn assert_reparses(src: &str) {
+    let (original, reparsed) = reparse(src).unwrap();
+    assert_eq!(original, reparsed);
+}
+
+#[test]
+fn constant_aliases_reparse_equal() {
+    assert_reparses("#define constant B = 0x05\n#define constant A = [B]");
+}
+
+#[test]
+fn constant_expressions_reparse_equal() {
+    assert_reparses(
+        "#define constant A = 0x10 + 0x20\n#define constant C = 0x02 + 0x03 * 0x04 - 0x01\n\
+         #define constant D = 0x06 / 0x02",
+    );
+}
+
+#[test]
+fn constructors_reparse_equal() {
+    assert_reparses("#define constructor(address owner)");
+}
+
+#[test]
+fn decorators_reparse_equal() {
+    let src =
+        "#[calldata(\"0x1234\"), value(0x10)]\n#define macro T() = takes(0) returns(0) { add }";
+    assert_reparses(src);
+    let ast = chumsky_huff::parse_str(src).unwrap();
+    assert!(chumsky_huff::formatter::format(&ast)
+        .starts_with("#[calldata(\"0x1234\"), value(0x10)]\n#define macro T()"));
+}
+
+#[test]
+fn selective_includes_reparse_equal() {
+    assert_reparses("#include \"lib.huff\" {A}\n#include \"x.huff\"");
+    let ast = chumsky_huff::parse_str("#include \"lib.huff\" { A, B }").unwrap();
+    assert_eq!(
+        chumsky_huff::formatter::format(&ast),
+        "#include \"lib.huff\" { A, B }\n"
+    );
+}
+
+#[test]
+fn tests_reparse_equal() {
+    let src = "#[calldata(\"0xabcd\"), value(0x10)]\n#define test MY_TEST() = { stop }";
+    assert_reparses(src);
+    let formatted = chumsky_huff::formatter::format(&chumsky_huff::parse_str(src).unwrap());
+    assert!(
+        formatted.contains("#define test MY_TEST() = takes (0) returns (0) {"),
+        "{formatted}"
+    );
+}
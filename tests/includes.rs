@@ -0,0 +1,135 @@
+use std::path::{Path, PathBuf};
+
+use chumsky_huff::{error::IncludeError, includes::resolve_includes, parser::Ast, span::Spanned};
+
+/// Writes the files into a fresh temporary directory and returns its path
+fn write_files<N: AsRef<Path>, C: AsRef<str>>(
+    test: &str,
+    files: impl IntoIterator<Item = (N, C)>,
+) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("chuff-{test}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    for (name, contents) in files {
+        std::fs::write(dir.join(name), contents.as_ref()).unwrap();
+    }
+    dir
+}
+
+/// The names of the definitions of an ast, in order
+fn names(ast: &[Spanned<Ast>]) -> Vec<&str> {
+    ast.iter()
+        .filter_map(|(item, _)| match item {
+            Ast::ConstantDefinition { name, .. } | Ast::MacroDefinition { name, .. } => {
+                Some(name.as_str())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn includes_deeper_than_the_limit_are_an_error() {
+    // f0 includes f1, which includes f2, and so on down to f4
+    let files = (0..5).map(|i| {
+        let include = match i {
+            4 => String::new(),
+            _ => format!("#include \"f{}.huff\"\n", i + 1),
+        };
+        (
+            format!("f{i}.huff"),
+            format!("{include}#define constant C{i} = 0x0{i}\n"),
+        )
+    });
+    let dir = write_files("depth", files);
+    let root = dir.join("f0.huff");
+
+    let ast = resolve_includes(&root, 4).unwrap();
+    assert_eq!(names(&ast), ["C4", "C3", "C2", "C1", "C0"]);
+
+    let error = resolve_includes(&root, 2).unwrap_err();
+    let IncludeError::TooDeep { chain, max_depth } = &error else {
+        panic!("expected a depth error, found {error}");
+    };
+    assert_eq!(*max_depth, 2);
+    let chain: Vec<_> = chain.iter().map(|p| p.file_name().unwrap()).collect();
+    assert_eq!(chain, ["f0.huff", "f1.huff", "f2.huff", "f3.huff"]);
+    assert!(error
+        .to_string()
+        .starts_with("includes are nested more than 2 deep: "));
+
+    // Including an earlier file again is a cycle rather than a depth error
+    std::fs::write(dir.join("f4.huff"), "#include \"f2.huff\"\n").unwrap();
+    let error = resolve_includes(&root, 64).unwrap_err();
+    let IncludeError::Cycle(chain) = &error else {
+        panic!("expected a cycle, found {error}");
+    };
+    assert_eq!(chain.len(), 6);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn repeated_includes_merge_their_selections() {
+    let lib = "#define constant C = 0x01
+#define macro A() = takes(0) returns(0) { B() }
+#define macro B() = takes(0) returns(0) { [C] }
+#define macro D() = takes(0) returns(0) { add }";
+    let dir = write_files(
+        "selections",
+        [
+            ("lib.huff", lib),
+            ("plain.huff", "#include \"lib.huff\""),
+            (
+                "twice.huff",
+                "#include \"lib.huff\" { A }\n#include \"lib.huff\" { D }",
+            ),
+            (
+                "then_plain.huff",
+                "#include \"lib.huff\" { D }\n#define macro MAIN() = takes(0) returns(0) { D() }\n#include \"plain.huff\"",
+            ),
+        ],
+    );
+
+    let ast = resolve_includes(&dir.join("plain.huff"), 64).unwrap();
+    assert_eq!(names(&ast), ["C", "A", "B", "D"]);
+
+    // Both selections are taken, along with what they use, and the constant only once
+    let ast = resolve_includes(&dir.join("twice.huff"), 64).unwrap();
+    assert_eq!(names(&ast), ["C", "A", "B", "D"]);
+
+    // A later plain include takes the rest of the macros, where it includes them
+    let ast = resolve_includes(&dir.join("then_plain.huff"), 64).unwrap();
+    assert_eq!(names(&ast), ["C", "D", "MAIN", "A", "B"]);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn selective_includes_drop_unlisted_macros() {
+    let lib = "#define constant K = 0x01
+#define macro A() = takes(0) returns(0) { HELPER() }
+#define macro HELPER() = takes(0) returns(0) { add }
+#define macro B() = takes(0) returns(0) { sub }
+#define macro C() = takes(0) returns(0) { mul }";
+    let main = "#define macro MAIN() = takes(0) returns(0) { A() }";
+    let dir = write_files(
+        "selective",
+        [
+            ("lib.huff", lib.to_string()),
+            ("plain.huff", format!("#include \"lib.huff\"\n{main}")),
+            (
+                "sel.huff",
+                format!("#include \"lib.huff\" {{ A, B, }}\n{main}"),
+            ),
+        ],
+    );
+
+    let ast = resolve_includes(&dir.join("plain.huff"), 8).unwrap();
+    assert_eq!(names(&ast), ["K", "A", "HELPER", "B", "C", "MAIN"]);
+    // `HELPER` is kept as `A` uses it
+    let ast = resolve_includes(&dir.join("sel.huff"), 8).unwrap();
+    assert_eq!(names(&ast), ["K", "A", "HELPER", "B", "MAIN"]);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
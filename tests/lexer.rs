@@ -0,0 +1,491 @@
+use chumsky::Parser;
+use chumsky_huff::{
+    lexer::{
+        lex_lossless, lexer,
+        token::{CommentKind, Token, KEYWORDS_MAP},
+        trivia_lexer,
+    },
+    utils::opcodes::{Opcode, OPCODES_MAP},
+};
+
+fn lex(src: &str) -> Vec<(Token, std::ops::Range<usize>)> {
+    lexer().parse(src).unwrap()
+}
+
+#[test]
+fn crlf_multiline_comment_collapses_to_one_newline() {
+    let src = "add\r\n/* a\r\n b\r\n */\r\nsub";
+    assert_eq!(
+        lex(src),
+        [
+            (Token::Opcode(Opcode::Add), 0..3),
+            (Token::Newline, 3..20),
+            (Token::Opcode(Opcode::Sub), 20..23),
+            (Token::Newline, 23..23),
+        ]
+    );
+
+    // A comment that starts on the line of a token
+    let src = "add /* a\r\n b */\r\nsub";
+    let tokens = lex(src);
+    assert_eq!(tokens[1], (Token::Newline, 4..17));
+    assert_eq!(&src[tokens[2].1.clone()], "sub");
+}
+
+fn comment_kinds(src: &str) -> Vec<CommentKind> {
+    trivia_lexer()
+        .parse(src)
+        .unwrap()
+        .into_iter()
+        .filter_map(|(token, _)| match token {
+            Token::Comment(kind, _) => Some(kind),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn trailing_and_standalone_comments() {
+    assert_eq!(
+        comment_kinds("add // foo\n// foo\n/* b */ sub /* c */"),
+        [
+            CommentKind::Trailing,
+            CommentKind::Line,
+            CommentKind::Block,
+            CommentKind::Trailing
+        ]
+    );
+}
+
+#[test]
+fn comment_after_a_multiline_block_comment_is_not_trailing() {
+    assert_eq!(
+        comment_kinds("add /* a\n b */ // c\nsub"),
+        [CommentKind::Trailing, CommentKind::Line]
+    );
+    assert_eq!(
+        comment_kinds("/* a\r\n b */ sub // c"),
+        [CommentKind::Block, CommentKind::Trailing]
+    );
+}
+
+#[test]
+fn every_keyword_lexes_to_its_token() {
+    for (keyword, token) in KEYWORDS_MAP.entries() {
+        // Data locations are only keywords inside abi declarations
+        let tokens = lex(&format!("#define function f({keyword}"));
+        assert_eq!(tokens[4].0, *token, "{keyword}");
+        assert_eq!(token.keyword(), Some(*keyword));
+        // Opcodes take precedence, so a keyword shadowed by one could never be lexed
+        assert!(OPCODES_MAP.get(keyword).is_none(), "{keyword}");
+    }
+}
+
+#[test]
+fn opcodes_are_not_keywords() {
+    assert_eq!(lex("return")[0].0, Token::Opcode(Opcode::Return));
+    assert_eq!(lex("returns")[0].0, Token::Returns);
+    assert_eq!(lex("returning")[0].0, Token::Ident("returning".to_string()));
+}
+
+#[test]
+fn unknown_builtins_are_reported_once() {
+    let (tokens, errors) = lexer().parse_recovery("__codesize(A) __notreal(A)");
+    let tokens: Vec<_> = tokens
+        .unwrap()
+        .into_iter()
+        .map(|(token, _)| token)
+        .collect();
+    assert_eq!(tokens[0], Token::BuiltinFunction("codesize".to_string()));
+    assert!(tokens.contains(&Token::Unknown("__notreal".to_string())));
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].span(), 14..23);
+
+    // The parser and lowering do not report the lexer's error again
+    let src = "#define macro MAIN() = takes(0) returns(0) { __notreal(A) __codesize(MAIN) }";
+    let messages: Vec<_> = chumsky_huff::diagnostics(src)
+        .into_iter()
+        .map(|d| d.message)
+        .collect();
+    assert_eq!(messages, ["Unknown builtin function `__notreal`"]);
+}
+
+#[test]
+fn arithmetic_operators_lex_outside_of_comments() {
+    let tokens: Vec<_> = lex("0x10 + 0x20 - 3 * 4 // a * comment\n/* - */")
+        .into_iter()
+        .map(|(token, _)| token)
+        .filter(|token| matches!(token, Token::Add | Token::Sub | Token::Mul))
+        .collect();
+    assert_eq!(tokens, [Token::Add, Token::Sub, Token::Mul]);
+
+    // A minus sign directly before a number is still an operator
+    let tokens: Vec<_> = lex("0x02 -1").into_iter().map(|(token, _)| token).collect();
+    assert_eq!(tokens[1], Token::Sub);
+}
+
+#[test]
+fn division_lexes_apart_from_comments() {
+    let tokens: Vec<_> = lex("0x6 / 0x2 // half\n/* c */ 0x1/0x2")
+        .into_iter()
+        .map(|(token, _)| token)
+        .filter(|token| !matches!(token, Token::Literal(_)))
+        .collect();
+    assert_eq!(
+        tokens,
+        [Token::Div, Token::Newline, Token::Div, Token::Newline]
+    );
+
+    let trivia: Vec<_> = trivia_lexer()
+        .parse("0x6/0x2 // c")
+        .unwrap()
+        .into_iter()
+        .map(|(token, _)| token)
+        .filter(|token| !matches!(token, Token::Literal(_)))
+        .collect();
+    assert_eq!(trivia.len(), 2);
+    assert_eq!(trivia[0], Token::Div);
+    assert!(matches!(trivia[1], Token::Comment(..)));
+}
+
+#[test]
+fn punctuation_displays_as_written() {
+    let tokens = [
+        (Token::Colon, ":"),
+        (Token::Comma, ","),
+        (Token::Assign, "="),
+        (Token::OpenParen, "("),
+        (Token::CloseParen, ")"),
+        (Token::OpenBracket, "["),
+        (Token::CloseBracket, "]"),
+        (Token::OpenBrace, "{"),
+        (Token::CloseBrace, "}"),
+        (Token::LeftAngle, "<"),
+        (Token::RightAngle, ">"),
+        (Token::Add, "+"),
+        (Token::Sub, "-"),
+        (Token::Mul, "*"),
+    ];
+    for (token, display) in tokens {
+        assert_eq!(token.to_string(), display);
+        // Displaying a token as written lets it be lexed back
+        assert_eq!(lex(display)[0].0, token);
+    }
+}
+
+#[test]
+fn comment_at_the_end_of_a_file_without_a_newline() {
+    for src in [
+        "#define constant A = 0x01 // trailing",
+        "#define constant A = 0x01\n// last",
+        "#define constant A = 0x01 //",
+    ] {
+        let tokens = lex(src);
+        assert_eq!(tokens.last().unwrap().0, Token::Newline, "{src}");
+        // The literal before the comment is kept
+        assert_eq!(tokens[4].1, 21..25, "{src}");
+        assert_eq!(chumsky_huff::parse_str(src).unwrap().len(), 1, "{src}");
+    }
+    assert_eq!(lex("// only").last().unwrap().0, Token::Newline);
+}
+
+#[test]
+fn tokens_are_categorised_for_highlighting() {
+    use chumsky_huff::{lexer::token::TokenCategory, utils::types::PrimitiveEVMType};
+
+    let categories = [
+        (Token::Macro, TokenCategory::Keyword),
+        (Token::Opcode(Opcode::Add), TokenCategory::Opcode),
+        (Token::Ident("a".to_string()), TokenCategory::Ident),
+        (Token::Comma, TokenCategory::Punctuation),
+        (Token::Add, TokenCategory::Operator),
+        (
+            Token::PrimitiveType(PrimitiveEVMType::Address),
+            TokenCategory::Type,
+        ),
+        (
+            Token::Comment(CommentKind::Line, "// a".to_string()),
+            TokenCategory::Comment,
+        ),
+    ];
+    for (token, category) in categories {
+        assert_eq!(token.category(), category, "{token}");
+    }
+    assert_eq!(lex("0x20")[0].0.category(), TokenCategory::Literal);
+}
+
+#[test]
+fn byte_order_mark_and_leading_whitespace_are_skipped() {
+    let src = "#define constant A = 0x01\n";
+    let significant = |tokens: Vec<(Token, std::ops::Range<usize>)>, offset: usize| -> Vec<_> {
+        tokens
+            .into_iter()
+            .filter(|(token, _)| *token != Token::Newline)
+            .map(|(token, span)| (token, span.start - offset..span.end - offset))
+            .collect()
+    };
+    let plain = significant(lex(src), 0);
+
+    for prefix in ["\u{FEFF}", "\u{FEFF}\n\n  \n", "  \n\t\n", "\u{FEFF}  "] {
+        let prefixed = lex(&format!("{prefix}{src}"));
+        assert_eq!(
+            significant(prefixed, prefix.chars().count()),
+            plain,
+            "{prefix:?}"
+        );
+    }
+}
+
+#[test]
+fn literals_must_be_separated_from_opcodes() {
+    let tokens = lex("push1 0x20");
+    assert_eq!(tokens[0].0, Token::Opcode(Opcode::Push1));
+    assert!(matches!(tokens[1].0, Token::Literal(_)));
+
+    let (tokens, errors) = lexer().parse_recovery("push10x20 add");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        chumsky_huff::error::Diagnostic::from_simple(&errors[0]).message,
+        "Expected whitespace between `push1` and `0x20`"
+    );
+    assert_eq!(errors[0].span(), 0..9);
+    assert_eq!(
+        tokens.unwrap()[0].0,
+        Token::Unknown("push10x20".to_string())
+    );
+    let src = "#define macro MAIN() = takes(0) returns(0) { push10x20 }";
+    assert_eq!(chumsky_huff::diagnostics(src).len(), 1);
+
+    // Identifiers that merely contain `0x` are unaffected
+    assert_eq!(lex("label0x")[0].0, Token::Ident("label0x".to_string()));
+}
+
+#[test]
+fn lossless_tokens_rebuild_the_source() {
+    let src = "\u{FEFF}#define  macro\tM() = takes(0) returns(0) { // hi\r\n    0x01 £ add /* c */\n}\n  ";
+    let tokens = lex_lossless(src);
+    let chars: Vec<char> = src.chars().collect();
+    let rebuilt: String = tokens
+        .iter()
+        .map(|(_, span)| chars[span.clone()].iter().collect::<String>())
+        .collect();
+    assert_eq!(rebuilt, src);
+
+    // Spans tile the source without gaps or overlaps
+    let mut end = 0;
+    for (_, span) in &tokens {
+        assert_eq!(span.start, end);
+        end = span.end;
+    }
+
+    assert!(tokens.iter().any(|(token, _)| *token == Token::Whitespace));
+    assert!(tokens
+        .iter()
+        .any(|(token, _)| *token == Token::Opcode(Opcode::Add)));
+    assert!(tokens
+        .iter()
+        .any(|(token, _)| *token == Token::Unknown("£".to_string())));
+    assert!(tokens
+        .iter()
+        .any(|(token, _)| matches!(token, Token::Comment(CommentKind::Trailing, _))));
+}
+
+#[test]
+fn lossless_comment_after_indentation_is_on_its_own_line() {
+    let tokens = lex_lossless("    // own line\n  push10x20");
+    assert!(matches!(tokens[1].0, Token::Comment(CommentKind::Line, _)));
+    assert_eq!(
+        tokens.last().unwrap().0,
+        Token::Unknown("push10x20".to_string())
+    );
+}
+
+#[test]
+fn opcodes_sharing_a_prefix_lex_as_whole_words() {
+    let tokens: Vec<_> = lex("gt sgt or origin add addmod push3 push32 oracle addr")
+        .into_iter()
+        .map(|(token, _)| token)
+        .collect();
+    assert_eq!(
+        tokens,
+        [
+            Token::Opcode(Opcode::Gt),
+            Token::Opcode(Opcode::Sgt),
+            Token::Opcode(Opcode::Or),
+            Token::Opcode(Opcode::Origin),
+            Token::Opcode(Opcode::Add),
+            Token::Opcode(Opcode::Addmod),
+            Token::Opcode(Opcode::Push3),
+            Token::Opcode(Opcode::Push32),
+            Token::Ident("oracle".to_string()),
+            Token::Ident("addr".to_string()),
+            Token::Newline,
+        ]
+    );
+}
+
+#[test]
+fn crlf_spans_point_at_the_same_text_as_lf() {
+    use chumsky_huff::span::offset_to_line_col;
+
+    let lf = "// header
+#define macro M() = takes(0) returns(0) { // c
+  0x01 add /* b */
+  label:
+}
+
+#define constant C = 0x02";
+    let crlf = lf.replace('\n', "\r\n");
+    // The text under a span, ignoring carriage returns
+    fn text(src: &str, span: &std::ops::Range<usize>) -> String {
+        src.chars()
+            .skip(span.start)
+            .take(span.len())
+            .filter(|c| *c != '\r')
+            .collect()
+    }
+
+    let (lf_tokens, crlf_tokens) = (lex(lf), lex(&crlf));
+    assert_eq!(lf_tokens.len(), crlf_tokens.len());
+    for ((lf_token, lf_span), (crlf_token, crlf_span)) in lf_tokens.iter().zip(&crlf_tokens) {
+        assert_eq!(lf_token, crlf_token);
+        assert_eq!(text(lf, lf_span), text(&crlf, crlf_span), "{lf_token:?}");
+        assert_eq!(
+            offset_to_line_col(lf, lf_span.start),
+            offset_to_line_col(&crlf, crlf_span.start),
+            "{lf_token:?}"
+        );
+    }
+
+    let lf_trivia = trivia_lexer().parse(lf).unwrap();
+    let crlf_trivia = trivia_lexer().parse(crlf.as_str()).unwrap();
+    assert_eq!(lf_trivia.len(), crlf_trivia.len());
+    for ((lf_token, lf_span), (crlf_token, crlf_span)) in lf_trivia.iter().zip(&crlf_trivia) {
+        assert_eq!(lf_token, crlf_token);
+        assert_eq!(text(lf, lf_span), text(&crlf, crlf_span), "{lf_token:?}");
+    }
+
+    // A lone carriage return still ends a line
+    let position = offset_to_line_col("a\rb", 2);
+    assert_eq!((position.line, position.col), (2, 1));
+    let position = offset_to_line_col("a\r\nb", 1);
+    assert_eq!((position.line, position.col), (1, 2));
+}
+
+#[test]
+fn numbered_opcodes_lex_as_one_token() {
+    for (src, opcode) in [
+        ("push1", Opcode::Push1),
+        ("push32", Opcode::Push32),
+        ("dup16", Opcode::Dup16),
+        ("swap16", Opcode::Swap16),
+        ("log4", Opcode::Log4),
+    ] {
+        let tokens = lex(src);
+        assert_eq!(tokens.len(), 2, "{src}");
+        assert_eq!(tokens[0], (Token::Opcode(opcode), 0..src.len()));
+    }
+
+    // Out of range numbers are identifiers, and a typo for an opcode in a macro body
+    for src in ["log5", "push33", "dup17", "swap0"] {
+        assert_eq!(lex(src)[0], (Token::Ident(src.to_string()), 0..src.len()));
+    }
+    let diagnostics =
+        chumsky_huff::diagnostics("#define macro MAIN() = takes(0) returns(0) { log5 }");
+    assert!(
+        diagnostics.iter().any(|d| d.message.contains("log4")),
+        "{diagnostics:?}"
+    );
+}
+
+#[test]
+fn abi_declarations_lex_types_and_names_rather_than_opcodes() {
+    use chumsky_huff::utils::types::PrimitiveEVMType;
+
+    let src = "#define function foo(address) view returns (uint256 gas)
+#define macro M() = takes(0) returns(0) { address gas }";
+    let tokens: Vec<_> = lex(src).into_iter().map(|(token, _)| token).collect();
+    assert!(tokens.contains(&Token::PrimitiveType(PrimitiveEVMType::Address)));
+    assert!(tokens.contains(&Token::Ident("gas".to_string())));
+    // The same words are opcodes in a macro body
+    assert!(tokens.contains(&Token::Opcode(Opcode::Address)));
+    assert!(tokens.contains(&Token::Opcode(Opcode::Gas)));
+    assert!(chumsky_huff::parse_str(src).is_ok());
+}
+
+#[test]
+fn sixty_four_hex_digits_lex_as_a_literal() {
+    let tokens = lex(&format!("0x{}", "f".repeat(64)));
+    assert_eq!(tokens[0].0, Token::Literal([0xff; 32]));
+    let tokens = lex(&format!("0x1{}", "f".repeat(64)));
+    assert_eq!(tokens[0].0, Token::Code(format!("1{}", "f".repeat(64))));
+}
+
+#[test]
+fn decimal_numbers_are_checked() {
+    const MAX: &str =
+        "115792089237316195423570985008687907853269984665640564039457584007913129639935";
+    const OVERFLOW: &str =
+        "115792089237316195423570985008687907853269984665640564039457584007913129639936";
+
+    assert_eq!(lex("push1 32")[1].0, Token::Num(32));
+    // Too large for a number, but not for a literal
+    assert_eq!(
+        lex(&format!("push32 {MAX}"))[1].0,
+        Token::Literal([0xff; 32])
+    );
+
+    let message = |src: &str| {
+        let (tokens, errors) = lexer().parse_recovery(src);
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        let start = src.find(' ').unwrap() + 1;
+        assert_eq!(errors[0].span(), start..src.len());
+        assert_eq!(
+            tokens.unwrap()[1].0,
+            Token::Unknown(src[start..].to_string())
+        );
+        chumsky_huff::error::Diagnostic::from_simple(&errors[0]).message
+    };
+    assert_eq!(
+        message("push1 1f"),
+        "Invalid decimal number `1f`, invalid digit `f` in literal"
+    );
+    assert_eq!(
+        message(&format!("push32 {OVERFLOW}"))[..],
+        format!("Invalid decimal number `{OVERFLOW}`, literal does not fit in 32 bytes")
+    );
+
+    // The parser does not report the lexer's error again
+    let src = "#define macro MAIN() = takes(0) returns(0) { push1 1f }";
+    assert_eq!(chumsky_huff::diagnostics(src).len(), 1);
+}
+
+#[test]
+fn every_lexer_applies_the_definition_context() {
+    let src = "#define function balance(address owner) view returns (uint256)
+#define /* a test */ test T() = takes(0) returns(0) {
+    address // the caller
+    memory: memory jump
+}";
+    let significant = |tokens: Vec<(Token, std::ops::Range<usize>)>| -> Vec<Token> {
+        tokens
+            .into_iter()
+            .map(|(token, _)| token)
+            .filter(|token| {
+                !matches!(
+                    token,
+                    Token::Comment(..) | Token::Whitespace | Token::Newline
+                )
+            })
+            .collect()
+    };
+
+    let expected = significant(lex(src));
+    assert!(expected.contains(&Token::Ident("balance".to_string())));
+    assert!(expected.contains(&Token::Test));
+    assert!(expected.contains(&Token::Opcode(Opcode::Address)));
+    assert!(expected.contains(&Token::Ident("memory".to_string())));
+
+    assert_eq!(significant(trivia_lexer().parse(src).unwrap()), expected);
+    assert_eq!(significant(lex_lossless(src)), expected);
+}
@@ -0,0 +1,596 @@
+use chumsky_huff::{
+    lowering::lower,
+    parse_str,
+    utils::{
+        ast::{Contract, Label, MacroArg, StatementType},
+        bytes_util::{literal_to_hex, str_to_bytes32},
+        opcodes::Opcode,
+    },
+};
+
+fn lower_src(src: &str) -> Contract {
+    let (contract, diagnostics) = lower(&parse_str(src).unwrap());
+    assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    contract
+}
+
+#[test]
+fn constant_reference_args_keep_their_brackets() {
+    let contract = lower_src(
+        "#define constant OWNER = 0x01
+#define macro FOO(a, b) = takes(0) returns(0) { <a> <b> }
+#define macro BAR(x) = takes(0) returns(0) { FOO([OWNER], <x>) }",
+    );
+    let bar = contract.find_macro_by_name("BAR").unwrap();
+    let StatementType::MacroInvocation(invocation) = &bar.statements[0].ty else {
+        panic!("expected an invocation");
+    };
+    assert_eq!(
+        invocation.args,
+        [
+            MacroArg::ConstantRef("OWNER".to_string()),
+            MacroArg::ArgCall("x".to_string())
+        ]
+    );
+    assert_eq!(bar.statements[0].ty.to_huff_source(), "FOO([OWNER], <x>)");
+}
+
+#[test]
+fn constant_references_lower_to_constant_statements() {
+    let contract = lower_src(
+        "#define constant OWNER = 0x01
+#define macro MAIN() = takes(0) returns(1) { [OWNER] sload }",
+    );
+    let main = contract.find_macro_by_name("MAIN").unwrap();
+    assert_eq!(
+        main.statements[0].ty,
+        StatementType::Constant("OWNER".to_string())
+    );
+    assert_eq!(main.statements[0].ty.to_huff_source(), "[OWNER]");
+}
+
+#[test]
+fn constant_aliases_resolve_to_their_value() {
+    let src = "#define constant B = 0x05
+#define constant A = [B]
+#define constant X = [Y]
+#define constant Y = [X]
+#define constant Z = [NOPE]";
+    let (contract, diagnostics) = lower(&parse_str(src).unwrap());
+
+    let constants = contract.constants.lock().unwrap();
+    let names: Vec<_> = constants.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["B", "A"]);
+    assert_eq!(constants[1].value.as_u256_bytes().unwrap()[31], 5);
+
+    let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        [
+            "constant `X` is defined in terms of itself: X -> Y -> X",
+            "constant `Y` is defined in terms of itself: Y -> X -> Y",
+            "constant `Z` references undefined constant `NOPE`",
+        ]
+    );
+}
+
+#[test]
+fn constant_expressions_fold_with_wrapping_arithmetic() {
+    let src = format!(
+        "#define constant A = 0x10 + 0x20
+#define constant B = 0x00 - 0x01
+#define constant C = 0x02 + 0x03 * 0x04 - 0x01
+#define constant D = 0x{} * 0x20",
+        "f".repeat(64)
+    );
+    let contract = lower_src(&src);
+    let values: Vec<_> = contract
+        .constants
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|c| literal_to_hex(&c.value.as_u256_bytes().unwrap(), true, true))
+        .collect();
+    assert_eq!(
+        values,
+        [
+            "0x30".to_string(),
+            format!("0x{}", "f".repeat(64)),
+            "0xd".to_string(),
+            format!("0x{}e0", "f".repeat(62)),
+        ]
+    );
+}
+
+#[test]
+fn division_rounds_down_and_binds_like_multiplication() {
+    let contract = lower_src(
+        "#define constant A = 0x6 / 0x2
+#define constant B = 0x7 / 0x2
+#define constant C = 0x1 + 0x8 / 0x2 * 0x3",
+    );
+    let values: Vec<_> = contract
+        .constants
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|c| literal_to_hex(&c.value.as_u256_bytes().unwrap(), true, true))
+        .collect();
+    assert_eq!(values, ["0x3", "0x3", "0xd"]);
+}
+
+#[test]
+fn duplicate_constants_follow_the_strategy() {
+    use chumsky_huff::lowering::{lower_with_options, DuplicateStrategy, LowerOptions};
+
+    let ast = parse_str(
+        "#define constant A = 0x01
+#define constant B = 0x03
+#define constant A = 0x02",
+    )
+    .unwrap();
+    let lower_a = |on_duplicate| {
+        let (contract, diagnostics) = lower_with_options(&ast, &LowerOptions { on_duplicate });
+        let constants = contract.constants.lock().unwrap();
+        assert_eq!(constants.len(), 2);
+        let a = constants.iter().find(|c| c.name == "A").unwrap();
+        let messages: Vec<_> = diagnostics.into_iter().map(|d| d.message).collect();
+        (a.value.as_u256_bytes().unwrap()[31], messages)
+    };
+
+    assert_eq!(lower_a(DuplicateStrategy::KeepFirst), (1, vec![]));
+    assert_eq!(lower_a(DuplicateStrategy::KeepLast), (2, vec![]));
+    let (value, messages) = lower_a(DuplicateStrategy::Error);
+    assert_eq!(value, 1);
+    assert_eq!(messages, ["constant `A` is defined more than once"]);
+}
+
+#[test]
+fn huff_source_round_trips_through_lowering() {
+    let contract = lower_src(
+        r#"
+#define constant A = 0x20
+#define constant SLOT = FREE_STORAGE_POINTER()
+#define function transfer(address to, uint256) nonpayable returns (bool)
+#define event Transfer(address indexed from, uint256 value)
+#define error Oops(string memory reason)
+#define jumptable T() = { a b }
+#define macro INNER(x) = takes (0) returns (1) { <x> }
+#define macro MAIN() = takes (0) returns (0) {
+    INNER([A]) __FUNC_SIG(transfer) [SLOT] a: 0x01 jump b:
+}
+"#,
+    );
+    let again = lower_src(&contract.to_huff_source());
+
+    let macros = |contract: &Contract| -> Vec<_> {
+        contract
+            .macros
+            .iter()
+            .map(|m| {
+                let statements: Vec<_> = m.statements.iter().map(|s| s.ty.clone()).collect();
+                (m.name.clone(), statements)
+            })
+            .collect()
+    };
+    let tables = |contract: &Contract| -> Vec<_> {
+        contract
+            .tables
+            .iter()
+            .map(|t| (t.name.clone(), t.size))
+            .collect()
+    };
+    assert_eq!(macros(&again), macros(&contract));
+    assert_eq!(tables(&again), tables(&contract));
+    assert_eq!(again.functions, contract.functions);
+    assert_eq!(again.events, contract.events);
+    assert_eq!(again.errors, contract.errors);
+    assert_eq!(
+        *again.constants.lock().unwrap(),
+        *contract.constants.lock().unwrap()
+    );
+}
+
+#[test]
+fn tests_and_decorators_round_trip_through_huff_source() {
+    let contract = lower_src(
+        r#"
+#define macro MAIN() = takes (0) returns (0) { 0x01 }
+#[calldata("0xdeadbeef"), value(0x01)]
+#define test PAYS() = takes (0) returns (0) { callvalue }
+#define test PLAIN() = takes (0) returns (0) { MAIN() }
+"#,
+    );
+    let source = contract.to_huff_source();
+    assert!(
+        source.contains(
+            "#[calldata(\"0xdeadbeef\"), value(0x1)]\n#define test PAYS() = takes (0) returns (0)"
+        ),
+        "{source}"
+    );
+
+    let again = lower_src(&source);
+    let tests = |contract: &Contract| -> Vec<_> {
+        contract
+            .macros
+            .iter()
+            .map(|m| (m.name.clone(), m.test, m.decorator.clone()))
+            .collect()
+    };
+    let lowered = tests(&again);
+    assert_eq!(lowered, tests(&contract));
+    assert!(lowered[1].1 && lowered[1].2.is_some());
+    assert_eq!(lowered[2], ("PLAIN".to_string(), true, None));
+}
+
+#[test]
+fn sizeless_push_lowers_to_the_smallest_push() {
+    let contract = lower_src(
+        "#define macro MAIN() = takes (0) returns (0) { push 0x20 push 0x0100 push 0x00 push: push jump }",
+    );
+    let statements: Vec<_> = contract.macros[0]
+        .statements
+        .iter()
+        .map(|s| s.ty.clone())
+        .collect();
+    assert_eq!(
+        statements,
+        [
+            StatementType::Opcode(Opcode::Push1),
+            StatementType::Literal(str_to_bytes32("20").unwrap()),
+            StatementType::Opcode(Opcode::Push2),
+            StatementType::Literal(str_to_bytes32("0100").unwrap()),
+            StatementType::Opcode(Opcode::Push1),
+            StatementType::Literal(str_to_bytes32("00").unwrap()),
+            // Without a literal `push` is a label
+            StatementType::Label(Label {
+                name: "push".to_string(),
+                inner: vec![]
+            }),
+            StatementType::LabelCall("push".to_string()),
+            StatementType::Opcode(Opcode::Jump),
+        ]
+    );
+}
+
+#[test]
+fn calldata_bytes_keep_their_location_in_interfaces() {
+    use chumsky_huff::{
+        parser::Ast,
+        utils::{
+            abi::{FunctionParam, FunctionParamType},
+            ast::ArgumentLocation,
+        },
+    };
+
+    let src = "#define function f(bytes calldata data, string s, uint256 x) view returns (bytes)
+#define event E(bytes indexed b)
+#define error Bad(string why)";
+    let ast = parse_str(src).unwrap();
+    let Ast::AbiFunction(function) = &ast[0].0 else {
+        panic!("expected a function, found {:?}", ast[0].0);
+    };
+    assert_eq!(
+        function.inputs[0].0,
+        FunctionParam {
+            name: "data".to_string(),
+            kind: FunctionParamType::Bytes,
+            internal_type: None,
+            location: Some(ArgumentLocation::Calldata),
+        }
+    );
+
+    let interface = lower_src(src).to_solidity_interface("IFoo");
+    assert!(interface.contains(
+        "function f(bytes calldata data, string memory s, uint256 x) external view returns (bytes memory);"
+    ));
+    assert!(interface.contains("event E(bytes indexed b);"));
+    assert!(interface.contains("error Bad(string why);"));
+}
+
+#[test]
+fn statement_spans_survive_lowering() {
+    let src = "#define macro MAIN() = takes (0) returns (0) {\n    caller\n    0x01 add\n    push 0x02\n}";
+    let contract = lower_src(src);
+    let spans: Vec<_> = contract.macros[0]
+        .statements
+        .iter()
+        .map(|s| &src[s.span.clone()])
+        .collect();
+    // Both statements of a sizeless push share its span
+    assert_eq!(spans, ["caller", "0x01", "add", "push 0x02", "push 0x02"]);
+}
+
+#[test]
+fn merging_contracts_reports_collisions() {
+    use chumsky_huff::{error::MergeError, lowering::DuplicateStrategy, utils::ast::ConstVal};
+
+    let mut contract = lower_src(
+        "#define constant A = 0x01
+#define macro MAIN() = takes (0) returns (0) { INNER() }
+#define macro INNER() = takes (0) returns (0) { stop }",
+    );
+    let other = lower_src(
+        "#define constant B = 0x02
+#define macro OTHER() = takes (0) returns (0) { INNER() }",
+    );
+    contract.merge(other).unwrap();
+    assert_eq!(contract.macros.len(), 3);
+    assert_eq!(contract.constants.lock().unwrap().len(), 2);
+    assert_eq!(contract.invocations.len(), 2);
+
+    let colliding = lower_src(
+        "#define constant A = 0x03
+#define macro MAIN() = takes (0) returns (0) { caller }",
+    );
+    assert_eq!(
+        contract.merge(colliding.clone()).unwrap_err(),
+        MergeError {
+            collisions: vec![("macro", "MAIN".to_string()), ("constant", "A".to_string())]
+        }
+    );
+    // Nothing is merged when there is a collision
+    assert_eq!(contract.macros.len(), 3);
+
+    contract
+        .merge_with_strategy(colliding, DuplicateStrategy::KeepLast)
+        .unwrap();
+    assert_eq!(contract.macros.len(), 3);
+    // The invocation in the replaced `MAIN` is gone
+    assert_eq!(contract.invocations.len(), 1);
+    assert_eq!(
+        contract.constants.lock().unwrap()[0].value,
+        ConstVal::Literal(str_to_bytes32("03").unwrap())
+    );
+
+    // Merging a contract that shares its constants must not deadlock
+    let shared = contract.clone();
+    contract
+        .merge_with_strategy(shared, DuplicateStrategy::KeepFirst)
+        .unwrap();
+    assert_eq!(contract.constants.lock().unwrap().len(), 2);
+}
+
+#[test]
+fn tuple_event_topics_hash_the_canonical_signature() {
+    use chumsky_huff::{parser::Ast, utils::abi::FunctionParamType};
+
+    let src = "#define event Swap((uint256,uint256) amounts, (address,(bool,bytes32))[] nested)
+#define function f((uint256,address) p) view returns (uint256)";
+    let ast = parse_str(src).unwrap();
+    let Ast::AbiEvent(event) = &ast[0].0 else {
+        panic!("expected an event, found {:?}", ast[0].0);
+    };
+    assert_eq!(event.inputs[0].0.name, "amounts");
+    assert_eq!(
+        event.inputs[0].0.kind,
+        FunctionParamType::Tuple(vec![
+            FunctionParamType::Uint(256),
+            FunctionParamType::Uint(256)
+        ])
+    );
+
+    let contract = lower_src(src);
+    let signature = contract.events[0].canonical_signature();
+    assert_eq!(
+        signature,
+        "Swap((uint256,uint256),(address,(bool,bytes32))[])"
+    );
+    assert_eq!(
+        contract.events[0].hash,
+        ethers_core::utils::keccak256(signature.as_bytes())
+    );
+    assert_eq!(
+        contract.functions[0].canonical_signature(),
+        "f((uint256,address))"
+    );
+}
+
+#[test]
+fn literal_histogram_counts_repeated_literals() {
+    use chumsky_huff::utils::ast::literal_histogram;
+
+    let contract = lower_src(
+        "#define macro A() = takes (0) returns (0) { 0x20 0x00 mstore 0x20 }
+#define macro B() = takes (0) returns (0) { push 0x20 }",
+    );
+    let histogram = literal_histogram(&contract);
+    let counts: Vec<_> = histogram
+        .iter()
+        .map(|(literal, count)| (literal_to_hex(literal, true, true), *count))
+        .collect();
+    assert_eq!(counts, [("0x0".to_string(), 1), ("0x20".to_string(), 3)]);
+}
+
+#[test]
+fn macro_statements_are_listed_and_displayed() {
+    use chumsky_huff::utils::ast::{macro_statements, macro_statements_pretty};
+
+    let contract = lower_src(
+        "#define macro A() = takes (0) returns (0) { 0x20 B() done: caller }
+#define macro B() = takes (0) returns (0) { stop }",
+    );
+    let statements = macro_statements(&contract, "A").unwrap();
+    assert_eq!(statements.len(), 4);
+    assert!(matches!(statements[1], StatementType::MacroInvocation(_)));
+    assert_eq!(
+        macro_statements_pretty(&contract, "A").unwrap(),
+        "LITERAL: 0x20\nMACRO INVOCATION: B\nLABEL: done\nOPCODE: 33"
+    );
+    assert!(macro_statements(&contract, "C").is_none());
+}
+
+#[test]
+fn contract_stats_count_definitions_and_opcodes() {
+    use chumsky_huff::utils::ast::{stats, ContractStats};
+
+    let contract = lower_src(
+        "#define function f(uint256) view returns (uint256)
+#define event E(uint256)
+#define constant C = 0x01
+#define constant D = 0x02
+#define jumptable T() = { lbl }
+#define fn F() = takes(0) returns(0) { add }
+#define macro MAIN() = takes(0) returns(0) { 0x01 0x02 add lbl: add pop stop }",
+    );
+    assert_eq!(
+        stats(&contract),
+        ContractStats {
+            macros: 1,
+            fns: 1,
+            constants: 2,
+            tables: 1,
+            functions: 1,
+            events: 1,
+            errors: 0,
+            opcodes: 5,
+            distinct_opcodes: 3,
+        }
+    );
+}
+
+#[test]
+fn constructor_signature_lists_the_input_types() {
+    use chumsky_huff::utils::ast::constructor_signature;
+
+    let contract = lower_src(
+        "#define constructor(address owner, uint256[] memory amounts)
+#define macro MAIN() = takes(0) returns(0) {}",
+    );
+    assert_eq!(
+        constructor_signature(&contract).as_deref(),
+        Some("(address,uint256[])")
+    );
+    assert!(contract
+        .to_huff_source()
+        .contains("#define constructor(address owner, uint256[] memory amounts)"));
+
+    let mut merged = lower_src("#define macro OTHER() = takes(0) returns(0) {}");
+    assert_eq!(constructor_signature(&merged), None);
+    merged.merge(contract.clone()).unwrap();
+    assert!(merged.constructor.is_some());
+
+    // Two constructors can't be merged into one contract
+    let mut both = contract.clone();
+    assert!(both.merge(contract).is_err());
+}
+
+#[test]
+fn builtin_calls_resolve_their_args() {
+    use chumsky_huff::utils::ast::{builtin_calls, BuiltinArg, BuiltinFunctionKind};
+
+    let src = "#define function transfer(address,uint256) nonpayable returns ()
+#define jumptable T() = { a }
+#define macro MAIN() = takes(0) returns(0) {
+    __tablesize(T) a: __FUNC_SIG(transfer) __FUNC_SIG(missing)
+}";
+    let (contract, _) = lower(&parse_str(src).unwrap());
+    let calls = builtin_calls(&contract);
+    assert_eq!(calls.len(), 3);
+    assert_eq!(calls[0].0, BuiltinFunctionKind::Tablesize);
+    assert_eq!(calls[0].1, [BuiltinArg::Table("T".to_string())]);
+    assert_eq!(&src[calls[0].2.clone()], "__tablesize(T)");
+    // Calls nested inside a label are listed too
+    assert_eq!(calls[1].0, BuiltinFunctionKind::FunctionSignature);
+    assert_eq!(calls[1].1, [BuiltinArg::Function("transfer".to_string())]);
+    assert_eq!(calls[2].1, [BuiltinArg::Unresolved("missing".to_string())]);
+}
+
+#[test]
+fn builtin_calls_keep_literal_args() {
+    use chumsky_huff::utils::ast::{builtin_calls, BuiltinArg, BuiltinFunctionKind};
+
+    let src = "#define macro MAIN() = takes(0) returns(0) {
+    __RIGHTPAD(0x01) __tablesize(0x20)
+}";
+    let (contract, _) = lower(&parse_str(src).unwrap());
+    let calls = builtin_calls(&contract);
+    assert_eq!(calls.len(), 2);
+    assert_eq!(calls[0].0, BuiltinFunctionKind::RightPad);
+    assert_eq!(
+        calls[0].1,
+        [BuiltinArg::Literal(str_to_bytes32("01").unwrap())]
+    );
+    // Literals are kept whatever the builtin expects
+    assert_eq!(
+        calls[1].1,
+        [BuiltinArg::Literal(str_to_bytes32("20").unwrap())]
+    );
+}
+
+#[test]
+fn packed_tables_lay_out_two_byte_entries() {
+    let src = "#define jumptablepacked P { a b c }
+#define jumptable J { a b }
+#define codetable C { 0x0102 }
+#define macro MAIN() = takes(0) returns(0) { a: b: c: }";
+    let contract = lower_src(src);
+
+    let packed = contract.find_table_by_name("P").unwrap();
+    let entries = packed.jump_entries();
+    let layout: Vec<_> = entries
+        .iter()
+        .map(|entry| (entry.label.as_str(), entry.offset, entry.size))
+        .collect();
+    assert_eq!(layout, [("a", 0, 2), ("b", 2, 2), ("c", 4, 2)]);
+    assert_eq!(&src[entries[1].span.clone()], "b");
+    assert_eq!(packed.size[31], 6);
+
+    let full = contract.find_table_by_name("J").unwrap();
+    let layout: Vec<_> = full
+        .jump_entries()
+        .iter()
+        .map(|entry| (entry.offset, entry.size))
+        .collect();
+    assert_eq!(layout, [(0, 32), (32, 32)]);
+    assert_eq!(full.size[31], 64);
+
+    let code = contract.find_table_by_name("C").unwrap();
+    assert!(code.jump_entries().is_empty());
+    assert_eq!(code.size[31], 2);
+}
+
+#[test]
+fn bare_push_is_sized_to_its_literal() {
+    let contract =
+        lower_src("#define macro M() = takes(0) returns(0) { push 0x0100 push 0x00 push 0xff }");
+    let opcodes: Vec<_> = contract
+        .find_macro_by_name("M")
+        .unwrap()
+        .statements
+        .iter()
+        .filter_map(|statement| match statement.ty {
+            StatementType::Opcode(opcode) => Some(opcode),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(opcodes, [Opcode::Push2, Opcode::Push1, Opcode::Push1]);
+}
+
+#[test]
+fn builtin_args_keep_literals_and_numbers() {
+    let contract = lower_src(
+        "#define jumptable T { lbl }
+#define macro MAIN() = takes(0) returns(0) { __RIGHTPAD(0x01) __tablesize(32) __tablesize(T) lbl: }",
+    );
+    let calls: Vec<_> = contract.macros[0]
+        .statements
+        .iter()
+        .filter_map(|statement| match &statement.ty {
+            StatementType::BuiltinFunctionCall(call) => Some(call.args.clone()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        calls,
+        [
+            vec![MacroArg::Literal(str_to_bytes32("01").unwrap())],
+            vec![MacroArg::Literal(str_to_bytes32("20").unwrap())],
+            vec![MacroArg::Ident("T".to_string())],
+        ]
+    );
+
+    let source = contract.to_huff_source();
+    assert!(source.contains("__RIGHTPAD(0x1)"), "{source}");
+    assert!(source.contains("__tablesize(0x20)"), "{source}");
+}
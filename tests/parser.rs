@@ -0,0 +1,660 @@
+use chumsky_huff::{
+    error::ChuffError,
+    parse_str,
+    parser::{Arg, Ast, ConstantValue, MacroBody},
+    utils::opcodes::Opcode,
+};
+
+fn parse_error_messages(src: &str) -> Vec<String> {
+    match parse_str(src) {
+        Err(ChuffError::Parse(errors)) => errors
+            .iter()
+            .map(|error| chumsky_huff::error::Diagnostic::from_simple(error).message)
+            .collect(),
+        other => panic!("expected parse errors, found {other:?}"),
+    }
+}
+
+#[test]
+fn division_by_zero_in_a_constant_is_an_error() {
+    assert_eq!(
+        parse_error_messages("#define constant C = 0x6 / 0x0"),
+        ["Division by zero in constant expression"]
+    );
+}
+
+#[test]
+fn indexed_after_the_name_is_an_error() {
+    assert_eq!(
+        parse_error_messages("#define event A(uint256 a indexed, uint256 b)"),
+        ["`indexed` must be placed between the parameter type and name"]
+    );
+
+    let ast = parse_str("#define event A(uint256 indexed a, uint256 b)").unwrap();
+    let Ast::AbiEvent(event) = &ast[0].0 else {
+        panic!("expected an event, found {:?}", ast[0].0);
+    };
+    let indexed: Vec<_> = event.inputs.iter().map(|(p, _)| p.indexed).collect();
+    assert_eq!(indexed, [true, false]);
+}
+
+/// The statements of the macro with the given name
+fn macro_body(ast: &[chumsky_huff::span::Spanned<Ast>], name: &str) -> Vec<MacroBody> {
+    ast.iter()
+        .find_map(|(item, _)| match item {
+            Ast::MacroDefinition {
+                name: defined,
+                statements,
+                ..
+            } if defined == name => Some(statements.iter().map(|(s, _)| s.clone()).collect()),
+            _ => None,
+        })
+        .unwrap_or_else(|| panic!("no macro named {name}"))
+}
+
+#[test]
+fn constant_and_arg_references_are_distinct_args() {
+    let src = "#define macro BAR(x) = takes(0) returns(0) { FOO([OWNER], <x>) }";
+    let ast = parse_str(src).unwrap();
+    let [MacroBody::MacroInvocation { name, args }] = &macro_body(&ast, "BAR")[..] else {
+        panic!("expected a single invocation");
+    };
+    assert_eq!(name, "FOO");
+    let args: Vec<_> = args.iter().map(|(arg, _)| arg.clone()).collect();
+    assert_eq!(
+        args,
+        [
+            Arg::ConstantRef(("OWNER".to_string(), 50..55)),
+            Arg::ArgRef(("x".to_string(), 59..60))
+        ]
+    );
+}
+
+/// The value of the constant with the given name
+fn constant_value(ast: &[chumsky_huff::span::Spanned<Ast>], name: &str) -> ConstantValue {
+    ast.iter()
+        .find_map(|(item, _)| match item {
+            Ast::ConstantDefinition {
+                name: defined,
+                value,
+                ..
+            } if defined == name => Some(value.clone()),
+            _ => None,
+        })
+        .unwrap_or_else(|| panic!("no constant named {name}"))
+}
+
+#[test]
+fn constant_literals_convert_to_bytes() {
+    let ast = parse_str(
+        "#define constant A = 0x0100
+#define constant B = FREE_STORAGE_POINTER()",
+    )
+    .unwrap();
+
+    let bytes = constant_value(&ast, "A").as_u256_bytes().unwrap();
+    assert_eq!(bytes[..30], [0; 30]);
+    assert_eq!(bytes[30..], [0x01, 0x00]);
+    #[cfg(feature = "u256")]
+    assert_eq!(constant_value(&ast, "A").as_u256().unwrap().as_u64(), 256);
+
+    assert_eq!(constant_value(&ast, "B").as_u256_bytes(), None);
+}
+
+#[test]
+fn returns_accepts_an_empty_or_single_stack_size() {
+    for (annotation, size) in [("returns ()", 0), ("returns (2)", 2)] {
+        let src = format!("#define macro A() = takes(1) {annotation} {{ }}");
+        let ast = parse_str(&src).unwrap();
+        let Ast::MacroDefinition { returns, .. } = &ast[0].0 else {
+            panic!("expected a macro, found {:?}", ast[0].0);
+        };
+        assert_eq!(returns.0, size, "{annotation}");
+    }
+}
+
+#[test]
+fn returns_rejects_several_stack_sizes() {
+    let messages = parse_error_messages("#define macro A() = takes(0) returns (1,2) { }");
+    assert_eq!(messages.len(), 1);
+    assert!(
+        messages[0].contains("`returns` expects a single stack size"),
+        "{messages:?}"
+    );
+}
+
+#[test]
+fn constant_references_parse_in_macro_bodies() {
+    let ast = parse_str("#define macro MAIN() = takes(0) returns(1) { [OWNER] sload }").unwrap();
+    assert_eq!(
+        macro_body(&ast, "MAIN"),
+        [
+            MacroBody::ConstantRef(("OWNER".to_string(), 46..51)),
+            MacroBody::Opcode(Opcode::Sload)
+        ]
+    );
+}
+
+#[test]
+fn macros_without_stack_annotations_default_to_zero() {
+    let src = "#define macro MAIN() = {\n    0x01 pop\n}";
+    let ast = parse_str(src).unwrap();
+    let Ast::MacroDefinition { takes, returns, .. } = &ast[0].0 else {
+        panic!("expected a macro, found {:?}", ast[0].0);
+    };
+    assert_eq!(takes.0, 0);
+    assert_eq!(returns.0, 0);
+    // The missing annotations point at the macro name
+    assert_eq!(&src[takes.1.clone()], "MAIN");
+    assert_eq!(&src[returns.1.clone()], "MAIN");
+    assert_eq!(macro_body(&ast, "MAIN").len(), 2);
+}
+
+#[test]
+fn abi_inputs_parse_from_a_token_slice() {
+    use chumsky::{prelude::end, Parser};
+    use chumsky_huff::{
+        lexer::token::Token, parser::components::parse_abi_inputs, utils::types::PrimitiveEVMType,
+    };
+
+    let tokens = vec![
+        Token::PrimitiveType(PrimitiveEVMType::Address),
+        Token::Ident("to".to_string()),
+        Token::Comma,
+        Token::PrimitiveType(PrimitiveEVMType::Uint(256)),
+    ];
+    let params = parse_abi_inputs().then_ignore(end()).parse(tokens).unwrap();
+    let names: Vec<_> = params.iter().map(|(p, _)| p.name.as_str()).collect();
+    assert_eq!(names, ["to", ""]);
+    // Spans are token indices when parsing a slice
+    assert_eq!(params[1].1, 3..4);
+}
+
+#[test]
+fn missing_names_are_reported_at_the_next_token() {
+    for (src, span) in [
+        ("#define error (uint256)", 14..15),
+        ("#define function (uint256) view returns (uint256)", 17..18),
+        ("#define constant = 0x01", 17..18),
+    ] {
+        let Err(ChuffError::Parse(errors)) = parse_str(src) else {
+            panic!("expected a parse error for {src}");
+        };
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert_eq!(errors[0].span(), span, "{src}");
+    }
+}
+
+#[test]
+fn unclosed_delimiters_point_at_the_opening_delimiter() {
+    let src = "#define macro MAIN() = takes (0) returns (0) {\n 0x01 add\n\n#define macro B() = takes (0) returns (0) { 0x01 }";
+    let diagnostics = chumsky_huff::diagnostics(src);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].message, "Unclosed delimiter `{`");
+    assert_eq!(diagnostics[0].span, 45..46);
+
+    let src = "#define macro MAIN() = takes (0) returns (0) { 0x01 ) }";
+    let diagnostics = chumsky_huff::diagnostics(src);
+    assert_eq!(
+        diagnostics[0].message,
+        "Unclosed delimiter `{` opened at 45..46, found `)`"
+    );
+    assert_eq!(&src[diagnostics[0].span.clone()], ")");
+    assert_eq!(diagnostics[0].related.len(), 1);
+    assert_eq!(diagnostics[0].related[0], 45..46);
+}
+
+#[test]
+fn keywords_and_opcodes_can_name_labels() {
+    let src =
+        "#define macro A() = takes (0) returns (0) { 0x00 0x00 return\n return:\n stop\n macro: }";
+    let ast = parse_str(src).unwrap();
+    assert_eq!(
+        macro_body(&ast, "A")[2..],
+        [
+            MacroBody::Opcode(Opcode::Return),
+            MacroBody::JumpLabelDest("return".to_string()),
+            MacroBody::Opcode(Opcode::Stop),
+            MacroBody::JumpLabelDest("macro".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn definitions_sort_by_kind_then_name() {
+    let mut ast = parse_str(
+        "#define macro B() = takes(0) returns(0) {}
+#define constant Z = 0x01
+#define macro A() = takes(0) returns(0) {}
+#define constant Y = 0x02
+#include \"x.huff\"",
+    )
+    .unwrap();
+    ast.sort_by(|(a, _), (b, _)| a.sort_key().cmp(&b.sort_key()));
+    let keys: Vec<_> = ast.iter().map(|(item, _)| item.sort_key()).collect();
+    assert_eq!(
+        keys,
+        [(0, "x.huff"), (1, "Y"), (1, "Z"), (7, "A"), (7, "B")]
+    );
+}
+
+#[test]
+fn strict_abi_commas_reject_trailing_and_missing_commas() {
+    use chumsky_huff::{parse_str_with_options, parser::ParserOptions};
+
+    let strict = ParserOptions {
+        strict_abi_commas: true,
+        ..Default::default()
+    };
+
+    let trailing = "#define function f(uint256 a, address b,) view returns (uint256)";
+    assert!(parse_str(trailing).is_ok());
+    assert_eq!(
+        parse_str_with_options(trailing, strict)
+            .unwrap_err()
+            .to_string(),
+        "1 error(s) while parsing: Unexpected trailing `,`"
+    );
+
+    let missing = "#define error E(uint256 a address b)";
+    assert!(parse_str(missing).is_ok());
+    assert_eq!(
+        parse_str_with_options(missing, strict)
+            .unwrap_err()
+            .to_string(),
+        "1 error(s) while parsing: Expected `,` between parameters"
+    );
+
+    let event = "#define event T(address indexed a, uint256,)";
+    assert!(parse_str_with_options(event, strict).is_err());
+
+    let clean = "#define function f(uint256 a, address b) view returns (uint256)
+#define event T(address indexed a, uint256)";
+    assert!(parse_str_with_options(clean, strict).is_ok());
+}
+
+#[test]
+fn opcodes_are_valid_invocation_args() {
+    let ast =
+        parse_str("#define macro MAIN() = takes(0) returns(0) { FOO(add, x) __FUNC_SIG(stop) }")
+            .unwrap();
+    let body = macro_body(&ast, "MAIN");
+
+    let MacroBody::MacroInvocation { args, .. } = &body[0] else {
+        panic!("expected an invocation, found {:?}", body[0]);
+    };
+    let args: Vec<_> = args.iter().map(|(arg, _)| arg.clone()).collect();
+    assert_eq!(
+        args,
+        [Arg::Valid("add".to_string()), Arg::Valid("x".to_string())]
+    );
+
+    let MacroBody::BuiltinInvocation { args, .. } = &body[1] else {
+        panic!("expected a builtin, found {:?}", body[1]);
+    };
+    assert_eq!(args[0].0, Arg::Valid("stop".to_string()));
+}
+
+#[test]
+fn free_storage_pointer_in_a_macro_body_is_an_error() {
+    let src = "#define macro MAIN() = takes(0) returns(0) { 0x01 FREE_STORAGE_POINTER() add }";
+    assert_eq!(
+        parse_error_messages(src),
+        ["FREE_STORAGE_POINTER is only valid in constant definitions"]
+    );
+
+    // The rest of the body still parses around the misplaced pointer
+    let diagnostics = chumsky_huff::diagnostics(src);
+    assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+    assert_eq!(&src[diagnostics[0].span.clone()], "FREE_STORAGE_POINTER()");
+
+    assert!(parse_str("#define constant S = FREE_STORAGE_POINTER()").is_ok());
+}
+
+#[test]
+fn macro_and_builtin_invocations_accept_every_arg_kind() {
+    let src = "#define macro M(a) = takes(0) returns(0) {
+    FOO(x, 12, 0x20, [C], <a>, add)
+    __tablesize(x, 12, 0x20, [C], <a>, add)
+}";
+    let ast = parse_str(src).unwrap();
+    let mut literal = [0u8; 32];
+    literal[31] = 0x20;
+    // References keep the span of their name, which differs between the two invocations
+    let expected = |invocation: &str| {
+        let line = src.find(invocation).unwrap();
+        // The name inside the brackets of a reference
+        let at = |reference: &str| {
+            let start = line + src[line..].find(reference).unwrap() + 1;
+            start..start + reference.len() - 2
+        };
+        [
+            Arg::Valid("x".to_string()),
+            Arg::Number(12),
+            Arg::Literal(literal),
+            Arg::ConstantRef(("C".to_string(), at("[C]"))),
+            Arg::ArgRef(("a".to_string(), at("<a>"))),
+            Arg::Valid("add".to_string()),
+        ]
+    };
+
+    let statements = macro_body(&ast, "M");
+    assert_eq!(statements.len(), 2);
+    for (statement, invocation) in statements.into_iter().zip(["FOO", "__tablesize"]) {
+        let args = match statement {
+            MacroBody::MacroInvocation { args, .. } | MacroBody::BuiltinInvocation { args, .. } => {
+                args
+            }
+            other => panic!("expected an invocation, found {other:?}"),
+        };
+        let args: Vec<_> = args.into_iter().map(|(arg, _)| arg).collect();
+        assert_eq!(args, expected(invocation));
+    }
+
+    // Definitions still only accept names
+    let ast = parse_str("#define macro M(a, 12) = takes(0) returns(0) {}").unwrap();
+    let Ast::MacroDefinition { args, .. } = &ast[0].0 else {
+        panic!("expected a macro, found {:?}", ast[0].0);
+    };
+    assert_eq!(args[1].0, Arg::Invalid);
+}
+
+#[test]
+fn tables_parse_with_or_without_parens_and_assign() {
+    use chumsky_huff::{parser::TableStatements, utils::ast::TableKind};
+
+    fn table(src: &str) -> (String, TableKind, Vec<TableStatements>) {
+        let ast = parse_str(src).unwrap();
+        let Ast::TableDefinition {
+            name,
+            kind,
+            statements,
+            ..
+        } = &ast[0].0
+        else {
+            panic!("expected a table, found {:?}", ast[0].0);
+        };
+        let statements = statements.iter().map(|(s, _)| s.clone()).collect();
+        (name.clone(), kind.clone(), statements)
+    }
+
+    let expected = table("#define jumptable T() = { a b }");
+    assert_eq!(expected.2.len(), 2);
+    for src in [
+        "#define jumptable T { a b }",
+        "#define jumptable T() { a b }",
+        "#define jumptable T = { a b }",
+    ] {
+        assert_eq!(table(src), expected, "{src}");
+    }
+    assert_eq!(
+        table("#define codetable C { 0xdead }"),
+        table("#define codetable C() = { 0xdead }")
+    );
+}
+
+#[test]
+fn code_table_contents_are_normalized_bytes() {
+    use chumsky_huff::{
+        parse_str_with_options,
+        parser::{ParserOptions, TableStatements},
+    };
+
+    fn code(src: &str) -> String {
+        let ast = parse_str(src).unwrap();
+        let Ast::TableDefinition { statements, .. } = &ast[0].0 else {
+            panic!("expected a table, found {:?}", ast[0].0);
+        };
+        let TableStatements::Code(code) = &statements[0].0 else {
+            panic!("expected code, found {:?}", statements[0].0);
+        };
+        code.clone()
+    }
+
+    // Short contents lex as a literal, which keeps its leading zero bytes
+    assert_eq!(code("#define codetable T { 0x00010203 }"), "00010203");
+    assert_eq!(code("#define codetable T { 0x00 }"), "00");
+    assert_eq!(code("#define codetable T { 0xAbCd }"), "abcd");
+    // 64 digits or more lex as raw code, and come out in the same form
+    let short = "00".repeat(30) + "AB01";
+    let long = "00".repeat(31) + "AB01";
+    assert_eq!(
+        code(&format!("#define codetable T {{ 0x{short} }}")),
+        short.to_lowercase()
+    );
+    assert_eq!(
+        code(&format!("#define codetable T {{ 0x{long} }}")),
+        long.to_lowercase()
+    );
+
+    // Odd contents are padded on both paths, or rejected when strict
+    let odd_long = "1".repeat(65);
+    assert_eq!(code("#define codetable T { 0x123 }"), "0123");
+    assert_eq!(
+        code(&format!("#define codetable T {{ 0x{odd_long} }}")),
+        format!("0{odd_long}")
+    );
+    let strict = ParserOptions {
+        strict_code_tables: true,
+        ..Default::default()
+    };
+    let err = parse_str_with_options("#define codetable T { 0x123 }", strict).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "1 error(s) while parsing: Code table contents must be whole bytes, found 3 hex digits"
+    );
+    assert!(
+        parse_str_with_options(&format!("#define codetable T {{ 0x{odd_long} }}"), strict).is_err()
+    );
+    assert!(parse_str_with_options("#define codetable T { 0x1234 }", strict).is_ok());
+}
+
+#[test]
+fn array_dimensions_round_trip_in_declaration_order() {
+    use chumsky_huff::utils::abi::FunctionParamType;
+
+    for ty in [
+        "uint256[][2]",
+        "uint256[2][]",
+        "address[3][4][]",
+        "bytes32[]",
+    ] {
+        let ast = parse_str(&format!("#define function f({ty} a) view returns ()")).unwrap();
+        let Ast::AbiFunction(function) = &ast[0].0 else {
+            panic!("expected a function, found {:?}", ast[0].0);
+        };
+        let kind = &function.inputs[0].0.kind;
+        assert_eq!(kind.to_string(), ty);
+        assert_eq!(
+            &FunctionParamType::convert_string_to_type(ty).unwrap(),
+            kind
+        );
+    }
+
+    // A size that doesn't fit is an error rather than a wrapped dimension
+    assert!(
+        parse_str("#define function f(uint256[99999999999999999999999] a) view returns ()")
+            .is_err()
+    );
+}
+
+#[test]
+fn contradictory_mutability_is_an_error() {
+    assert_eq!(
+        parse_error_messages("#define function f() payable view returns (uint256)"),
+        ["A function cannot be both `payable` and `view`"]
+    );
+    assert_eq!(
+        parse_error_messages("#define function f() view view"),
+        ["`view` is repeated"]
+    );
+    assert!(parse_str("#define function f() nonpayable returns (uint256)").is_ok());
+
+    // The error doesn't stop the following definitions from parsing
+    let diagnostics = chumsky_huff::diagnostics(
+        "#define function f() payable view returns (uint256)
+#define macro M() = takes(0) returns(0) { add }",
+    );
+    let errors: Vec<_> = diagnostics.iter().filter(|d| d.is_error()).collect();
+    assert_eq!(errors.len(), 1, "{diagnostics:?}");
+
+    let expected = chumsky_huff::expected_at("#define function f() ", 21);
+    for keyword in ["view", "pure", "payable", "nonpayable"] {
+        assert!(expected.iter().any(|e| e == keyword), "{expected:?}");
+    }
+}
+
+#[test]
+fn decorator_flags_capture_their_payloads() {
+    use chumsky_huff::{
+        lowering::lower,
+        utils::ast::{Decorator, DecoratorFlag},
+    };
+
+    let ast = parse_str(
+        "#[calldata(\"0x1234\"), value(0x10)]
+#define macro T() = takes(0) returns(0) { add }",
+    )
+    .unwrap();
+    let Ast::MacroDefinition { decorator, .. } = &ast[0].0 else {
+        panic!("expected a macro, found {:?}", ast[0].0);
+    };
+    let mut value = [0u8; 32];
+    value[31] = 0x10;
+    let expected = Decorator {
+        flags: vec![
+            DecoratorFlag::Calldata("0x1234".to_string()),
+            DecoratorFlag::Value(value),
+        ],
+    };
+    assert_eq!(decorator.as_ref(), Some(&expected));
+    let (contract, _) = lower(&ast);
+    assert_eq!(
+        contract.find_macro_by_name("T").unwrap().decorator,
+        Some(expected)
+    );
+
+    for (src, message) in [
+        ("#[value(\"0x10\")]", "The `value` flag takes a literal"),
+        ("#[calldata(0x10)]", "The `calldata` flag takes a string"),
+        ("#[gas(0x10)]", "Unknown decorator flag `gas`"),
+    ] {
+        let src = format!("{src}\n#define macro T() = takes(0) returns(0) {{}}");
+        assert_eq!(parse_error_messages(&src), [message], "{src}");
+    }
+    assert_eq!(
+        parse_error_messages("#[value(0x10)]\n#define constant C = 0x01"),
+        ["Decorators can only be placed on macros"]
+    );
+}
+
+#[test]
+fn includes_parse_an_optional_macro_list() {
+    let ast = parse_str("#include \"lib.huff\" { A, B }\n#include \"x.huff\"").unwrap();
+    assert_eq!(
+        ast[0].0,
+        Ast::FileInclude {
+            path: "lib.huff".to_string(),
+            only: Some(vec!["A".to_string(), "B".to_string()]),
+        }
+    );
+    assert_eq!(
+        ast[1].0,
+        Ast::FileInclude {
+            path: "x.huff".to_string(),
+            only: None,
+        }
+    );
+}
+
+#[test]
+fn locations_are_labels_outside_of_abi_declarations() {
+    let ast = parse_str(
+        "#define function f(bytes memory data) view returns ()
+#[calldata(\"0x01\")]
+#define macro MAIN() = takes(0) returns(0) { memory jump memory: }",
+    )
+    .unwrap();
+    let Ast::AbiFunction(function) = &ast[0].0 else {
+        panic!("expected a function, found {:?}", ast[0].0);
+    };
+    assert!(function.inputs[0].0.location.is_some());
+
+    let statements = macro_body(&ast, "MAIN");
+    assert_eq!(statements[0], MacroBody::JumpLabel("memory".to_string()));
+    assert_eq!(
+        statements[2],
+        MacroBody::JumpLabelDest("memory".to_string())
+    );
+}
+
+#[test]
+fn stack_annotations_parse_in_either_order() {
+    fn annotations(annotations: &str) -> (usize, usize) {
+        let ast = parse_str(&format!("#define macro M() = {annotations} {{ }}")).unwrap();
+        let Ast::MacroDefinition { takes, returns, .. } = &ast[0].0 else {
+            panic!("expected a macro, found {:?}", ast[0].0);
+        };
+        (takes.0, returns.0)
+    }
+
+    assert_eq!(annotations("takes(1) returns(2)"), (1, 2));
+    assert_eq!(annotations("returns(2) takes(1)"), (1, 2));
+    assert_eq!(annotations("returns(2)"), (0, 2));
+    assert_eq!(annotations("takes(1)"), (1, 0));
+    assert_eq!(annotations(""), (0, 0));
+    assert!(parse_str("#define macro M() = takes(1) takes(1) { }").is_err());
+}
+
+#[test]
+fn references_are_not_macro_parameters() {
+    assert_eq!(
+        parse_error_messages("#define macro FOO([C], <a>) = takes(0) returns(0) {}"),
+        ["Macro parameters must be names, found `[C]`, `<a>`"]
+    );
+    assert_eq!(
+        parse_error_messages("#define macro FOO(a, [C]) = takes(0) returns(0) {}"),
+        ["Macro parameters must be names, found `[C]`"]
+    );
+}
+
+#[test]
+fn full_width_literals_parse_in_macro_bodies() {
+    let max = "f".repeat(64);
+    let ast = parse_str(&format!(
+        "#define macro MAIN() = takes(0) returns(1) {{ push32 0x{max} }}"
+    ))
+    .unwrap();
+    assert_eq!(
+        macro_body(&ast, "MAIN")[1],
+        MacroBody::HexLiteral([0xff; 32])
+    );
+
+    // One digit wider no longer fits a word
+    assert!(parse_str(&format!(
+        "#define macro MAIN() = takes(0) returns(1) {{ 0x1{max} }}"
+    ))
+    .is_err());
+}
+
+#[test]
+fn sizeless_push_accepts_decimal_numbers() {
+    let max = "115792089237316195423570985008687907853269984665640564039457584007913129639935";
+    let src = format!("#define macro MAIN() = takes(0) returns(2) {{ push 32 push {max} }}");
+    let ast = parse_str(&src).unwrap();
+    let mut literal = [0u8; 32];
+    literal[31] = 32;
+    assert_eq!(
+        macro_body(&ast, "MAIN"),
+        [
+            MacroBody::AutoPush(literal),
+            MacroBody::AutoPush([0xff; 32])
+        ]
+    );
+    // Not a jump label named `push`, so nothing is reported
+    assert!(
+        chumsky_huff::diagnostics(&src).is_empty(),
+        "{:?}",
+        chumsky_huff::diagnostics(&src)
+    );
+}
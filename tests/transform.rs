@@ -0,0 +1,131 @@
+use chumsky_huff::{
+    lowering::lower,
+    parse_str,
+    parser::{Ast, ConstantValue, MacroBody},
+    transform::{fold_constants, inline_constants},
+    utils::{
+        ast::{MacroArg, Statement, StatementType},
+        bytes_util::str_to_bytes32,
+        opcodes::Opcode,
+    },
+};
+
+#[test]
+fn folded_constants_are_seen_by_their_references() {
+    let src = "#define constant E = [D]
+#define constant C = 0x1 + 0x2
+#define constant D = [C]
+#define constant F = FREE_STORAGE_POINTER()
+#define constant G = [F]
+#define macro MAIN() = takes(0) returns(2) { [E] [F] }";
+    let mut ast = parse_str(src).unwrap();
+    let spans: Vec<_> = ast.iter().map(|(_, span)| span.clone()).collect();
+    fold_constants(&mut ast);
+
+    let three = str_to_bytes32("03").unwrap();
+    let values: Vec<_> = ast
+        .iter()
+        .filter_map(|(item, _)| match item {
+            Ast::ConstantDefinition { value, .. } => Some(value.clone()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        values,
+        [
+            ConstantValue::Literal(three),
+            ConstantValue::Literal(three),
+            ConstantValue::Literal(three),
+            ConstantValue::FreeStoragePointer,
+            ConstantValue::ConstantRef(("F".to_string(), 147..148)),
+        ]
+    );
+
+    let Ast::MacroDefinition { statements, .. } = &ast[5].0 else {
+        panic!("expected a macro, found {:?}", ast[5].0);
+    };
+    let statements: Vec<_> = statements.iter().map(|(s, _)| s.clone()).collect();
+    assert_eq!(
+        statements,
+        [
+            MacroBody::HexLiteral(three),
+            MacroBody::ConstantRef(("F".to_string(), 200..201))
+        ]
+    );
+
+    // Definitions are rewritten in place
+    let folded: Vec<_> = ast.iter().map(|(_, span)| span.clone()).collect();
+    assert_eq!(folded, spans);
+}
+
+#[test]
+fn inlined_constants_push_their_value() {
+    let src = "#define constant C = 0x0102
+#define constant P = FREE_STORAGE_POINTER()
+#define macro TAKE(a) = takes(0) returns(1) { <a> }
+#define macro MAIN() = takes(0) returns(0) {
+    [C] TAKE([C])
+    lbl:
+        [P] TAKE([P])
+}";
+    let (mut contract, diagnostics) = lower(&parse_str(src).unwrap());
+    assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    let diagnostics = inline_constants(&mut contract);
+
+    let value = str_to_bytes32("0102").unwrap();
+    let main = contract.find_macro_by_name("MAIN").unwrap();
+    let span = src.find("[C]").unwrap()..src.find("[C]").unwrap() + 3;
+    assert_eq!(
+        main.statements[..2],
+        [
+            Statement {
+                ty: StatementType::Opcode(Opcode::Push2),
+                span: span.clone(),
+            },
+            Statement {
+                ty: StatementType::Literal(value),
+                span,
+            },
+        ]
+    );
+    let StatementType::MacroInvocation(invocation) = &main.statements[2].ty else {
+        panic!("expected an invocation, found {:?}", main.statements[2]);
+    };
+    assert_eq!(invocation.args, [MacroArg::Literal(value)]);
+    assert_eq!(contract.invocations[0].args, [MacroArg::Literal(value)]);
+
+    // Free storage pointers are left for compilation
+    let rest: Vec<_> = main.statements[3..].iter().map(|s| s.ty.clone()).collect();
+    assert_eq!(rest.len(), 3);
+    assert!(matches!(&rest[0], StatementType::Label(label) if label.name == "lbl"));
+    assert_eq!(rest[1], StatementType::Constant("P".to_string()));
+    let StatementType::MacroInvocation(invocation) = &rest[2] else {
+        panic!("expected an invocation, found {:?}", rest[2]);
+    };
+    assert_eq!(invocation.args, [MacroArg::ConstantRef("P".to_string())]);
+
+    let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        ["`P` is a free storage pointer, which is not resolved until compilation"; 2]
+    );
+    assert!(diagnostics.iter().all(|d| !d.is_error()));
+}
+
+#[test]
+fn undefined_constants_are_not_inlined() {
+    let src = "#define macro MAIN() = takes(0) returns(0) { [X] }";
+    let (mut contract, _) = lower(&parse_str(src).unwrap());
+    let diagnostics = inline_constants(&mut contract);
+    assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+    assert!(diagnostics[0].is_error());
+    assert_eq!(
+        diagnostics[0].message,
+        "reference to undefined constant `[X]`"
+    );
+    assert_eq!(&src[diagnostics[0].span.clone()], "[X]");
+    assert_eq!(
+        contract.macros[0].statements[0].ty,
+        StatementType::Constant("X".to_string())
+    );
+}
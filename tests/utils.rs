@@ -0,0 +1,264 @@
+use chumsky_huff::utils::{
+    bytes_util::{
+        literal_to_hex, str_to_bytes32, str_to_bytes32_radix, wrapping_div_bytes32, LiteralError,
+    },
+    interner::Interner,
+};
+
+#[test]
+fn literal_to_hex_minimal_and_padded() {
+    let one = str_to_bytes32("01").unwrap();
+    assert_eq!(literal_to_hex(&one, true, true), "0x1");
+    assert_eq!(
+        literal_to_hex(&one, false, true),
+        format!("0x{}1", "0".repeat(63))
+    );
+    assert_eq!(literal_to_hex(&one, false, false).len(), 64);
+
+    let zero = str_to_bytes32("00").unwrap();
+    assert_eq!(literal_to_hex(&zero, true, true), "0x0");
+    assert_eq!(literal_to_hex(&zero, true, false), "0");
+}
+
+#[test]
+fn interning_duplicates_returns_the_same_symbol() {
+    let mut interner = Interner::new();
+    let main = interner.intern("MAIN");
+    let owner = interner.intern("OWNER");
+
+    assert_eq!(interner.intern("MAIN"), main);
+    assert_ne!(main, owner);
+    assert_eq!(interner.len(), 2);
+    assert_eq!(interner.resolve(main), "MAIN");
+    assert_eq!(interner.get("OWNER"), Some(owner));
+    assert_eq!(interner.get("MISSING"), None);
+}
+
+#[test]
+fn str_to_bytes32_reports_overflow() {
+    assert_eq!(str_to_bytes32(&"f".repeat(64)).unwrap(), [0xff; 32]);
+    assert_eq!(str_to_bytes32(&"f".repeat(65)), Err(LiteralError::Overflow));
+    // Leading zeros do not count towards the width
+    assert_eq!(
+        str_to_bytes32(&format!("0{}", "f".repeat(64))).unwrap(),
+        [0xff; 32]
+    );
+    assert_eq!(str_to_bytes32("a57b").unwrap()[30..], [0xa5, 0x7b]);
+
+    let max = "115792089237316195423570985008687907853269984665640564039457584007913129639935";
+    assert_eq!(str_to_bytes32_radix(max, 10).unwrap(), [0xff; 32]);
+    let over = "115792089237316195423570985008687907853269984665640564039457584007913129639936";
+    assert_eq!(str_to_bytes32_radix(over, 10), Err(LiteralError::Overflow));
+
+    assert_eq!(str_to_bytes32_radix("101", 2).unwrap()[31], 5);
+    assert_eq!(
+        str_to_bytes32_radix("12", 2),
+        Err(LiteralError::InvalidDigit('2'))
+    );
+}
+
+#[test]
+fn opcodes_resolve_case_insensitively() {
+    use chumsky::Parser;
+    use chumsky_huff::{
+        lexer::{lexer, token::Token},
+        utils::opcodes::{opcode_from_str_ci, Opcode},
+    };
+
+    assert_eq!(opcode_from_str_ci("ADD"), Some(Opcode::Add));
+    assert_eq!(opcode_from_str_ci("add"), Some(Opcode::Add));
+    assert_eq!(opcode_from_str_ci("Push32"), Some(Opcode::Push32));
+    assert_eq!(opcode_from_str_ci("nope"), None);
+
+    // The lexer still only accepts lowercase mnemonics
+    let tokens = lexer().parse("ADD add").unwrap();
+    assert_eq!(tokens[0].0, Token::Ident("ADD".to_string()));
+    assert_eq!(tokens[1].0, Token::Opcode(Opcode::Add));
+}
+
+#[test]
+fn division_handles_full_width_words() {
+    let max = [0xff; 32];
+    let mut two = [0u8; 32];
+    two[31] = 2;
+    let mut expected = [0xff; 32];
+    expected[0] = 0x7f;
+    assert_eq!(wrapping_div_bytes32(&max, &two), expected);
+    assert_eq!(
+        literal_to_hex(&wrapping_div_bytes32(&max, &max), true, true),
+        "0x1"
+    );
+    assert_eq!(wrapping_div_bytes32(&max, &[0u8; 32]), [0u8; 32]);
+}
+
+#[test]
+fn opcodes_know_the_fork_that_introduced_them() {
+    use chumsky_huff::utils::opcodes::{HardFork, Opcode};
+
+    assert_eq!(Opcode::Add.introduced_in(), HardFork::Frontier);
+    assert_eq!(Opcode::Delegatecall.introduced_in(), HardFork::Homestead);
+    assert_eq!(Opcode::Revert.introduced_in(), HardFork::Byzantium);
+    assert_eq!(Opcode::Shl.introduced_in(), HardFork::Constantinople);
+    assert_eq!(Opcode::Chainid.introduced_in(), HardFork::Istanbul);
+    assert_eq!(Opcode::Basefee.introduced_in(), HardFork::London);
+    assert_eq!(Opcode::Prevrandao.introduced_in(), HardFork::Paris);
+    assert_eq!(Opcode::TLoad.introduced_in(), HardFork::Cancun);
+    assert!(HardFork::Istanbul < HardFork::Cancun);
+    assert_eq!(HardFork::Cancun.to_string(), "Cancun");
+}
+
+#[test]
+fn json_abi_carries_event_flags() {
+    use chumsky_huff::{parse_str, utils::json_abi::json_abi};
+
+    let abi = json_abi(
+        &parse_str(
+            "#define event Transfer(address indexed from, uint256 value)
+#define event Anon(uint256) anonymous
+#define function f((uint256,address)[] xs) view returns (bool)
+#define constructor(address)",
+        )
+        .unwrap(),
+    );
+    assert_eq!(abi[0]["anonymous"], false);
+    assert_eq!(abi[0]["inputs"][0]["indexed"], true);
+    assert_eq!(abi[0]["inputs"][0]["name"], "from");
+    assert_eq!(abi[0]["inputs"][1]["indexed"], false);
+    assert_eq!(abi[0]["inputs"][1]["type"], "uint256");
+    assert_eq!(abi[1]["anonymous"], true);
+    assert_eq!(abi[2]["inputs"][0]["type"], "tuple[]");
+    assert_eq!(abi[2]["inputs"][0]["components"][1]["type"], "address");
+    assert_eq!(abi[2]["stateMutability"], "view");
+    assert_eq!(abi[2]["outputs"][0]["type"], "bool");
+    assert_eq!(abi[3]["type"], "constructor");
+}
+
+#[test]
+fn contract_builder_validates_names() {
+    use chumsky_huff::utils::ast::{
+        Argument, ConstVal, ConstantDefinition, ContractBuilder, Function, FunctionType,
+        MacroDefinition,
+    };
+
+    let main = MacroDefinition::new("MAIN".into(), None, vec![], vec![], 0, 0, false, false);
+    let one = str_to_bytes32("01").unwrap();
+    let contract = ContractBuilder::new()
+        .add_macro(main.clone())
+        .add_constant(ConstantDefinition {
+            name: "ONE".into(),
+            value: ConstVal::Literal(one),
+        })
+        .add_function(Function {
+            name: "burn".into(),
+            signature: [0; 4],
+            inputs: vec![Argument {
+                arg_type: Some("uint256".into()),
+                ..Default::default()
+            }],
+            fn_type: FunctionType::NonPayable,
+            outputs: vec![],
+        })
+        .build()
+        .unwrap();
+    assert_eq!(contract.macros.len(), 1);
+    assert_eq!(contract.macros[0], main);
+    assert_eq!(contract.constants.lock().unwrap()[0].name, "ONE");
+    // The selector is computed from the signature rather than trusted
+    assert_eq!(contract.functions[0].signature, [0x42, 0x96, 0x6c, 0x68]);
+
+    let err = ContractBuilder::new()
+        .add_macro(main.clone())
+        .add_macro(main)
+        .add_constant(ConstantDefinition {
+            name: "add".into(),
+            value: ConstVal::Literal(one),
+        })
+        .add_constant(ConstantDefinition {
+            name: "1x".into(),
+            value: ConstVal::Literal(one),
+        })
+        .build()
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid constant name `add`, invalid constant name `1x`, duplicate macro `MAIN`"
+    );
+}
+
+#[test]
+fn primitive_types_display_as_they_parse() {
+    use chumsky::Parser;
+    use chumsky_huff::{
+        lexer::{lexer, token::Token},
+        utils::{abi::FunctionParamType, types::PrimitiveEVMType},
+    };
+
+    let mut primitives = vec![
+        PrimitiveEVMType::String,
+        PrimitiveEVMType::DynBytes,
+        PrimitiveEVMType::Bool,
+        PrimitiveEVMType::Address,
+    ];
+    for size in (8..=256).step_by(8) {
+        primitives.push(PrimitiveEVMType::Uint(size));
+        primitives.push(PrimitiveEVMType::Int(size));
+    }
+    primitives.extend((1..=32).map(PrimitiveEVMType::Bytes));
+
+    for primitive in primitives {
+        let text = primitive.to_string();
+        assert_eq!(PrimitiveEVMType::try_from(text.clone()).unwrap(), primitive);
+        let param = FunctionParamType::convert_string_to_type(&text).unwrap();
+        assert_eq!(param, FunctionParamType::from(primitive));
+        assert_eq!(param.to_string(), text);
+        let tokens = lexer().parse(text.as_str()).unwrap();
+        assert_eq!(tokens[0].0, Token::PrimitiveType(primitive), "{text}");
+    }
+}
+
+#[test]
+fn keccak256_matches_known_vectors() {
+    use chumsky_huff::utils::bytes_util::{hash_bytes, keccak256};
+
+    assert_eq!(
+        literal_to_hex(&keccak256(b""), false, false),
+        "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+    );
+    assert_eq!(
+        keccak256(b"transfer(address,uint256)")[..4],
+        [0xa9, 0x05, 0x9c, 0xbb]
+    );
+
+    let mut selector = [0u8; 4];
+    hash_bytes(&mut selector, &"transfer(address,uint256)".to_string());
+    assert_eq!(selector, [0xa9, 0x05, 0x9c, 0xbb]);
+}
+
+#[test]
+fn literal_bit_lengths_ignore_leading_zeros() {
+    use chumsky_huff::utils::bytes_util::{literal_bit_length, literals_equal};
+
+    let literal = |s: &str| str_to_bytes32(s).unwrap();
+    assert_eq!(literal_bit_length(&literal("00")), 0);
+    assert_eq!(literal_bit_length(&literal("ff")), 8);
+    assert_eq!(literal_bit_length(&literal("0100")), 9);
+    assert_eq!(literal_bit_length(&literal("1")), 1);
+    assert_eq!(literal_bit_length(&[0xff; 32]), 256);
+    assert!(literals_equal(&literal("1"), &literal("0001")));
+    assert!(!literals_equal(&literal("1"), &literal("10")));
+}
+
+#[test]
+fn minimal_push_trims_leading_zero_bytes() {
+    use chumsky_huff::utils::{bytes_util::minimal_push, opcodes::Opcode};
+
+    // There is no push0 yet, so zero is a single zero byte
+    assert_eq!(minimal_push(&[0u8; 32]), (Opcode::Push1, vec![0]));
+    let mut literal = [0u8; 32];
+    literal[31] = 0xff;
+    assert_eq!(minimal_push(&literal), (Opcode::Push1, vec![0xff]));
+    literal[30] = 0x01;
+    assert_eq!(minimal_push(&literal), (Opcode::Push2, vec![0x01, 0xff]));
+    let full = [0xab; 32];
+    assert_eq!(minimal_push(&full), (Opcode::Push32, full.to_vec()));
+}